@@ -1,3 +1,110 @@
+use std::path::Path;
+
+/// Where the benchmark clip is written, bundled as a resource by `tauri.conf.json`
+const BENCHMARK_CLIP: &str = "resources/benchmark.wav";
+const SAMPLE_RATE: u32 = 16000;
+const CLIP_SECONDS: u32 = 30;
+
+/// First three formants of a few vowels in Hz
+const VOWELS: [[f32; 3]; 5] = [[730.0, 1090.0, 2440.0], [530.0, 1840.0, 2480.0], [270.0, 2290.0, 3010.0], [570.0, 840.0, 2410.0], [300.0, 870.0, 2240.0]];
+
+/// Voice-like clip, syllables of a pitched pulse train through vowel formants with pauses between phrases,
+/// generated the same on every machine so benchmarks compare without shipping a recording
+fn benchmark_clip() -> Vec<i16> {
+    let rate = SAMPLE_RATE as f32;
+    let syllable = (rate * 0.22) as usize;
+    let mut samples = vec![0.0f32; (SAMPLE_RATE * CLIP_SECONDS) as usize];
+
+    // Fixed seed, every build makes the same clip
+    let mut seed = 0x2545f491u32;
+    let mut random = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed as f32 / u32::MAX as f32
+    };
+
+    let mut position = (rate * 0.5) as usize;
+
+    while position + syllable * 8 < samples.len() {
+        let syllables = 3 + (random() * 5.0) as usize;
+
+        for _ in 0..syllables {
+            let formants = VOWELS[(random() * VOWELS.len() as f32) as usize % VOWELS.len()];
+            let pitch = 110.0 + random() * 40.0;
+            let mut state = [[0.0f32; 2]; 3];
+            let mut phase = 0.0;
+
+            for offset in 0..syllable {
+                phase += pitch / rate;
+
+                let mut pulse = (random() - 0.5) * 0.02;
+
+                if phase >= 1.0 {
+                    phase -= 1.0;
+                    pulse += 1.0;
+                }
+
+                let mut output = 0.0;
+
+                for (formant, state) in formants.iter().zip(&mut state) {
+                    let radius = (-std::f32::consts::PI * 80.0 / rate).exp();
+                    let theta = 2.0 * std::f32::consts::PI * formant / rate;
+                    let value = pulse + 2.0 * radius * theta.cos() * state[0] - radius * radius * state[1];
+
+                    *state = [value, state[0]];
+                    output += value;
+                }
+
+                let envelope = (std::f32::consts::PI * offset as f32 / syllable as f32).sin();
+                samples[position + offset] += output * envelope;
+            }
+
+            position += syllable + (rate * 0.03) as usize;
+        }
+
+        position += (rate * (0.3 + random() * 0.5)) as usize;
+    }
+
+    let peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs())).max(f32::EPSILON);
+
+    samples.iter().map(|sample| (sample / peak * 0.8 * i16::MAX as f32) as i16).collect()
+}
+
+fn write_wav(path: &Path, samples: &[i16]) -> std::io::Result<()> {
+    let data_size = samples.len() as u32 * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, mono
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(path, wav)
+}
+
 fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={BENCHMARK_CLIP}");
+
+    // Tauri checks that every resource exists, only written when missing so it doesn't trigger another build
+    if !Path::new(BENCHMARK_CLIP).exists() {
+        write_wav(Path::new(BENCHMARK_CLIP), &benchmark_clip()).expect("Failed writing the benchmark clip");
+    }
+
     tauri_build::build()
 }
@@ -0,0 +1,141 @@
+//! Pin the SHA-256 of every download in `models.json`, run after adding or updating a model:
+//! `cargo run --example pin_models`
+//!
+//! Every file is downloaded and hashed, what the server advertises isn't trusted.
+//! Entries that already have a digest are left alone, the offline translation pairs below are pinned
+//! to the current commit of their repository.
+
+use std::{collections::BTreeMap, io::Read as _, path::Path};
+
+use anyhow::Context as _;
+
+use serde::Serialize;
+
+use sha2::{Digest as _, Sha256};
+
+/// Language pairs of the offline translator with their repository,
+/// Opus-MT names some languages by other codes, e.g. `jap` for Japanese
+const TRANSLATION_PAIRS: [(&str, &str, &str); 14] = [
+    ("en", "de", "Xenova/opus-mt-en-de"),
+    ("de", "en", "Xenova/opus-mt-de-en"),
+    ("en", "fr", "Xenova/opus-mt-en-fr"),
+    ("fr", "en", "Xenova/opus-mt-fr-en"),
+    ("en", "es", "Xenova/opus-mt-en-es"),
+    ("es", "en", "Xenova/opus-mt-es-en"),
+    ("en", "it", "Xenova/opus-mt-en-it"),
+    ("it", "en", "Xenova/opus-mt-it-en"),
+    ("en", "nl", "Xenova/opus-mt-en-nl"),
+    ("nl", "en", "Xenova/opus-mt-nl-en"),
+    ("en", "zh", "Xenova/opus-mt-en-zh"),
+    ("zh", "en", "Xenova/opus-mt-zh-en"),
+    ("en", "ja", "Xenova/opus-mt-en-jap"),
+    ("ja", "en", "Xenova/opus-mt-ja-en"),
+];
+
+/// Same as `translation::TRANSLATION_MODEL_FILES`
+const TRANSLATION_MODEL_FILES: [&str; 4] = ["config.json", "tokenizer.json", "onnx/encoder_model.onnx", "onnx/decoder_model.onnx"];
+
+#[derive(Serialize)]
+struct TranslationModelInfo {
+    source: String,
+    target: String,
+    repo: String,
+    revision: String,
+    sha256: BTreeMap<String, String>,
+}
+
+fn sha256(client: &reqwest::blocking::Client, url: &str) -> anyhow::Result<String> {
+    println!("Hashing {url}");
+
+    let mut response = client.get(url).send()?.error_for_status().with_context(|| format!("Can't download {url}"))?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1 << 20];
+
+    loop {
+        let read = response.read(&mut buffer)?;
+
+        if read == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Add `"sha256"` right after the `"url"` line of `url`, keeping the rest of the file as it's written
+fn insert_sha256(manifest: &mut String, url: &str, sha256: &str) -> anyhow::Result<()> {
+    let url_line = format!("\"url\": {}", serde_json::to_string(url)?);
+
+    let start = manifest.find(&url_line).with_context(|| format!("{url} isn't in the manifest"))?;
+    let line_start = manifest[..start].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = manifest[start..].find('\n').map_or(manifest.len(), |index| start + index);
+
+    let indent = manifest[line_start..start].to_owned();
+
+    if manifest[..line_end].ends_with(',') {
+        manifest.insert_str(line_end, &format!("\n{indent}\"sha256\": \"{sha256}\","));
+    } else {
+        manifest.insert_str(line_end, &format!(",\n{indent}\"sha256\": \"{sha256}\""));
+    }
+
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("models.json");
+    let mut manifest = std::fs::read_to_string(&path)?;
+    let parsed: serde_json::Value = serde_json::from_str(&manifest)?;
+
+    let client = reqwest::blocking::Client::builder().timeout(None).build()?;
+    let base_url = parsed["base_url"].as_str().context("The manifest has no base_url")?.trim_end_matches('/').to_owned();
+
+    let mut files: Vec<(String, String)> = Vec::new();
+
+    for model in parsed["models"].as_array().context("The manifest has no models")? {
+        let (Some(url), None) = (model["url"].as_str(), model["sha256"].as_str()) else { continue };
+
+        let download_url = if url.starts_with("http") { url.to_owned() } else { format!("{base_url}/{url}") };
+        files.push((url.to_owned(), download_url));
+    }
+
+    if let (Some(url), None) = (parsed["speaker_model"]["url"].as_str(), parsed["speaker_model"]["sha256"].as_str()) {
+        files.push((url.to_owned(), url.to_owned()));
+    }
+
+    for (url, download_url) in files {
+        insert_sha256(&mut manifest, &url, &sha256(&client, &download_url)?)?;
+        // Written as it goes, a large download failing keeps what's pinned so far
+        std::fs::write(&path, &manifest)?;
+    }
+
+    if parsed["translation_models"].as_array().is_some_and(Vec::is_empty) {
+        let mut translation_models = Vec::new();
+
+        for (source, target, repo) in TRANSLATION_PAIRS {
+            let info: serde_json::Value = client.get(format!("{base_url}/api/models/{repo}/revision/main"))
+                .send()?
+                .error_for_status()
+                .with_context(|| format!("{repo} isn't on the hub"))?
+                .json()?;
+
+            let revision = info["sha"].as_str().with_context(|| format!("{repo} has no commit"))?.to_owned();
+
+            let sha256 = TRANSLATION_MODEL_FILES.iter()
+                .map(|file| anyhow::Ok(((*file).to_owned(), sha256(&client, &format!("{base_url}/{repo}/resolve/{revision}/{file}?download=true"))?)))
+                .collect::<anyhow::Result<_>>()?;
+
+            translation_models.push(TranslationModelInfo { source: source.to_owned(), target: target.to_owned(), repo: repo.to_owned(), revision, sha256 });
+        }
+
+        let pretty = serde_json::to_string_pretty(&translation_models)?.replace('\n', "\n  ");
+        manifest = manifest.replacen("\"translation_models\": []", &format!("\"translation_models\": {pretty}"), 1);
+        std::fs::write(&path, &manifest)?;
+    }
+
+    println!("Pinned every download in {}", path.display());
+
+    Ok(())
+}
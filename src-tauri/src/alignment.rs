@@ -0,0 +1,251 @@
+use crate::{timestamp::Timestamp, transcript::{Segment, Word}};
+
+/// Words compare without case and punctuation
+fn normalize(word: &str) -> String {
+    word.chars().filter(|char| char.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Cost of a word only in the script or only heard
+const GAP_COST: u32 = 2;
+/// Cost of lining up different words, below two gaps but above one so an extra word isn't taken for a misheard one
+const MISHEARD_COST: u32 = 3;
+
+/// Share of the script that must be heard word for word, below it the script is likely for another recording
+const MIN_HEARD_RATIO: f32 = 0.5;
+/// Words either side of the even pace through the recording an alignment may stray, bounds memory on long recordings
+const BAND: usize = 200;
+/// Cost of cells outside the band, low enough to add to without overflowing
+const UNREACHABLE: u32 = u32::MAX / 2;
+
+fn substitution_cost(script_word: &str, heard_word: &str) -> u32 {
+    if !script_word.is_empty() && script_word == heard_word { 0 } else { MISHEARD_COST }
+}
+
+/// Costs of lining up the script up to each row with the heard words, only kept near the diagonal
+struct Band {
+    /// First heard index in each row
+    starts: Vec<usize>,
+    rows: Vec<Vec<u32>>,
+}
+
+impl Band {
+    fn get(&self, row: usize, column: usize) -> u32 {
+        column.checked_sub(self.starts[row])
+            .and_then(|offset| self.rows[row].get(offset))
+            .copied()
+            .unwrap_or(UNREACHABLE)
+    }
+}
+
+/// Index of the heard word every script word was heard as, by the fewest words added, dropped or misheard,
+/// misheard words aren't matched
+fn match_words(script: &[&str], heard: &[Word]) -> Vec<Option<usize>> {
+    let script_words: Vec<String> = script.iter().map(|word| normalize(word)).collect();
+    let heard_words: Vec<String> = heard.iter().map(|word| normalize(&word.text)).collect();
+
+    let (script_len, heard_len) = (script_words.len(), heard_words.len());
+    let pace = |row: usize| row * heard_len / script_len.max(1);
+
+    let mut band = Band { starts: Vec::with_capacity(script_len + 1), rows: Vec::with_capacity(script_len + 1) };
+
+    for row in 0..=script_len {
+        // Starting from the previous row's pace keeps the band connected however fast the pace is
+        let start = pace(row.saturating_sub(1)).saturating_sub(BAND);
+        let end = (pace(row) + BAND).min(heard_len);
+
+        let mut costs = Vec::with_capacity(end - start + 1);
+
+        for column in start..=end {
+            let cost = if row == 0 {
+                column as u32 * GAP_COST
+            } else if column == 0 {
+                row as u32 * GAP_COST
+            } else {
+                let substitution = substitution_cost(&script_words[row - 1], &heard_words[column - 1]);
+                let left = costs.last().copied().unwrap_or(UNREACHABLE);
+
+                (band.get(row - 1, column - 1) + substitution)
+                    .min(band.get(row - 1, column) + GAP_COST)
+                    .min(left + GAP_COST)
+            };
+
+            costs.push(cost);
+        }
+
+        band.starts.push(start);
+        band.rows.push(costs);
+    }
+
+    let mut matches = vec![None; script_len];
+    let (mut row, mut column) = (script_len, heard_len);
+
+    while row > 0 && column > 0 {
+        let cost = band.get(row, column);
+        let substitution = substitution_cost(&script_words[row - 1], &heard_words[column - 1]);
+
+        if cost == band.get(row - 1, column - 1) + substitution {
+            if substitution == 0 {
+                matches[row - 1] = Some(column - 1);
+            }
+
+            row -= 1;
+            column -= 1;
+        } else if cost == band.get(row - 1, column) + GAP_COST {
+            row -= 1;
+        } else {
+            column -= 1;
+        }
+    }
+
+    matches
+}
+
+/// Time every script word, words whisper didn't hear share the time between their neighbours by length
+fn time_words(script: &[&str], heard: &[Word]) -> anyhow::Result<Vec<(Timestamp, Timestamp)>> {
+    let matches = match_words(script, heard);
+
+    let anchors: Vec<(usize, Timestamp, Timestamp)> = matches.iter()
+        .enumerate()
+        .filter_map(|(index, heard_index)| heard_index.map(|heard_index| (index, heard[heard_index].start, heard[heard_index].end)))
+        .collect();
+
+    anyhow::ensure!(!anchors.is_empty(), "None of the script was heard in the recording");
+    anyhow::ensure!(
+        anchors.len() as f32 >= script.len() as f32 * MIN_HEARD_RATIO,
+        "Only {} of {} script words were heard in the recording, is it the right script?", anchors.len(), script.len()
+    );
+
+    let mut times = vec![None; script.len()];
+
+    for (index, start, end) in &anchors {
+        times[*index] = Some((*start, *end));
+    }
+
+    let mut index = 0;
+
+    while index < script.len() {
+        if times[index].is_some() {
+            index += 1;
+            continue;
+        }
+
+        let run_end = (index..script.len()).find(|index| times[*index].is_some()).unwrap_or(script.len());
+
+        let from = index.checked_sub(1).and_then(|previous| times[previous]).map_or(anchors[0].1, |(_, end)| end);
+        let to = times.get(run_end).copied().flatten().map_or(anchors[anchors.len() - 1].2, |(start, _)| start).max(from);
+
+        let lengths: Vec<i64> = script[index..run_end].iter().map(|word| word.chars().count().max(1) as i64).collect();
+        let total: i64 = lengths.iter().sum();
+        let mut elapsed = 0;
+
+        for (offset, length) in lengths.iter().enumerate() {
+            let start = from.lerp(to, elapsed, total);
+            elapsed += length;
+
+            times[index + offset] = Some((start, from.lerp(to, elapsed, total)));
+        }
+
+        index = run_end;
+    }
+
+    Ok(times.into_iter().map(Option::unwrap).collect())
+}
+
+/// A cue for every sentence of `script`, timed by the words whisper heard, lines of the script always end a sentence
+pub fn align(script: &str, heard: &[Word]) -> anyhow::Result<Vec<Segment>> {
+    let sentences: Vec<Vec<&str>> = script.lines().flat_map(crate::shaping::sentences).collect();
+    let words: Vec<&str> = sentences.iter().flatten().copied().collect();
+
+    anyhow::ensure!(!words.is_empty(), "The script is empty");
+
+    let times = time_words(&words, heard)?;
+
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut first = 0;
+
+    for sentence in sentences {
+        let last = first + sentence.len() - 1;
+
+        let previous_end = segments.last().map(|segment| segment.end).unwrap_or_default();
+        let start = times[first].0.max(previous_end);
+        let end = times[last].1.max(start);
+
+        segments.push(Segment { start, end, text: sentence.join(" "), speaker: None, words: Vec::new() });
+
+        first = last + 1;
+    }
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::fixtures::{heard, timings};
+
+    #[test]
+    fn times_sentences_by_their_words() {
+        let script = "Open the settings. Then pick a model!\nDone";
+        let heard = heard(&[("open", 1000, 1200), ("the", 1200, 1300), ("settings", 1300, 1900), ("then", 2500, 2700), ("pick", 2700, 2900), ("a", 2900, 3000), ("model", 3000, 3400), ("done", 4000, 4300)]);
+
+        let segments = align(script, &heard).unwrap();
+
+        assert_eq!(segments.iter().map(|segment| segment.text.as_str()).collect::<Vec<_>>(), ["Open the settings.", "Then pick a model!", "Done"]);
+        assert_eq!(timings(&segments), [(1000, 1900), (2500, 3400), (4000, 4300)]);
+    }
+
+    #[test]
+    fn keeps_the_script_wording_over_what_was_heard() {
+        let script = "Record your screen with Recordscript today.";
+        // "your" wasn't heard, "Recordscript" was misheard and "um" wasn't in the script
+        let heard = heard(&[("record", 0, 400), ("screen", 1000, 1500), ("um", 1500, 1800), ("with", 1800, 2000), ("records", 2000, 2600), ("crypt", 2600, 2900), ("today", 3000, 3500)]);
+
+        let segments = align(script, &heard).unwrap();
+
+        assert_eq!(segments[0].text, script);
+        assert_eq!(timings(&segments), [(0, 3500)]);
+
+        let words: Vec<&str> = script.split_whitespace().collect();
+        let times = time_words(&words, &heard).unwrap();
+
+        // Between "record" and "screen"
+        assert_eq!(times[1], (Timestamp::from_millis(400), Timestamp::from_millis(1000)));
+        assert_eq!(times[2], (Timestamp::from_millis(1000), Timestamp::from_millis(1500)));
+        // Misheard words aren't anchors, so between "with" and "today"
+        assert_eq!(times[4], (Timestamp::from_millis(2000), Timestamp::from_millis(3000)));
+    }
+
+    #[test]
+    fn refuses_scripts_that_were_not_heard() {
+        assert!(align("Hello there", &[]).is_err());
+        assert!(align(" \n ", &heard(&[("hello", 0, 100)])).is_err());
+
+        // One word in common by chance
+        let err = align("Welcome to the quarterly report", &heard(&[("today", 0, 300), ("the", 300, 400), ("weather", 400, 900), ("is", 900, 1000), ("sunny", 1000, 1500)])).unwrap_err();
+        assert!(err.to_string().contains("Only 1 of 5"), "{err}");
+    }
+
+    #[test]
+    fn aligns_long_recordings() {
+        let script: Vec<String> = (0..50_000).map(|index| format!("word{index}")).collect();
+        // The reader skipped a stretch and added filler words, far from the even pace on either side
+        let heard: Vec<Word> = script.iter()
+            .enumerate()
+            .filter(|(index, _)| !(10_000..10_150).contains(index))
+            .flat_map(|(index, word)| {
+                let filler = (index % 1000 == 0).then(|| ("um".to_owned(), index));
+                std::iter::once((word.clone(), index)).chain(filler)
+            })
+            .map(|(text, index)| Word { text, start: Timestamp::from_millis(index as i64 * 100), end: Timestamp::from_millis(index as i64 * 100 + 80), confidence: 1.0 })
+            .collect();
+
+        let script: Vec<&str> = script.iter().map(String::as_str).collect();
+        let times = time_words(&script, &heard).unwrap();
+
+        assert_eq!(times[9_999], (Timestamp::from_millis(999_900), Timestamp::from_millis(999_980)));
+        assert_eq!(times[30_000], (Timestamp::from_millis(3_000_000), Timestamp::from_millis(3_000_080)));
+        // Skipped words share the time between their neighbours
+        assert_eq!(times[10_000].0, Timestamp::from_millis(999_980));
+        assert_eq!(times[10_149].1, Timestamp::from_millis(1_015_000));
+    }
+}
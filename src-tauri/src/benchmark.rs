@@ -0,0 +1,159 @@
+use std::{path::Path, sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc}, time::{Duration, Instant}};
+
+use serde::{Deserialize, Serialize};
+
+use whisper_rs::{WhisperContext, WhisperContextParameters};
+
+use crate::transcriber::Model;
+
+/// Real-time factor a recommended model has to reach, 1.0 transcribes as fast as the audio plays
+pub const DEFAULT_TARGET_REAL_TIME_FACTOR: f32 = 0.5;
+
+/// Clip every machine benchmarks on, generated by `build.rs` and bundled as a resource so results compare across machines
+pub const CLIP: &str = "resources/benchmark.wav";
+
+/// How often memory usage is sampled while a model runs
+const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Sample rate of the PCM produced by [`crate::transcriber::decode_audio`]
+const SAMPLE_RATE: f32 = 16000.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    /// Id of the benchmarked model
+    pub model: String,
+    /// Transcription time divided by the clip duration, lower is faster
+    pub real_time_factor: f32,
+    /// Seconds spent loading the model
+    pub load_time: f32,
+    /// Highest resident memory above what the app used before loading the model, in bytes
+    pub peak_memory: u64,
+    /// Seconds of audio transcribed
+    pub clip_duration: f32,
+}
+
+fn resident_memory() -> u64 {
+    use sysinfo::System;
+
+    let Ok(pid) = sysinfo::get_current_pid() else { return 0 };
+
+    let mut system = System::new();
+    system.refresh_process(pid);
+
+    system.process(pid).map_or(0, |process| process.memory())
+}
+
+/// Transcribe `pcm` with `model`, like `test_vpx` in `libs/scrap/examples/benchmark.rs` does for codecs
+fn test_model(model: &Model, pcm: &[f32]) -> anyhow::Result<BenchmarkResult> {
+    let baseline = resident_memory();
+    let peak = Arc::new(AtomicU64::new(baseline));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let sampler = std::thread::spawn({
+        let peak = peak.clone();
+        let done = done.clone();
+
+        move || {
+            while !done.load(Ordering::Relaxed) {
+                peak.fetch_max(resident_memory(), Ordering::Relaxed);
+                std::thread::sleep(MEMORY_SAMPLE_INTERVAL);
+            }
+        }
+    });
+
+    let result = (|| {
+        let start = Instant::now();
+        let whisper_context = WhisperContext::new_with_params(&model.path().to_string_lossy(), WhisperContextParameters::default())?;
+        let load_time = start.elapsed();
+
+        let mut state = whisper_context.create_state()?;
+
+        let settings = crate::configuration::TranscriptionConfig::default().settings_for(model);
+        let language = if model.whitelisted_lang().is_some_and(|languages| languages.iter().all(|language| language == "en")) { "en" } else { "auto" };
+        let params = crate::transcriber::full_params(&settings, model, language);
+
+        let start = Instant::now();
+        state.full(params, pcm)?;
+        let transcription_time = start.elapsed();
+
+        anyhow::Ok((load_time, transcription_time))
+    })();
+
+    done.store(true, Ordering::Relaxed);
+    let _ = sampler.join();
+
+    let (load_time, transcription_time) = result?;
+    let clip_duration = pcm.len() as f32 / SAMPLE_RATE;
+
+    let result = BenchmarkResult {
+        model: model.id().to_owned(),
+        real_time_factor: transcription_time.as_secs_f32() / clip_duration,
+        load_time: load_time.as_secs_f32(),
+        peak_memory: peak.load(Ordering::Relaxed).saturating_sub(baseline),
+        clip_duration,
+    };
+
+    println!(
+        "{}: real-time factor {:.3}, load {:.2}s, peak memory {} MB",
+        result.model,
+        result.real_time_factor,
+        result.load_time,
+        result.peak_memory / 1000000,
+    );
+
+    Ok(result)
+}
+
+/// Benchmark every downloaded model on `clip`, `on_progress` receives the model about to run with its index
+pub fn run(clip: &Path, mut on_progress: impl FnMut(&Model, usize, usize)) -> anyhow::Result<Vec<BenchmarkResult>> {
+    let pcm = crate::transcriber::decode_audio(std::fs::read(clip)?.into(), None)?;
+
+    anyhow::ensure!(!pcm.is_empty(), "Benchmark clip has no audio");
+
+    let models: Vec<_> = Model::all().into_iter().filter(Model::is_downloaded).collect();
+
+    anyhow::ensure!(!models.is_empty(), "Download a model before benchmarking");
+
+    println!("Benchmarking {} models on {:.1}s of audio", models.len(), pcm.len() as f32 / SAMPLE_RATE);
+
+    let mut results = Vec::new();
+
+    for (index, model) in models.iter().enumerate() {
+        on_progress(model, index, models.len());
+
+        match test_model(model, &pcm) {
+            Ok(result) => results.push(result),
+            Err(err) => eprintln!("Failed benchmarking {}: {err:#}", model.id()),
+        }
+    }
+
+    Ok(results)
+}
+
+/// Largest model, the most accurate, that still transcribes within `target_real_time_factor`
+pub fn recommend(results: &[BenchmarkResult], target_real_time_factor: f32) -> Option<Model> {
+    results.iter()
+        .filter(|result| result.real_time_factor <= target_real_time_factor)
+        .filter_map(|result| Some((Model::find(&result.model).filter(Model::is_downloaded)?, result)))
+        .max_by(|(a, a_result), (b, b_result)| {
+            a.average_memory_usage().cmp(&b.average_memory_usage())
+                .then(b_result.real_time_factor.total_cmp(&a_result.real_time_factor))
+        })
+        .map(|(model, _)| model)
+}
+
+/// Seconds `model` needs to transcribe `duration` seconds of audio, from its benchmark or, when it wasn't benchmarked,
+/// from the benchmarked model closest in size scaled by their size, `None` when no model was benchmarked
+pub fn estimate_time(results: &[BenchmarkResult], model: &Model, duration: f32) -> Option<f32> {
+    if let Some(result) = results.iter().find(|result| result.model == model.id()) {
+        return Some(result.load_time + result.real_time_factor * duration);
+    }
+
+    let (other, result) = results.iter()
+        .filter_map(|result| Some((Model::find(&result.model)?, result)))
+        .min_by_key(|(other, _)| other.disk_usage().abs_diff(model.disk_usage()))?;
+
+    let scale = model.disk_usage() as f32 / other.disk_usage().max(1) as f32;
+
+    Some((result.load_time + result.real_time_factor * duration) * scale)
+}
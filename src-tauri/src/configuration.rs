@@ -1,4 +1,4 @@
-use std::{path::PathBuf, time::Duration};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
@@ -90,6 +90,563 @@ impl Default for GeneralConfig {
     }
 }
 
+/// Kept apart from [`GeneralConfig`] so settings saved before it existed still load
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordConfig {
+    pub mode: crate::recorder::RecordMode,
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            mode: crate::recorder::RecordMode::Screen,
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Find and replace rule applied to transcribed text before it's saved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplacementRule {
+    pub find: String,
+    pub replace: String,
+    /// `find` is a regular expression and `replace` can refer to its groups like `$1`
+    pub regex: bool,
+    pub case_sensitive: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VocabularyProfile {
+    pub name: String,
+    /// Terms whisper should recognize and spell this way, passed as its initial prompt
+    pub glossary: Vec<String>,
+    pub replacements: Vec<ReplacementRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VocabularyConfig {
+    /// Name of the profile used when transcribing
+    pub active_profile: String,
+    pub profiles: Vec<VocabularyProfile>,
+}
+
+impl Default for VocabularyConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            active_profile: "Default".to_owned(),
+            profiles: vec![VocabularyProfile {
+                name: "Default".to_owned(),
+                glossary: Vec::new(),
+                replacements: Vec::new(),
+            }],
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+impl VocabularyConfig {
+    pub fn active(&self) -> Option<&VocabularyProfile> {
+        self.profiles.iter().find(|profile| profile.name == self.active_profile)
+    }
+}
+
+/// Names written in front of the lines attributed to each side of a recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeakerLabelConfig {
+    pub me: String,
+    pub others: String,
+}
+
+impl Default for SpeakerLabelConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            me: "Me".to_owned(),
+            others: "Others".to_owned(),
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+impl SpeakerLabelConfig {
+    pub fn label(&self, speaker: crate::speaker::Speaker) -> &str {
+        match speaker {
+            crate::speaker::Speaker::Me => &self.me,
+            crate::speaker::Speaker::Others => &self.others,
+        }
+    }
+}
+
+/// Speaker clustering applied after transcription
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiarizationConfig {
+    pub enabled: bool,
+    /// Most speakers to tell apart, 0 lets the similarity threshold decide
+    pub max_speakers: usize,
+    /// Cosine similarity above which two voices are considered the same speaker
+    pub similarity_threshold: f32,
+}
+
+impl Default for DiarizationConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            enabled: false,
+            max_speakers: 0,
+            similarity_threshold: crate::diarization::DEFAULT_SIMILARITY_THRESHOLD,
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Languages every transcription is translated into, each saved as `name.<language>.srt`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationConfig {
+    pub backend: crate::translation::TranslationBackend,
+    /// ISO 639-1 codes, e.g. `de`
+    pub target_languages: Vec<String>,
+}
+
+impl Default for TranslationConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            backend: crate::translation::TranslationBackend::Local,
+            target_languages: Vec::new(),
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Limits of a cue showing the original line above its translation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BilingualConfig {
+    /// Also save `name.<source>-<target>.<format>` for every translated language
+    pub enabled: bool,
+    pub format: crate::subtitle::SubtitleFormat,
+    /// Characters per line, longer lines are wrapped
+    pub max_line_length: usize,
+    /// Lines per cue for both languages together, longer cues are split in time
+    pub max_lines: usize,
+}
+
+impl Default for BilingualConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            enabled: false,
+            format: crate::subtitle::SubtitleFormat::Srt,
+            max_line_length: 42,
+            max_lines: 4,
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Limits every subtitle cue is shaped to, whisper segments are often far longer than a readable cue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CueRulesConfig {
+    /// Off by default so subtitles keep matching whisper's segments until asked otherwise
+    pub enabled: bool,
+    /// Characters per line, speaker labels included
+    pub max_line_length: usize,
+    pub max_lines: usize,
+    /// Reading speed, cues too short to read are extended when there's room before the next one
+    pub max_chars_per_second: f32,
+    /// Milliseconds
+    pub min_duration: i64,
+    /// Milliseconds
+    pub max_duration: i64,
+    /// Milliseconds between the end of a cue and the start of the next
+    pub min_gap: i64,
+}
+
+impl Default for CueRulesConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            enabled: false,
+            max_line_length: 42,
+            max_lines: 2,
+            max_chars_per_second: 17.0,
+            min_duration: 1000,
+            max_duration: 7000,
+            min_gap: 80,
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Proofreading of words whisper wasn't sure about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewConfig {
+    /// Save `name.review.txt` and hold back emails until doubtful words are proofread
+    pub enabled: bool,
+    /// Words less likely than this, from 0 to 1, are doubtful
+    pub threshold: f32,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            enabled: false,
+            threshold: 0.5,
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Personal data taken out of transcripts before they're saved or emailed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    pub enabled: bool,
+    pub emails: bool,
+    pub phone_numbers: bool,
+    /// Numbers passing the Luhn checksum
+    pub card_numbers: bool,
+    /// Regular expressions of anything else to redact, e.g. customer IDs
+    pub custom_patterns: Vec<String>,
+    /// Also save `name.redacted.m4a` with the spoken data bleeped or silenced
+    pub audio: crate::redaction::AudioRedaction,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            enabled: false,
+            emails: true,
+            phone_numbers: true,
+            card_numbers: true,
+            custom_patterns: Vec::new(),
+            audio: crate::redaction::AudioRedaction::Keep,
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Folder checked for new media to transcribe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchedFolder {
+    pub path: PathBuf,
+    /// Globs like `*.mp4` matched against file names, every file when empty
+    pub patterns: Vec<String>,
+    /// Also look in folders inside, e.g. one per meeting
+    pub recursive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WatchOutput {
+    NextToFile,
+    /// In [`GeneralConfig::transcript_save_to`]
+    TranscriptSaveTo,
+}
+
+/// Folders where other apps drop recordings, their new files are transcribed once they stop growing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchFolderConfig {
+    pub enabled: bool,
+    pub folders: Vec<WatchedFolder>,
+    /// How long a file keeps its size before it's taken as finished
+    pub settle_seconds: u64,
+    pub output: WatchOutput,
+    /// Email transcripts like the ones of our own recordings
+    pub email: bool,
+}
+
+impl Default for WatchFolderConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            enabled: false,
+            folders: Vec::new(),
+            settle_seconds: 10,
+            output: WatchOutput::NextToFile,
+            email: false,
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Size and modification time of a file, a file that changes is transcribed again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Seconds since the Unix epoch
+    pub modified: u64,
+}
+
+/// Files of the watch folders that were already transcribed, so they aren't after a restart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchFolderState {
+    pub transcribed: HashMap<PathBuf, FileStamp>,
+}
+
+impl Default for WatchFolderState {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            transcribed: HashMap::new(),
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Devices selected for recording, `None` uses the system default
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    pub microphone: Option<crate::recorder::DeviceId>,
+    pub speaker: Option<crate::recorder::DeviceId>,
+    pub screen: Option<crate::recorder::DeviceId>,
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            microphone: None,
+            speaker: None,
+            screen: None,
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Metadata supplied when importing a model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedModel {
+    /// File name in the model directory
+    pub id: String,
+    pub name: String,
+    pub languages: Option<Vec<String>>,
+    /// Estimated memory usage in MB
+    pub memory_usage: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedModelConfig {
+    pub models: Vec<ImportedModel>,
+}
+
+impl Default for ImportedModelConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            models: Vec::new(),
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DecodingStrategy {
+    /// Keep the best of `best_of` sampled candidates
+    Greedy { best_of: i32 },
+    /// Keep `beam_size` hypotheses while decoding, slower but usually more accurate
+    BeamSearch { beam_size: i32 },
+}
+
+/// Whisper decoding parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionSettings {
+    pub strategy: DecodingStrategy,
+    /// Sampling temperature of the first attempt
+    pub temperature: f32,
+    /// Temperature added on each retry of a segment that fails the thresholds, 0 disables the fallback
+    pub temperature_increment: f32,
+    /// 0 uses every available core, up to 8
+    pub threads: usize,
+    /// Segments more likely than this to be silent are skipped
+    pub no_speech_threshold: f32,
+    /// Segments with an average token log probability below this are retried at a higher temperature
+    pub log_prob_threshold: f32,
+    /// Maximum segment length in characters, 0 doesn't limit it
+    pub max_segment_length: usize,
+    pub suppress_blank: bool,
+    pub suppress_non_speech: bool,
+    /// Mark speaker turns, only applied with models that support it
+    pub speaker_turns: bool,
+}
+
+impl Default for TranscriptionSettings {
+    /// whisper.cpp defaults
+    fn default() -> Self {
+        Self {
+            strategy: DecodingStrategy::Greedy { best_of: 5 },
+            temperature: 0.0,
+            temperature_increment: 0.2,
+            threads: 0,
+            no_speech_threshold: 0.6,
+            log_prob_threshold: -1.0,
+            max_segment_length: 0,
+            suppress_blank: true,
+            suppress_non_speech: false,
+            speaker_turns: false,
+        }
+    }
+}
+
+/// Transcription settings the user changed, by model id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptionConfig {
+    pub models: HashMap<String, TranscriptionSettings>,
+}
+
+impl Default for TranscriptionConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            models: HashMap::new(),
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+impl TranscriptionConfig {
+    /// Saved settings of `model`, or its defaults
+    pub fn settings_for(&self, model: &crate::transcriber::Model) -> TranscriptionSettings {
+        self.models.get(model.id()).cloned().unwrap_or_else(|| model.default_settings())
+    }
+}
+
+/// Silence handling with [`crate::vad`], apart from [`TranscriptionSettings`] so settings saved before it existed still load.
+/// Off by default so transcripts keep whisper's timing until asked otherwise
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VadConfig {
+    /// Only pass the detected speech to whisper, skipping silence
+    pub voice_activity_detection: bool,
+    /// Drop segments that overlap no detected speech, they're usually hallucinated
+    pub drop_non_speech_segments: bool,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            voice_activity_detection: false,
+            drop_non_speech_segments: false,
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
+/// Results of the last model benchmark
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkConfig {
+    pub results: Vec<crate::benchmark::BenchmarkResult>,
+    pub target_real_time_factor: f32,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        if let Ok(this) = load::<Self>() {
+            return this;
+        }
+
+        let this = Self {
+            results: Vec::new(),
+            target_real_time_factor: crate::benchmark::DEFAULT_TARGET_REAL_TIME_FACTOR,
+        };
+
+        save(&this);
+
+        this
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SMTPConfig {
     pub host: String,
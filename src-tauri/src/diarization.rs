@@ -0,0 +1,394 @@
+use std::{ops::Range, path::PathBuf, sync::Arc};
+
+use anyhow::Context as _;
+
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+use crate::vad::SAMPLE_RATE;
+
+/// WeSpeaker ResNet34 trained on VoxCeleb, as exported to ONNX by sherpa-onnx, downloaded from the model manifest's `speaker_model`
+const EMBEDDING_MODEL_FILE: &str = "wespeaker_en_voxceleb_resnet34_LM.onnx";
+
+/// Size of the embedding model in bytes, for the download progress until the server reports it
+pub const EMBEDDING_MODEL_SIZE: u64 = 26_500_000;
+
+/// Windows of the same speaker are usually more similar than this
+pub const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.5;
+
+/// Kaldi filterbank the embedding model was trained on, 25ms frames every 10ms
+const FRAME_LENGTH: usize = SAMPLE_RATE * 25 / 1000;
+const FRAME_SHIFT: usize = SAMPLE_RATE * 10 / 1000;
+const FFT_LENGTH: usize = 512;
+const MEL_BINS: usize = 80;
+const LOW_FREQUENCY: f32 = 20.0;
+const PREEMPHASIS: f32 = 0.97;
+
+/// Speech regions are embedded in windows this long, overlapping by half
+const WINDOW: usize = SAMPLE_RATE * 2;
+const WINDOW_STEP: usize = WINDOW / 2;
+
+/// Shorter regions don't hold enough voice to tell speakers apart
+const MIN_WINDOW: usize = SAMPLE_RATE / 2;
+
+/// Kept apart from the whisper models so it isn't cleaned up as a leftover file
+fn model_dir() -> PathBuf {
+    crate::project_directory().cache_dir().join("diarization")
+}
+
+/// Where the model is downloaded to, its directory is created
+pub fn model_path() -> anyhow::Result<PathBuf> {
+    let dir = model_dir();
+    std::fs::create_dir_all(&dir).with_context(|| format!("Can't create {}", dir.display()))?;
+
+    Ok(dir.join(EMBEDDING_MODEL_FILE))
+}
+
+pub fn is_downloaded() -> bool {
+    model_dir().join(EMBEDDING_MODEL_FILE).exists()
+}
+
+fn mel(frequency: f32) -> f32 {
+    1127.0 * (1.0 + frequency / 700.0).ln()
+}
+
+/// Triangular filters evenly spaced on the mel scale, each weighting every power spectrum bin
+fn mel_filters() -> Vec<Vec<f32>> {
+    let low = mel(LOW_FREQUENCY);
+    let high = mel(SAMPLE_RATE as f32 / 2.0);
+    let step = (high - low) / (MEL_BINS + 1) as f32;
+
+    (0..MEL_BINS)
+        .map(|bin| {
+            let (left, center, right) = (low + bin as f32 * step, low + (bin + 1) as f32 * step, low + (bin + 2) as f32 * step);
+
+            (0..=FFT_LENGTH / 2)
+                .map(|index| {
+                    let frequency = mel(index as f32 * SAMPLE_RATE as f32 / FFT_LENGTH as f32);
+
+                    if frequency <= left || frequency >= right {
+                        0.0
+                    } else if frequency <= center {
+                        (frequency - left) / (center - left)
+                    } else {
+                        (right - frequency) / (right - center)
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Log mel energies of every frame of `pcm`, `MEL_BINS` values per frame
+fn log_mel_frames(pcm: &[f32], filters: &[Vec<f32>], fft: &dyn Fft<f32>) -> Vec<f32> {
+    let window: Vec<f32> = (0..FRAME_LENGTH)
+        .map(|index| (0.5 - 0.5 * (std::f32::consts::TAU * index as f32 / (FRAME_LENGTH - 1) as f32).cos()).powf(0.85))
+        .collect();
+
+    let frames = if pcm.len() < FRAME_LENGTH { 0 } else { 1 + (pcm.len() - FRAME_LENGTH) / FRAME_SHIFT };
+
+    let mut features = Vec::with_capacity(frames * MEL_BINS);
+    let mut spectrum = vec![Complex::default(); FFT_LENGTH];
+
+    for frame in 0..frames {
+        // The model expects 16-bit sample values
+        let mut samples: Vec<f32> = pcm[frame * FRAME_SHIFT..][..FRAME_LENGTH].iter().map(|sample| sample * 32768.0).collect();
+
+        let mean = samples.iter().sum::<f32>() / FRAME_LENGTH as f32;
+        samples.iter_mut().for_each(|sample| *sample -= mean);
+
+        for index in (1..FRAME_LENGTH).rev() {
+            samples[index] -= PREEMPHASIS * samples[index - 1];
+        }
+        samples[0] -= PREEMPHASIS * samples[0];
+
+        spectrum.fill(Complex::default());
+
+        for (bin, (sample, weight)) in spectrum.iter_mut().zip(samples.iter().zip(&window)) {
+            bin.re = sample * weight;
+        }
+
+        fft.process(&mut spectrum);
+
+        let power: Vec<f32> = spectrum[..=FFT_LENGTH / 2].iter().map(|bin| bin.norm_sqr()).collect();
+
+        features.extend(filters.iter().map(|filter| {
+            let energy = filter.iter().zip(&power).map(|(weight, power)| weight * power).sum::<f32>();
+
+            energy.max(f32::EPSILON).ln()
+        }));
+    }
+
+    features
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt().max(f32::EPSILON);
+
+    vector.iter_mut().for_each(|value| *value /= norm);
+}
+
+/// Speaker embedding model, maps a speech window to a vector that's close for the same voice
+pub struct Embedder {
+    session: ort::session::Session,
+    filters: Vec<Vec<f32>>,
+    fft: Arc<dyn Fft<f32>>,
+}
+
+impl Embedder {
+    pub fn new() -> anyhow::Result<Self> {
+        let session = ort::session::Session::builder()?
+            .commit_from_file(model_path()?)
+            .context("Failed loading the speaker model, try downloading it again")?;
+
+        Ok(Self {
+            session,
+            filters: mel_filters(),
+            fft: FftPlanner::new().plan_fft_forward(FFT_LENGTH),
+        })
+    }
+
+    /// Unit length embedding of the voice in `pcm`
+    pub fn embed(&self, pcm: &[f32]) -> anyhow::Result<Vec<f32>> {
+        let mut features = log_mel_frames(pcm, &self.filters, self.fft.as_ref());
+        let frames = features.len() / MEL_BINS;
+
+        anyhow::ensure!(frames > 0, "Speech window is too short");
+
+        // Mean normalization over the window, like the model was trained with
+        for bin in 0..MEL_BINS {
+            let mean = features.iter().skip(bin).step_by(MEL_BINS).sum::<f32>() / frames as f32;
+            features.iter_mut().skip(bin).step_by(MEL_BINS).for_each(|value| *value -= mean);
+        }
+
+        let input = ort::value::Tensor::from_array(([1, frames, MEL_BINS], features))?;
+        let outputs = self.session.run(ort::inputs![input]?)?;
+
+        let (_, embedding) = outputs[0].try_extract_raw_tensor::<f32>()?;
+        let mut embedding = embedding.to_vec();
+
+        normalize(&mut embedding);
+
+        Ok(embedding)
+    }
+}
+
+/// Windows every speech region is embedded in, regions too short to recognize a voice are skipped
+fn windows(regions: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut windows = Vec::new();
+
+    for region in regions.iter().filter(|region| region.len() >= MIN_WINDOW) {
+        let mut start = region.start;
+
+        loop {
+            // The last window ends with the region instead of being cut short
+            let start_at = start.min(region.end.saturating_sub(WINDOW).max(region.start));
+            let end = (start_at + WINDOW).min(region.end);
+
+            windows.push(start_at..end);
+
+            if end == region.end { break };
+
+            start += WINDOW_STEP;
+        }
+    }
+
+    windows
+}
+
+/// Agglomerative clustering with average linkage over the cosine similarity of unit length `embeddings`
+///
+/// Clusters are merged until none are more similar than `threshold`, and further while there are more than
+/// `max_speakers` if it isn't 0. Returns the cluster of every embedding.
+pub fn cluster(embeddings: &[Vec<f32>], threshold: f32, max_speakers: usize) -> Vec<usize> {
+    let count = embeddings.len();
+
+    let mut similarity: Vec<Vec<f32>> = embeddings.iter()
+        .map(|a| embeddings.iter().map(|b| a.iter().zip(b).map(|(a, b)| a * b).sum()).collect())
+        .collect();
+
+    let mut members: Vec<Vec<usize>> = (0..count).map(|index| vec![index]).collect();
+    let mut active: Vec<usize> = (0..count).collect();
+
+    while active.len() > 1 {
+        let mut best = (f32::NEG_INFINITY, 0, 0);
+
+        for (position, &a) in active.iter().enumerate() {
+            for &b in &active[position + 1..] {
+                if similarity[a][b] > best.0 {
+                    best = (similarity[a][b], a, b);
+                }
+            }
+        }
+
+        let (score, a, b) = best;
+        let too_many = max_speakers != 0 && active.len() > max_speakers;
+
+        if score < threshold && !too_many { break };
+
+        // Lance-Williams update, the merged cluster's similarity is the size weighted average
+        let (size_a, size_b) = (members[a].len() as f32, members[b].len() as f32);
+
+        for &c in active.iter().filter(|&&c| c != a && c != b) {
+            let merged = (size_a * similarity[a][c] + size_b * similarity[b][c]) / (size_a + size_b);
+
+            similarity[a][c] = merged;
+            similarity[c][a] = merged;
+        }
+
+        let merged = std::mem::take(&mut members[b]);
+        members[a].extend(merged);
+        active.retain(|&c| c != b);
+    }
+
+    let mut labels = vec![0; count];
+
+    for (label, &cluster) in active.iter().enumerate() {
+        for &member in &members[cluster] {
+            labels[member] = label;
+        }
+    }
+
+    labels
+}
+
+/// Speaker of every embedded window, speakers are numbered in the order they first talk
+#[derive(Debug, Clone, Default)]
+pub struct Diarization {
+    windows: Vec<(Range<usize>, usize)>,
+}
+
+impl Diarization {
+    fn new(windows: Vec<Range<usize>>, labels: Vec<usize>) -> Self {
+        let mut order: Vec<usize> = Vec::new();
+
+        let windows = windows.into_iter()
+            .zip(labels)
+            .map(|(window, label)| {
+                let speaker = order.iter().position(|&known| known == label).unwrap_or_else(|| {
+                    order.push(label);
+                    order.len() - 1
+                });
+
+                (window, speaker)
+            })
+            .collect();
+
+        Self { windows }
+    }
+
+    /// Number of speakers told apart
+    pub fn speakers(&self) -> usize {
+        self.windows.iter().map(|(_, speaker)| speaker + 1).max().unwrap_or(0)
+    }
+
+    /// Speaker talking the most between the whisper timestamps `start` and `end`, named `SPEAKER_1`…
+    pub fn speaker_at(&self, start: i64, end: i64) -> Option<String> {
+        let start = start.max(0) as usize * SAMPLE_RATE / 100;
+        let end = end.max(0) as usize * SAMPLE_RATE / 100;
+
+        let mut overlaps = vec![0; self.speakers()];
+
+        for (window, speaker) in &self.windows {
+            overlaps[*speaker] += end.min(window.end).saturating_sub(start.max(window.start));
+        }
+
+        let (speaker, overlap) = overlaps.into_iter().enumerate().max_by_key(|(_, overlap)| *overlap)?;
+
+        (overlap > 0).then(|| format!("SPEAKER_{}", speaker + 1))
+    }
+}
+
+/// Tell the speakers of the speech `regions` of `pcm` apart
+pub fn diarize(pcm: &[f32], regions: &[Range<usize>], config: &crate::configuration::DiarizationConfig) -> anyhow::Result<Diarization> {
+    let embedder = Embedder::new()?;
+
+    let windows = windows(regions);
+
+    let embeddings = windows.iter()
+        .map(|window| embedder.embed(&pcm[window.clone()]))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let labels = cluster(&embeddings, config.similarity_threshold, config.max_speakers);
+    let diarization = Diarization::new(windows, labels);
+
+    println!("Found {} speakers in {} speech windows", diarization.speakers(), embeddings.len());
+
+    Ok(diarization)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit vector along `axis` nudged towards the other axes
+    fn voice(axis: usize, nudge: f32) -> Vec<f32> {
+        let mut embedding: Vec<f32> = (0..8).map(|index| if index == axis { 1.0 } else { nudge * ((index * 7 + axis) % 5) as f32 }).collect();
+        normalize(&mut embedding);
+        embedding
+    }
+
+    #[test]
+    fn clusters_similar_voices() {
+        let embeddings = [voice(0, 0.1), voice(3, 0.1), voice(0, 0.2), voice(5, 0.1), voice(3, 0.15), voice(0, 0.05)];
+
+        assert_eq!(cluster(&embeddings, DEFAULT_SIMILARITY_THRESHOLD, 0), [0, 1, 0, 2, 1, 0]);
+
+        let labels = cluster(&embeddings, DEFAULT_SIMILARITY_THRESHOLD, 2);
+        assert_eq!(labels.iter().max(), Some(&1));
+        assert_eq!(labels[0], labels[2]);
+        assert_eq!(labels[1], labels[4]);
+
+        assert!(cluster(&[], DEFAULT_SIMILARITY_THRESHOLD, 0).is_empty());
+    }
+
+    #[test]
+    fn names_speakers_by_first_turn() {
+        let second = SAMPLE_RATE;
+        let diarization = Diarization::new(vec![0..2 * second, 2 * second..4 * second, 5 * second..7 * second], vec![4, 1, 4]);
+
+        assert_eq!(diarization.speakers(), 2);
+        assert_eq!(diarization.speaker_at(0, 150).as_deref(), Some("SPEAKER_1"));
+        assert_eq!(diarization.speaker_at(150, 350).as_deref(), Some("SPEAKER_2"));
+        assert_eq!(diarization.speaker_at(500, 700).as_deref(), Some("SPEAKER_1"));
+        assert_eq!(diarization.speaker_at(420, 480), None);
+    }
+
+    #[test]
+    fn windows_cover_regions() {
+        let second = SAMPLE_RATE;
+        let windows = windows(&[0..second / 4, second..second * 7 / 2, 5 * second..6 * second]);
+
+        assert_eq!(windows, [second..3 * second, 3 * second / 2..7 * second / 2, 5 * second..6 * second]);
+    }
+
+    #[test]
+    fn filterbank_peaks_at_tone() {
+        let tone: Vec<f32> = (0..SAMPLE_RATE).map(|index| 0.3 * (std::f32::consts::TAU * 1000.0 * index as f32 / SAMPLE_RATE as f32).sin()).collect();
+
+        let filters = mel_filters();
+        let features = log_mel_frames(&tone, &filters, FftPlanner::new().plan_fft_forward(FFT_LENGTH).as_ref());
+
+        assert_eq!(features.len(), MEL_BINS * (1 + (SAMPLE_RATE - FRAME_LENGTH) / FRAME_SHIFT));
+
+        let loudest = (0..MEL_BINS).max_by(|a, b| features[*a].total_cmp(&features[*b])).unwrap();
+        let (left, right) = (mel(1000.0) - mel(LOW_FREQUENCY), mel(SAMPLE_RATE as f32 / 2.0) - mel(LOW_FREQUENCY));
+
+        // 1kHz sits at this fraction of the mel range
+        assert!(((loudest + 1) as f32 / (MEL_BINS + 1) as f32 - left / right).abs() < 0.02);
+    }
+
+    #[test]
+    #[ignore = "needs the speaker model"]
+    fn diarizes_multi_speaker_clip() {
+        gst::init().unwrap();
+
+        let clip = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/two-speakers.ogg")).unwrap();
+
+        let pcm = crate::transcriber::decode_audio(clip.into(), None).unwrap();
+        let regions = crate::vad::detect_speech(&pcm);
+
+        let config = crate::configuration::DiarizationConfig { enabled: true, max_speakers: 0, similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD };
+
+        assert_eq!(diarize(&pcm, &regions, &config).unwrap().speakers(), 2);
+    }
+}
@@ -0,0 +1,256 @@
+use std::{io::Read as _, path::{Path, PathBuf}, sync::Mutex};
+
+use anyhow::Context as _;
+
+use reqwest::{header, StatusCode};
+
+use sha2::{Digest as _, Sha256};
+
+use tokio::io::AsyncWriteExt as _;
+
+/// File that holds the bytes downloaded so far, renamed to `destination` once verified
+pub fn partial_path(destination: &Path) -> PathBuf {
+    let mut file_name = destination.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".part");
+
+    destination.with_file_name(file_name)
+}
+
+/// Partial files being written by a running [`download`] or import
+static ACTIVE_DOWNLOADS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Marks a partial file as being written until it's dropped, so it isn't cleaned up meanwhile
+pub struct ActiveDownload(PathBuf);
+
+impl ActiveDownload {
+    pub fn start(partial: &Path) -> anyhow::Result<Self> {
+        let mut active_downloads = ACTIVE_DOWNLOADS.lock().unwrap();
+
+        anyhow::ensure!(!active_downloads.iter().any(|active| active == partial), "{} is already being downloaded", partial.display());
+
+        active_downloads.push(partial.to_path_buf());
+
+        Ok(Self(partial.to_path_buf()))
+    }
+}
+
+impl Drop for ActiveDownload {
+    fn drop(&mut self) {
+        ACTIVE_DOWNLOADS.lock().unwrap().retain(|active| *active != self.0);
+    }
+}
+
+/// Whether a running download or import is writing the partial file `partial`
+pub fn is_downloading(partial: &Path) -> bool {
+    ACTIVE_DOWNLOADS.lock().unwrap().iter().any(|active| active == partial)
+}
+
+/// SHA-256 of a file as lowercase hex
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+
+    loop {
+        let length = file.read(&mut buffer)?;
+
+        if length == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..length]);
+    }
+
+    Ok(hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Whether `digest` looks like a SHA-256 in hex
+pub fn is_sha256(digest: &str) -> bool {
+    digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Download `url` into `destination`
+///
+/// Bytes go to [`partial_path`] first so an interrupted download is resumed with a `Range` request next time,
+/// the file is only moved into place after its SHA-256 matches `expected_sha256`, whatever the server claims about it.
+/// `on_progress` receives the downloaded and total size in bytes.
+pub async fn download(url: &str, destination: &Path, expected_sha256: &str, mut on_progress: impl FnMut(u64, Option<u64>)) -> anyhow::Result<()> {
+    anyhow::ensure!(is_sha256(expected_sha256), "{expected_sha256:?} is not a SHA-256, {url} can't be verified");
+
+    let expected_sha256 = expected_sha256.to_ascii_lowercase();
+
+    let partial = partial_path(destination);
+
+    let _active = ActiveDownload::start(&partial)?;
+
+    let mut downloaded = tokio::fs::metadata(&partial).await.map(|metadata| metadata.len()).unwrap_or(0);
+
+    let mut request = reqwest::Client::new().get(url);
+
+    if downloaded > 0 {
+        println!("Resuming download of {url} from {downloaded} bytes");
+        request = request.header(header::RANGE, format!("bytes={downloaded}-"));
+    }
+
+    let mut response = request.send().await.context("Failed connecting to the download server")?;
+
+    let (resumed, total) = match response.status() {
+        StatusCode::PARTIAL_CONTENT => (true, response.content_length().map(|length| length + downloaded)),
+        // The partial file already holds everything
+        StatusCode::RANGE_NOT_SATISFIABLE if downloaded > 0 => (true, Some(downloaded)),
+        status if status.is_success() => {
+            downloaded = 0;
+            (false, response.content_length())
+        }
+        status => anyhow::bail!("Download server responded with {status}"),
+    };
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&partial)
+        .await
+        .context("Failed creating the download file")?;
+
+    let transfer = async {
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            return anyhow::Ok(());
+        }
+
+        while let Some(chunk) = response.chunk().await.context("Download was interrupted")? {
+            file.write_all(&chunk).await.context("Failed writing the download file")?;
+            downloaded += chunk.len() as u64;
+
+            on_progress(downloaded, total);
+        }
+
+        anyhow::Ok(())
+    }.await;
+
+    // Keep whatever arrived so the next attempt can resume from it
+    file.sync_all().await.context("Failed writing the download file")?;
+    drop(file);
+
+    transfer?;
+
+    if let Some(total) = total {
+        anyhow::ensure!(downloaded == total, "Download ended at {downloaded} of {total} bytes");
+    }
+
+    let sha256 = tokio::task::spawn_blocking({
+        let partial = partial.clone();
+        move || sha256_file(&partial)
+    }).await??;
+
+    if sha256 != expected_sha256 {
+        // Corrupted bytes can't be resumed from, start over next time
+        let _ = tokio::fs::remove_file(&partial).await;
+
+        anyhow::bail!("Downloaded file is corrupted, expected SHA-256 {expected_sha256} but got {sha256}");
+    }
+
+    tokio::fs::rename(&partial, destination).await.context("Failed moving the downloaded file into place")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::test_server::{self, Response};
+
+    /// Serves `body`, honouring `Range` and cutting the first response after `cut_after` bytes
+    struct StandIn {
+        url: String,
+        ranges: Arc<Mutex<Vec<Option<u64>>>>,
+    }
+
+    impl StandIn {
+        fn serve(body: Vec<u8>, etag: Option<String>, cut_after: Option<usize>) -> Self {
+            let ranges = Arc::new(Mutex::new(Vec::new()));
+
+            let url = test_server::serve({
+                let ranges = ranges.clone();
+
+                move |request| {
+                    let start = request.header("range")
+                        .and_then(|range| range.strip_prefix("bytes="))
+                        .and_then(|range| range.trim_end_matches('-').parse::<u64>().ok());
+
+                    ranges.lock().unwrap().push(start);
+
+                    let status = if start.is_some() { "206 Partial Content" } else { "200 OK" };
+                    let mut response = Response::new(status, &body[start.unwrap_or(0) as usize..]);
+
+                    response.headers.extend(etag.as_ref().map(|etag| format!("ETag: \"{etag}\"")));
+                    response.cut_after = cut_after.filter(|_| request.index == 0);
+
+                    response
+                }
+            });
+
+            Self { url: format!("{url}/model.bin"), ranges }
+        }
+    }
+
+    fn body() -> Vec<u8> {
+        (0..100_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        Sha256::digest(data).iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    fn destination(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("recordscript-downloader-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        dir.join("model.bin")
+    }
+
+    #[tokio::test]
+    async fn resumes_interrupted_download() {
+        let body = body();
+        let server = StandIn::serve(body.clone(), None, Some(30_000));
+        let destination = destination("resume");
+
+        assert!(download(&server.url, &destination, &sha256(&body), |_, _| {}).await.is_err());
+        assert!(!destination.exists());
+        assert_eq!(std::fs::metadata(partial_path(&destination)).unwrap().len(), 30_000);
+
+        download(&server.url, &destination, &sha256(&body), |_, _| assert!(is_downloading(&partial_path(&destination)))).await.unwrap();
+        assert!(!is_downloading(&partial_path(&destination)));
+
+        assert_eq!(*server.ranges.lock().unwrap(), [None, Some(30_000)]);
+        assert_eq!(std::fs::read(&destination).unwrap(), body);
+        assert!(!partial_path(&destination).exists());
+    }
+
+    #[tokio::test]
+    async fn rejects_checksum_mismatch() {
+        let server = StandIn::serve(body(), None, None);
+        let destination = destination("mismatch");
+
+        let result = download(&server.url, &destination, &sha256(b"something else"), |_, _| {}).await;
+
+        assert!(result.is_err());
+        assert!(!destination.exists());
+        assert!(!partial_path(&destination).exists());
+    }
+
+    #[tokio::test]
+    async fn ignores_advertised_digest() {
+        let destination = destination("advertised");
+
+        // The server vouching for what it sends proves nothing
+        let server = StandIn::serve(body(), Some(sha256(&body())), None);
+        assert!(download(&server.url, &destination, &sha256(b"something else"), |_, _| {}).await.is_err());
+        assert!(download(&server.url, &destination, "", |_, _| {}).await.is_err());
+        assert!(!destination.exists());
+    }
+}
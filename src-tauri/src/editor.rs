@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+
+use crate::{subtitle::SubtitleFormat, timestamp::Timestamp, transcript::Segment};
+
+/// Subtitle file opened for fixing, edits stay in memory until it's saved
+#[derive(Debug, Default)]
+pub struct Document {
+    path: Option<PathBuf>,
+    cues: Vec<Segment>,
+}
+
+impl Document {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let format = SubtitleFormat::from_path(path).with_context(|| format!("{} is not an SRT, WebVTT or ASS file", path.display()))?;
+
+        let content = std::fs::read(path).with_context(|| format!("Can't read {}", path.display()))?;
+        let cues = crate::subtitle::parse(&String::from_utf8_lossy(&content), format)?;
+
+        Ok(Self { path: Some(path.to_owned()), cues })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn cues(&self) -> &[Segment] {
+        &self.cues
+    }
+
+    fn cue(&mut self, index: usize) -> anyhow::Result<&mut Segment> {
+        let count = self.cues.len();
+
+        self.cues.get_mut(index).with_context(|| format!("There's no cue {} of {count}", index + 1))
+    }
+
+    /// Replace the text or timing of a cue, cues are kept in order of their start
+    pub fn edit(&mut self, index: usize, text: Option<String>, start: Option<Timestamp>, end: Option<Timestamp>) -> anyhow::Result<()> {
+        let cue = self.cue(index)?;
+
+        let start = start.unwrap_or(cue.start);
+        let end = end.unwrap_or(cue.end);
+
+        anyhow::ensure!(start <= end, "Cue {} would end at {} before starting at {}", index + 1, end.srt(), start.srt());
+
+        cue.start = start;
+        cue.end = end;
+
+        if let Some(text) = text {
+            cue.text = text;
+        }
+
+        self.cues.sort_by_key(|cue| cue.start);
+
+        Ok(())
+    }
+
+    /// Move every cue `milliseconds` later, or earlier when negative, cues can't move before the start
+    pub fn shift(&mut self, milliseconds: i64) {
+        for cue in &mut self.cues {
+            cue.start = cue.start.offset(milliseconds);
+            cue.end = cue.end.offset(milliseconds);
+        }
+    }
+
+    /// Multiply every timestamp by `factor`, e.g. 25 / 23.976 for subtitles timed at the wrong framerate
+    pub fn scale(&mut self, factor: f64) -> anyhow::Result<()> {
+        anyhow::ensure!(factor.is_finite() && factor > 0.0, "Can't scale timestamps by {factor}");
+
+        for cue in &mut self.cues {
+            cue.start = cue.start.scale(factor);
+            cue.end = cue.end.scale(factor);
+        }
+
+        Ok(())
+    }
+
+    /// Join a cue with the one after it, speakers that differ are kept in the text
+    pub fn merge(&mut self, index: usize) -> anyhow::Result<()> {
+        anyhow::ensure!(index + 1 < self.cues.len(), "Cue {} has no next cue to merge with", index + 1);
+
+        let next = self.cues.remove(index + 1);
+        let cue = self.cue(index)?;
+
+        if cue.speaker == next.speaker {
+            cue.text = format!("{}\n{}", cue.text.trim(), next.text.trim());
+        } else {
+            cue.text = format!("{}\n{}", cue.labelled_text(), next.labelled_text());
+            cue.speaker = None;
+        }
+
+        cue.start = cue.start.min(next.start);
+        cue.end = cue.end.max(next.end);
+
+        Ok(())
+    }
+
+    /// Split a cue before the character at `position` of its text, the time is shared by the length of both parts
+    pub fn split(&mut self, index: usize, position: usize) -> anyhow::Result<()> {
+        let cue = self.cue(index)?;
+
+        let chars: Vec<char> = cue.text.chars().collect();
+        let (first, second): (String, String) = (chars[..position.min(chars.len())].iter().collect(), chars[position.min(chars.len())..].iter().collect());
+        let (first, second) = (first.trim().to_owned(), second.trim().to_owned());
+
+        anyhow::ensure!(!first.is_empty() && !second.is_empty(), "Cue {} can't be split at character {position}, both parts need text", index + 1);
+
+        let (first_length, second_length) = (first.chars().count() as i64, second.chars().count() as i64);
+        let middle = cue.start.lerp(cue.end, first_length, first_length + second_length);
+
+        let next = Segment { start: middle, end: cue.end, text: second, speaker: cue.speaker.clone(), words: Vec::new() };
+
+        cue.end = middle;
+        cue.text = first;
+
+        self.cues.insert(index + 1, next);
+
+        Ok(())
+    }
+
+    /// Write the cues to `path` in `format`, later saves go to the same file
+    pub fn save(&mut self, path: &Path, format: SubtitleFormat) -> anyhow::Result<()> {
+        std::fs::write(path, crate::subtitle::to_string(&self.cues, format)).with_context(|| format!("Can't write {}", path.display()))?;
+
+        self.path = Some(path.to_owned());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::fixtures::{segment, timings};
+
+    fn document(cues: &[(i64, i64, &str, Option<&str>)]) -> Document {
+        Document {
+            path: None,
+            cues: cues.iter().map(|(start, end, text, speaker)| segment(*start, *end, text, *speaker)).collect(),
+        }
+    }
+
+    #[test]
+    fn edits_and_reorders_cues() {
+        let mut document = document(&[(0, 1000, "One", None), (2000, 3000, "Two", None)]);
+
+        document.edit(0, Some("Three".to_owned()), Some(Timestamp::from_millis(4000)), Some(Timestamp::from_millis(5000))).unwrap();
+
+        assert_eq!(document.cues()[1].text, "Three");
+        assert_eq!(timings(document.cues()), [(2000, 3000), (4000, 5000)]);
+
+        assert!(document.edit(0, None, Some(Timestamp::from_millis(3500)), None).is_err());
+        assert!(document.edit(2, None, None, None).is_err());
+    }
+
+    #[test]
+    fn shifts_and_scales_timestamps() {
+        let mut document = document(&[(500, 1000, "One", None), (23_976, 47_952, "Two", None)]);
+
+        document.shift(-700);
+        assert_eq!(timings(document.cues()), [(0, 300), (23_276, 47_252)]);
+
+        let mut document = self::document(&[(23_976, 47_952, "Two", None)]);
+
+        document.scale(25.0 / 23.976).unwrap();
+        assert_eq!(timings(document.cues()), [(25_000, 50_000)]);
+
+        assert!(document.scale(0.0).is_err());
+    }
+
+    #[test]
+    fn merges_and_splits_cues() {
+        let mut document = document(&[(0, 1000, "Good morning", Some("Me")), (1000, 2000, "everyone", Some("Me")), (2000, 3000, "Hi", Some("Others"))]);
+
+        document.merge(0).unwrap();
+        assert_eq!(document.cues()[0].labelled_text(), "Me: Good morning\neveryone");
+        assert_eq!(timings(document.cues()), [(0, 2000), (2000, 3000)]);
+
+        document.merge(0).unwrap();
+        assert_eq!(document.cues()[0].speaker, None);
+        assert_eq!(document.cues()[0].text, "Me: Good morning\neveryone\nOthers: Hi");
+        assert!(document.merge(0).is_err());
+
+        let mut document = self::document(&[(0, 2000, "Good morning everyone", Some("Me"))]);
+
+        document.split(0, 12).unwrap();
+        assert_eq!(document.cues().iter().map(Segment::labelled_text).collect::<Vec<_>>(), ["Me: Good morning", "Me: everyone"]);
+        assert_eq!(timings(document.cues()), [(0, 1200), (1200, 2000)]);
+
+        assert!(document.split(0, 0).is_err());
+        assert!(document.split(0, 100).is_err());
+    }
+
+    #[test]
+    fn opens_and_saves_every_format() {
+        let dir = std::env::temp_dir().join(format!("editor-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let srt = dir.join("meeting.srt");
+        std::fs::write(&srt, "1\n00:00:01,000 --> 00:00:02,000\nHello\n").unwrap();
+
+        let mut document = Document::open(&srt).unwrap();
+        document.shift(1000);
+
+        for format in [SubtitleFormat::Srt, SubtitleFormat::Vtt, SubtitleFormat::Ass] {
+            let path = dir.join(format!("fixed.{}", format.extension()));
+
+            document.save(&path, format).unwrap();
+
+            let reopened = Document::open(&path).unwrap();
+            assert_eq!(timings(reopened.cues()), [(2000, 3000)], "{format:?}");
+            assert_eq!(reopened.cues()[0].text, "Hello", "{format:?}");
+        }
+
+        assert_eq!(document.path(), Some(dir.join("fixed.ass").as_path()));
+        assert!(Document::open(&dir.join("meeting.txt")).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -5,13 +5,11 @@
 
 use std::io::Write as _;
 use std::str::FromStr as _;
-use std::{fs::File, path::PathBuf, sync::{atomic, Arc, Mutex}};
+use std::{fs::File, path::{Path, PathBuf}, sync::{atomic, Arc, Mutex}};
 
-use gst::prelude::*;
+use anyhow::Context as _;
 
-use cpal::traits::DeviceTrait as _;
-use cpal::traits::HostTrait as _;
-use cpal::traits::StreamTrait as _;
+use gst::prelude::*;
 
 use dialog::DialogBox as _;
 
@@ -23,96 +21,136 @@ use strum::IntoEnumIterator as _;
 use tauri::Manager as _;
 use tauri::{State, Window};
 
-use crate::recorder::DeviceEq as _;
-
+mod alignment;
+mod benchmark;
 mod configuration;
+mod diarization;
+mod downloader;
+mod editor;
+mod media;
 mod recorder;
+mod redaction;
+mod review;
+mod shaping;
+mod speaker;
+mod subtitle;
+#[cfg(test)]
+mod test_server;
+mod timestamp;
 mod transcriber;
+mod transcript;
+mod translation;
 mod util;
+mod vad;
+mod vocabulary;
+mod watch;
 
-type SelectedDevice = Arc<Mutex<recorder::SelectedDevice>>;
+type SelectedDevice = Arc<Mutex<configuration::DeviceConfig>>;
 type RecordChannel = tauri::async_runtime::Sender<recorder::RecordCommand>;
 
 type GeneralConfig = Arc<Mutex<configuration::GeneralConfig>>;
 type SMTPConfig = Arc<Mutex<configuration::SMTPConfig>>;
+type SubtitleEditor = Arc<Mutex<editor::Document>>;
+type DeviceWatch = Arc<recorder::DeviceWatch>;
 
 #[tauri::command]
 fn list_microphone(selected_device: State<'_, SelectedDevice>) -> Vec<recorder::DeviceResult> {
-    let default_device = &selected_device.lock().unwrap().microphone;
+    let selected = recorder::find_microphone(selected_device.lock().unwrap().microphone.as_ref()).map(|(id, _)| id);
 
     recorder::list_microphone().into_iter()
-        .filter(|device| device.name().is_ok())
-        .map(|device| 
+        .filter(|(id, _)| !id.name.is_empty())
+        .map(|(id, _)| 
             recorder::DeviceResult {
-                name: device.name().unwrap(),
-                is_selected: if let Some(default_device) = default_device { device.eq_device(default_device) } else { false },
+                name: id.name.clone(),
+                is_selected: selected.as_ref() == Some(&id),
+                id,
             })
         .collect()
 }
 
 #[tauri::command]
 fn list_speaker(selected_device: State<'_, SelectedDevice>) -> Vec<recorder::DeviceResult> {
-    let default_device = &selected_device.lock().unwrap().speaker;
+    let selected = recorder::find_speaker(selected_device.lock().unwrap().speaker.as_ref()).map(|(id, _)| id);
 
     recorder::list_speaker().into_iter()
-        .map(|device| 
+        .map(|(id, _)| 
             recorder::DeviceResult {
-                name: device.name().unwrap_or("Unkown device".to_owned()),
-                is_selected: if let Some(default_device) = default_device { device.eq_device(default_device) } else { false },
+                name: if id.name.is_empty() { "Unkown device".to_owned() } else { id.name.clone() },
+                is_selected: selected.as_ref() == Some(&id),
+                id,
             })
         .collect()
 }
 
 #[tauri::command]
 fn list_screen(selected_device: State<'_, SelectedDevice>) -> Vec<recorder::DeviceResult> {
-    let default_device = &selected_device.lock().unwrap().screen;
+    let selected = recorder::find_screen(selected_device.lock().unwrap().screen.as_ref()).map(|(id, _)| id);
 
     recorder::list_screen().unwrap_or_default().into_iter()
-        .map(|screen|
+        .map(|(id, _)|
             recorder::DeviceResult {
-                name: screen.name(),
-                is_selected: screen.eq_device(default_device),
+                name: id.name.clone(),
+                is_selected: selected.as_ref() == Some(&id),
+                id,
              })
         .collect()
 }
 
+/// Devices are watched for hotplugging while their selection is shown
+#[tauri::command]
+fn show_devices(device_watch: State<'_, DeviceWatch>, is_shown: bool) {
+    device_watch.is_shown.store(is_shown, atomic::Ordering::Relaxed);
+}
 
 #[tauri::command]
-fn select_microphone(selected_device: State<'_, SelectedDevice>, device_name: String) {
-    let device = recorder::list_microphone().into_iter()
-        .find(|device| device.name().unwrap_or_default() == device_name).unwrap();
-    
-    selected_device.lock().unwrap().microphone = Some(device);
+fn select_microphone(selected_device: State<'_, SelectedDevice>, device_id: recorder::DeviceId) {
+    println!("Switching microphone to {device_id}");
 
-    println!("Switching microphone to {device_name:?}");
+    let mut selected_device = selected_device.lock().unwrap();
+    selected_device.microphone = Some(device_id);
+
+    configuration::save(&*selected_device);
 }
 
 #[tauri::command]
-fn select_speaker(selected_device: State<'_, SelectedDevice>, device_name: String) {
-    let device = recorder::list_speaker().into_iter()
-        .find(|device| device.name().unwrap_or_default() == device_name).unwrap();
-    
-    selected_device.lock().unwrap().speaker = Some(device);
+fn select_speaker(selected_device: State<'_, SelectedDevice>, device_id: recorder::DeviceId) {
+    println!("Switching speaker to {device_id}");
 
-    println!("Switching speaker to {device_name:?}");
+    let mut selected_device = selected_device.lock().unwrap();
+    selected_device.speaker = Some(device_id);
+
+    configuration::save(&*selected_device);
 }
 
 #[tauri::command]
-fn select_screen(selected_device: State<'_, SelectedDevice>, device_name: String) {
-    let device = recorder::list_screen().unwrap().into_iter()
-        .find(|device| device.name() == device_name).unwrap();
-
-    println!("Switching screen to {device_name:?}");
+fn select_screen(selected_device: State<'_, SelectedDevice>, device_id: recorder::DeviceId) {
+    println!("Switching screen to {device_id}");
     
-    selected_device.lock().unwrap().screen = device;
+    let mut selected_device = selected_device.lock().unwrap();
+    selected_device.screen = Some(device_id);
+
+    configuration::save(&*selected_device);
 }
 
 #[tauri::command]
-fn preview_screen(device_name: String) -> String {
-    let screen = recorder::Screen::all().unwrap().into_iter()
-        .find(|screen| screen.name() == &device_name).unwrap();
+fn preview_screen(window: Window, device_id: recorder::DeviceId) -> String {
+    let Some(screen) = recorder::Screen::all().unwrap_or_default().into_iter()
+        .find(|screen| screen.id() == &device_id) else {
+        eprintln!("Can't preview screen {device_id}, it's not available");
+        return String::new();
+    };
+
+    let preview = match screen.preview() {
+        Ok(preview) => preview,
+        Err(err) => {
+            util::emit_all(&window, "app://notification", serde_json::json!({
+                "type": "error",
+                "value": format!("Can't preview screen {device_id} because: {err:#}")
+            }));
+            return String::new();
+        }
+    };
 
-    let preview = screen.preview().unwrap();
     let encoded = gst::glib::base64_encode(&preview);
 
     encoded.to_string()
@@ -121,42 +159,102 @@ fn preview_screen(device_name: String) -> String {
 #[tauri::command]
 async fn start_record(selected_device: State<'_, SelectedDevice>, record_channel: State<'_, RecordChannel>) -> Result<(), ()> {
     let selected_device = selected_device.lock().unwrap().clone();
-    record_channel.send(recorder::RecordCommand::Start(selected_device)).await.unwrap();
+    let record_mode = configuration::RecordConfig::default().mode;
+    record_channel.send(recorder::RecordCommand::Start(selected_device, record_mode)).await.unwrap();
 
     Ok(())
 }
 
+/// Where the transcript of `media_path` is saved in `directory`, numbered when the name is taken
+fn transcription_path(directory: &Path, media_path: &Path) -> PathBuf {
+    let target_name = media_path.file_stem().unwrap_or_default().to_string_lossy();
+
+    let mut transcription_path = directory.join(format!("{target_name}.srt"));
+
+    let mut n = 1;
+    loop {
+        if !transcription_path.exists() { break };
+
+        transcription_path.set_file_name(format!("{target_name} ({n}).srt"));
+
+        n += 1;
+    }
+
+    transcription_path
+}
+
+/// Duration, streams and codecs of a media file, with how long the selected model takes to transcribe it
 #[tauri::command]
-fn start_transcription(window: Window, general_config: State<GeneralConfig>, smtp_config: State<SMTPConfig>, transcriber: State<Arc<Mutex<transcriber::Transcriber>>>, media_path: String) {
-    let Ok(buffer) = std::fs::read(&media_path) else {
+fn probe_media(window: Window, transcriber: State<Arc<Mutex<transcriber::Transcriber>>>, path: PathBuf) -> Result<media::MediaInfo, ()> {
+    let mut info = media::probe(&path).map_err(|err| {
         util::emit_all(&window, "app://notification", serde_json::json!({
             "type": "error",
-            "value": "Can't read the selected file"
+            "value": format!("Can't read {} because: {err:#}", path.display())
         }));
-        return;
-    };
+    })?;
 
+    let model = transcriber.lock().unwrap().model().clone();
+    let results = configuration::BenchmarkConfig::default().results;
+
+    info.transcription_eta = info.duration.and_then(|duration| benchmark::estimate_time(&results, &model, duration.as_secs_f32()));
+
+    Ok(info)
+}
+
+/// `selection` is the range and audio stream to transcribe, all of the first audio stream when `None`
+#[tauri::command]
+fn start_transcription(window: Window, general_config: State<GeneralConfig>, smtp_config: State<SMTPConfig>, transcriber: State<Arc<Mutex<transcriber::Transcriber>>>, media_path: String, selection: Option<media::MediaSelection>) {
     let media_path = PathBuf::from_str(&media_path).unwrap();
-    let target_name = media_path.file_stem().unwrap();
+    let selection = selection.unwrap_or_default();
+
+    // The discoverer only reads what it needs to tell whether the file can be decoded
+    let buffer = media::probe(&media_path)
+        .and_then(|info| selection.check(&info))
+        .and_then(|()| std::fs::read(&media_path).context("Failed reading the file"));
+
+    let buffer = match buffer {
+        Ok(buffer) => buffer,
+        Err(err) => {
+            util::emit_all(&window, "app://notification", serde_json::json!({
+                "type": "error",
+                "value": format!("Can't transcribe {} because: {err:#}", media_path.display())
+            }));
+            return;
+        }
+    };
+
+    // Recordings made by us know which lines came through the microphone
+    let source_levels = speaker::SourceLevels::load(&media_path);
 
     let general_config = general_config.lock().unwrap().clone();
     let smtp_config = smtp_config.lock().unwrap().clone();
 
-    let mut transcription_path = general_config.transcript_save_to.save_path.join(format!("{}.srt", target_name.to_string_lossy()));
+    let transcription_path = transcription_path(&general_config.transcript_save_to.save_path, &media_path);
 
-    let mut n = 1;
-    loop {
-        if !transcription_path.exists() { break };
+    transcriber.lock().unwrap()
+        .transcribe(&window, buffer, selection, source_levels, general_config.clone(), smtp_config, transcription_path.clone(), false);
 
-        transcription_path.set_file_name(format!("{} ({n}).srt", target_name.to_string_lossy()));
+    println!("Starting transcription with file \"{}\" to \"{}\"", media_path.display(), transcription_path.display());
+}
 
-        n += 1;
-    }
+/// Subtitles of `media` worded exactly like the `script` text file, timed by what whisper hears
+#[tauri::command]
+fn align_transcript(window: Window, general_config: State<GeneralConfig>, transcriber: State<Arc<Mutex<transcriber::Transcriber>>>, media: PathBuf, script: PathBuf) {
+    let (Ok(buffer), Ok(script_text)) = (std::fs::read(&media), std::fs::read(&script)) else {
+        util::emit_all(&window, "app://notification", serde_json::json!({
+            "type": "error",
+            "value": "Can't read the selected media or script"
+        }));
+        return;
+    };
 
-    transcriber.lock().unwrap()
-        .transcribe(&window, buffer, general_config.clone(), smtp_config, transcription_path.clone(), false);
+    let general_config = general_config.lock().unwrap().clone();
+    let transcription_path = transcription_path(&general_config.transcript_save_to.save_path, &media);
 
-    println!("Starting transcription with file \"{}\" to \"{}\"", media_path.display(), transcription_path.display());
+    println!("Aligning \"{}\" to \"{}\" into \"{}\"", script.display(), media.display(), transcription_path.display());
+
+    transcriber.lock().unwrap()
+        .align(&window, buffer, String::from_utf8_lossy(&script_text).into_owned(), transcription_path);
 }
 
 #[tauri::command]
@@ -187,6 +285,330 @@ fn set_general_config(general_config_state: State<'_, GeneralConfig>, general_co
     *general_config_state.lock().unwrap() = general_config;
 }
 
+#[tauri::command]
+fn get_record_config() -> configuration::RecordConfig {
+    configuration::RecordConfig::default()
+}
+
+#[tauri::command]
+fn set_record_config(config: configuration::RecordConfig) {
+    println!("Updating recording to {config:?}");
+
+    configuration::save(&config);
+}
+
+#[tauri::command]
+fn get_vocabulary_config() -> configuration::VocabularyConfig {
+    configuration::VocabularyConfig::default()
+}
+
+/// Patterns are checked before saving, the error describes the first invalid one
+#[tauri::command]
+fn set_vocabulary_config(config: configuration::VocabularyConfig) -> Result<(), String> {
+    println!("Updating vocabulary to {config:?}");
+
+    for profile in &config.profiles {
+        vocabulary::Replacer::new(&profile.replacements).map_err(|err| format!("{}: {err}", profile.name))?;
+    }
+
+    configuration::save(&config);
+
+    Ok(())
+}
+
+#[tauri::command]
+fn get_speaker_labels() -> configuration::SpeakerLabelConfig {
+    configuration::SpeakerLabelConfig::default()
+}
+
+#[tauri::command]
+fn set_speaker_labels(labels: configuration::SpeakerLabelConfig) {
+    println!("Updating speaker labels to {labels:?}");
+
+    configuration::save(&labels);
+}
+
+/// Diarization config with whether its speaker model is downloaded
+#[tauri::command]
+fn get_diarization_config() -> serde_json::Value {
+    let mut config = serde_json::to_value(configuration::DiarizationConfig::default()).unwrap();
+
+    config["downloaded"] = diarization::is_downloaded().into();
+
+    config
+}
+
+#[tauri::command]
+fn set_diarization_config(config: configuration::DiarizationConfig) {
+    println!("Updating diarization to {config:?}");
+
+    configuration::save(&config);
+}
+
+#[tauri::command]
+fn download_diarization_model(window: Window) -> String {
+    let channel_name = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos().to_string();
+
+    println!("Downloading speaker model");
+
+    let channel = channel_name.clone();
+    tauri::async_runtime::spawn(async move {
+        let speaker_model = &transcriber::ModelManifest::get().speaker_model;
+
+        let result = async {
+            let sha256 = speaker_model.sha256.as_deref().context("No SHA-256 is pinned for the speaker model, it can't be verified")?;
+
+            downloader::download(&speaker_model.url, &diarization::model_path()?, sha256, |downloaded_size, total_size| {
+                window.emit(&channel, serde_json::json!({
+                    "type": "progress",
+                    "value": (downloaded_size as f32 / total_size.unwrap_or(diarization::EMBEDDING_MODEL_SIZE) as f32) * 100.0,
+                })).unwrap();
+            }).await
+        }.await;
+
+        if let Err(err) = result {
+            eprintln!("Failed downloading speaker model: {err:?}");
+
+            window.emit(&channel, serde_json::json!({
+                "type": "error",
+                "value": format!("{err:#}"),
+            })).unwrap();
+            return;
+        }
+
+        window.emit(&channel, serde_json::json!({
+            "type": "done",
+            "value": "",
+        })).unwrap();
+    });
+
+    channel_name
+}
+
+#[tauri::command]
+fn get_translation_config() -> configuration::TranslationConfig {
+    configuration::TranslationConfig::default()
+}
+
+#[tauri::command]
+fn set_translation_config(config: configuration::TranslationConfig) {
+    println!("Updating translation to {config:?}");
+
+    configuration::save(&config);
+}
+
+#[tauri::command]
+fn get_bilingual_config() -> configuration::BilingualConfig {
+    configuration::BilingualConfig::default()
+}
+
+#[tauri::command]
+fn set_bilingual_config(config: configuration::BilingualConfig) {
+    println!("Updating bilingual subtitles to {config:?}");
+
+    configuration::save(&config);
+}
+
+#[tauri::command]
+fn get_cue_rules() -> configuration::CueRulesConfig {
+    configuration::CueRulesConfig::default()
+}
+
+#[tauri::command]
+fn set_cue_rules(rules: configuration::CueRulesConfig) {
+    println!("Updating cue rules to {rules:?}");
+
+    configuration::save(&rules);
+}
+
+#[tauri::command]
+fn get_review_config() -> configuration::ReviewConfig {
+    configuration::ReviewConfig::default()
+}
+
+#[tauri::command]
+fn set_review_config(config: configuration::ReviewConfig) {
+    println!("Updating review to {config:?}");
+
+    configuration::save(&config);
+}
+
+#[tauri::command]
+fn get_redaction_config() -> configuration::RedactionConfig {
+    configuration::RedactionConfig::default()
+}
+
+/// Custom patterns are checked before saving, the error describes the first invalid one
+#[tauri::command]
+fn set_redaction_config(config: configuration::RedactionConfig) -> Result<(), String> {
+    println!("Updating redaction to {config:?}");
+
+    redaction::Redactor::new(&config).map_err(|err| err.to_string())?;
+
+    configuration::save(&config);
+
+    Ok(())
+}
+
+#[tauri::command]
+fn get_watch_folder_config() -> configuration::WatchFolderConfig {
+    configuration::WatchFolderConfig::default()
+}
+
+#[tauri::command]
+fn set_watch_folder_config(config: configuration::WatchFolderConfig) {
+    println!("Updating watch folders to {config:?}");
+
+    configuration::save(&config);
+}
+
+/// Email a transcript that was held back for review, once it's proofread
+#[tauri::command]
+fn send_transcript_email(window: Window, general_config: State<GeneralConfig>, smtp_config: State<SMTPConfig>, path: PathBuf) {
+    let Ok(transcription) = std::fs::read_to_string(&path) else {
+        util::emit_all(&window, "app://notification", serde_json::json!({
+            "type": "error",
+            "value": format!("Can't read the transcript at {}", path.display())
+        }));
+        return;
+    };
+
+    let general_config = general_config.lock().unwrap().clone();
+    let smtp_config = smtp_config.lock().unwrap().clone();
+
+    std::thread::spawn(move || transcriber::send_transcript_email(&window, &general_config, &smtp_config, transcription));
+}
+
+/// Apply `action` to the opened subtitles, returns the cues after it or notifies why it failed
+fn edit_subtitles(window: &Window, editor: &SubtitleEditor, action: impl FnOnce(&mut editor::Document) -> anyhow::Result<()>) -> Result<Vec<transcript::Segment>, ()> {
+    let mut document = editor.lock().unwrap();
+
+    match action(&mut document) {
+        Ok(()) => Ok(document.cues().to_vec()),
+        Err(err) => {
+            util::emit_all(window, "app://notification", serde_json::json!({
+                "type": "error",
+                "value": format!("Failed editing the subtitles because: {err:#}"),
+            }));
+
+            Err(())
+        }
+    }
+}
+
+#[tauri::command]
+fn open_subtitles(window: Window, editor: State<'_, SubtitleEditor>, path: PathBuf) -> Result<Vec<transcript::Segment>, ()> {
+    println!("Opening subtitles {}", path.display());
+
+    edit_subtitles(&window, &editor, |document| {
+        *document = editor::Document::open(&path)?;
+
+        Ok(())
+    })
+}
+
+/// Timestamps are in milliseconds, omitted fields are kept
+#[tauri::command]
+fn edit_cue(window: Window, editor: State<'_, SubtitleEditor>, index: usize, text: Option<String>, start: Option<i64>, end: Option<i64>) -> Result<Vec<transcript::Segment>, ()> {
+    edit_subtitles(&window, &editor, |document| document.edit(index, text, start.map(timestamp::Timestamp::from_millis), end.map(timestamp::Timestamp::from_millis)))
+}
+
+#[tauri::command]
+fn shift_subtitles(window: Window, editor: State<'_, SubtitleEditor>, milliseconds: i64) -> Result<Vec<transcript::Segment>, ()> {
+    edit_subtitles(&window, &editor, |document| {
+        document.shift(milliseconds);
+
+        Ok(())
+    })
+}
+
+#[tauri::command]
+fn scale_subtitles(window: Window, editor: State<'_, SubtitleEditor>, factor: f64) -> Result<Vec<transcript::Segment>, ()> {
+    edit_subtitles(&window, &editor, |document| document.scale(factor))
+}
+
+#[tauri::command]
+fn merge_cues(window: Window, editor: State<'_, SubtitleEditor>, index: usize) -> Result<Vec<transcript::Segment>, ()> {
+    edit_subtitles(&window, &editor, |document| document.merge(index))
+}
+
+/// Split before the character at `position` of the cue's text
+#[tauri::command]
+fn split_cue(window: Window, editor: State<'_, SubtitleEditor>, index: usize, position: usize) -> Result<Vec<transcript::Segment>, ()> {
+    edit_subtitles(&window, &editor, |document| document.split(index, position))
+}
+
+/// Save to `path`, or over the opened file without one, in `format` or the format of the path's extension
+#[tauri::command]
+fn save_subtitles(window: Window, editor: State<'_, SubtitleEditor>, path: Option<PathBuf>, format: Option<subtitle::SubtitleFormat>) -> Result<Vec<transcript::Segment>, ()> {
+    edit_subtitles(&window, &editor, |document| {
+        let path = path.or_else(|| document.path().map(Path::to_path_buf)).context("Choose where to save the subtitles")?;
+
+        println!("Saving subtitles to {}", path.display());
+
+        let format = format.or_else(|| subtitle::SubtitleFormat::from_path(&path)).unwrap_or(subtitle::SubtitleFormat::Srt);
+
+        document.save(&path, format)
+    })
+}
+
+#[tauri::command]
+fn is_translation_model_downloaded(source: String, target: String) -> bool {
+    translation::is_translation_model_downloaded(&source, &target)
+}
+
+/// Download the offline model translating `source` into `target`, progress covers all of its files
+#[tauri::command]
+fn download_translation_model(window: Window, source: String, target: String) -> String {
+    let channel_name = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos().to_string();
+
+    println!("Downloading translation model from {source} to {target}");
+
+    let channel = channel_name.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = async {
+            let files = translation::translation_model_files(&source, &target)?;
+            let count = files.len();
+
+            for (index, (url, path, sha256)) in files.into_iter().enumerate() {
+                if path.exists() { continue };
+
+                if let Some(parent) = path.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+
+                downloader::download(&url, &path, &sha256, |downloaded_size, total_size| {
+                    let file_progress = total_size.map_or(0.0, |total_size| downloaded_size as f32 / total_size as f32);
+
+                    window.emit(&channel, serde_json::json!({
+                        "type": "progress",
+                        "value": (index as f32 + file_progress) / count as f32 * 100.0,
+                    })).unwrap();
+                }).await?;
+            }
+
+            anyhow::Ok(())
+        }.await;
+
+        if let Err(err) = result {
+            eprintln!("Failed downloading translation model from {source} to {target}: {err:?}");
+
+            window.emit(&channel, serde_json::json!({
+                "type": "error",
+                "value": format!("{err:#}"),
+            })).unwrap();
+            return;
+        }
+
+        window.emit(&channel, serde_json::json!({
+            "type": "done",
+            "value": "",
+        })).unwrap();
+    });
+
+    channel_name
+}
+
 #[tauri::command]
 fn get_smtp_config(config: State<'_, SMTPConfig>) -> configuration::SMTPConfig {
     config.lock().unwrap().clone()
@@ -200,6 +622,62 @@ fn set_smtp_config(config_state: State<'_, SMTPConfig>, config: configuration::S
     *config_state.lock().unwrap() = config;
 }
 
+/// Tell the user when a saved device is not available anymore
+fn notify_device_fallback(window: &Window, saved: Option<&recorder::DeviceId>, used: Option<&recorder::DeviceId>) {
+    let Some(saved) = saved else { return };
+
+    if used == Some(saved) { return };
+
+    let message = match used {
+        Some(used) => format!("\"{}\" is not available, recording with \"{}\" instead", saved.name, used.name),
+        None => format!("\"{}\" is not available", saved.name),
+    };
+
+    util::emit_all(window, "app://notification", serde_json::json!({
+        "type": "info",
+        "value": message
+    }));
+}
+
+/// Switch a disconnected audio source back to its device, or to the default one, while recording
+fn recover_audio_source(window: &Window, source: &mut recorder::AudioSource, pipeline: &gst::Pipeline, capture_clock: &recorder::CaptureClock) {
+    let kind = <&str>::from(source.device_type);
+    let was_lost = source.is_lost;
+    let lost_id = source.id.clone();
+
+    source.mark_lost();
+
+    let device = match source.device_type {
+        recorder::DeviceType::Microphone => recorder::find_microphone(Some(&lost_id)),
+        recorder::DeviceType::Speaker => recorder::find_speaker(Some(&lost_id)),
+    };
+
+    let appsrc = pipeline.by_name(&source.input_name)
+        .and_then(|element| element.dynamic_cast::<gst_app::AppSrc>().ok());
+
+    let result = match (device, appsrc) {
+        (Some(device), Some(appsrc)) => source.switch_device(device, capture_clock, &appsrc),
+        _ => Err(anyhow::anyhow!("No {kind} is available")),
+    };
+
+    let message = match result {
+        Ok(()) if source.id == lost_id => format!("{kind} \"{}\" is reconnected", lost_id.name),
+        Ok(()) => format!("{kind} \"{}\" is disconnected, recording continues with \"{}\"", lost_id.name, source.id.name),
+        Err(err) => {
+            eprintln!("Can't recover {kind} because: {err:?}");
+
+            if was_lost { return };
+
+            format!("{kind} \"{}\" is disconnected, recording silence until it's available again", lost_id.name)
+        },
+    };
+
+    util::emit_all(window, "app://notification", serde_json::json!({
+        "type": "info",
+        "value": message
+    }));
+}
+
 fn project_directory() -> directories::ProjectDirs {
     directories::ProjectDirs::from("com.recordscript", "Recordscript", "Recordscript").expect("Cannot use app directory")
 }
@@ -212,35 +690,34 @@ fn download_model(window: Window, model: transcriber::Model) -> String {
 
     let channel = channel_name.clone();
     tauri::async_runtime::spawn(async move {
-        let mut response = reqwest::get(model.download_url()).await.unwrap();
+        let predicted_size = (model.disk_usage() * 1000000) as u64;
 
-        let mut file = std::fs::File::create(model.path()).unwrap();
+        let Some(download_url) = model.download_url() else {
+            window.emit(&channel, serde_json::json!({
+                "type": "error",
+                "value": "Imported models can't be downloaded, import the file again",
+            })).unwrap();
+            return;
+        };
 
-        let mut downloaded_size = 0;
-        let predicted_size = response.content_length().unwrap_or((model.disk_usage() * 1000000) as _);
-        
-        while let Some(chunk) = match response.chunk().await {
-            Ok(chunk) => chunk,
-            Err(err) => {
+        let result = match model.sha256() {
+            Some(sha256) => downloader::download(&download_url, &model.path(), sha256, |downloaded_size, total_size| {
                 window.emit(&channel, serde_json::json!({
-                    "type": "error",
-                    "value": format!("{err:?}"),
+                    "type": "progress",
+                    "value": (downloaded_size as f32 / total_size.unwrap_or(predicted_size) as f32) * 100.0,
                 })).unwrap();
-                return;
-            },
-        } {
-            match file.write(&chunk) {
-                Ok(length) => downloaded_size += dbg!(length),
-                Err(err) => window.emit(&channel, serde_json::json!({
-                    "type": "error",
-                    "value": format!("{err:?}"),
-                })).unwrap(),
-            }
+            }).await,
+            None => Err(anyhow::anyhow!("No SHA-256 is pinned for {}, it can't be verified", model.name())),
+        };
+
+        if let Err(err) = result {
+            eprintln!("Failed downloading model {model:?}: {err:?}");
 
             window.emit(&channel, serde_json::json!({
-                "type": "progress",
-                "value": (downloaded_size as f32 / (predicted_size) as f32) * 100.0,
+                "type": "error",
+                "value": format!("{err:#}"),
             })).unwrap();
+            return;
         }
 
         window.emit(&channel, serde_json::json!({
@@ -268,23 +745,192 @@ fn select_model(transcriber: State<'_, Arc<Mutex<transcriber::Transcriber>>>, mo
 
 #[tauri::command]
 fn list_model() -> Vec<serde_json::Value> {
-    transcriber::Model::iter()
+    transcriber::Model::all().into_iter()
         .map(|model|
             serde_json::json!({
                 "type": model,
                 "name": model.name(),
                 "mem_usage": model.average_memory_usage(),
                 "disk_usage": model.disk_usage(),
+                "size_on_disk": model.size_on_disk(),
                 "is_downloaded": model.is_downloaded(),
                 "can_run": model.can_run(),
                 "whitelisted_lang": model.whitelisted_lang(),
                 "category": model.category(),
                 "type_name": model.r#type().name(),
                 "description": model.description(),
+                "speaker_turns": model.supports_speaker_turns(),
             })
         ).collect()
 }
 
+#[tauri::command]
+async fn import_model(window: Window, path: PathBuf, name: Option<String>, languages: Option<Vec<String>>, memory_usage: Option<usize>) -> Result<transcriber::Model, ()> {
+    println!("Importing model from {path:?}");
+
+    let result = tauri::async_runtime::spawn_blocking(move || transcriber::import_model(&path, name, languages, memory_usage)).await;
+
+    match result {
+        Ok(Ok(model)) => Ok(model),
+        Ok(Err(err)) => {
+            util::emit_all(&window, "app://notification", serde_json::json!({
+                "type": "error",
+                "value": format!("Failed importing the model because: {err:#}"),
+            }));
+
+            Err(())
+        }
+        Err(err) => {
+            eprintln!("Model import panicked: {err:?}");
+
+            Err(())
+        }
+    }
+}
+
+#[tauri::command]
+fn delete_model(window: Window, transcriber: State<'_, Arc<Mutex<transcriber::Transcriber>>>, model: transcriber::Model) -> Result<(), ()> {
+    println!("Deleting model {model:?}");
+
+    if let Err(err) = model.delete() {
+        util::emit_all(&window, "app://notification", serde_json::json!({
+            "type": "error",
+            "value": format!("Failed deleting the model because: {err:#}"),
+        }));
+
+        return Err(());
+    }
+
+    transcriber.lock().unwrap().unload_model(&model);
+
+    Ok(())
+}
+
+#[tauri::command]
+fn model_storage(window: Window) -> Option<transcriber::ModelStorage> {
+    match transcriber::ModelStorage::scan() {
+        Ok(storage) => Some(storage),
+        Err(err) => {
+            util::emit_all(&window, "app://notification", serde_json::json!({
+                "type": "error",
+                "value": format!("Failed reading the model directory because: {err:#}"),
+            }));
+
+            None
+        }
+    }
+}
+
+/// Remove partial and orphaned files from the model directory, returns the freed bytes
+#[tauri::command]
+fn clean_model_storage(window: Window) -> u64 {
+    let result = transcriber::ModelStorage::scan().and_then(|storage| storage.clean());
+
+    match result {
+        Ok(freed) => freed,
+        Err(err) => {
+            util::emit_all(&window, "app://notification", serde_json::json!({
+                "type": "error",
+                "value": format!("Failed cleaning up the model directory because: {err:#}"),
+            }));
+
+            0
+        }
+    }
+}
+
+#[tauri::command]
+fn get_transcription_settings(model: transcriber::Model) -> configuration::TranscriptionSettings {
+    configuration::TranscriptionConfig::default().settings_for(&model)
+}
+
+#[tauri::command]
+fn set_transcription_settings(model: transcriber::Model, mut settings: configuration::TranscriptionSettings) {
+    println!("Updating transcription settings of {} to {settings:?}", model.id());
+
+    settings.speaker_turns &= model.supports_speaker_turns();
+
+    let mut config = configuration::TranscriptionConfig::default();
+    config.models.insert(model.id().to_owned(), settings);
+    configuration::save(&config);
+}
+
+/// Go back to the model's default settings
+#[tauri::command]
+fn reset_transcription_settings(model: transcriber::Model) -> configuration::TranscriptionSettings {
+    let mut config = configuration::TranscriptionConfig::default();
+    config.models.remove(model.id());
+    configuration::save(&config);
+
+    model.default_settings()
+}
+
+#[tauri::command]
+fn get_vad_config() -> configuration::VadConfig {
+    configuration::VadConfig::default()
+}
+
+#[tauri::command]
+fn set_vad_config(config: configuration::VadConfig) {
+    println!("Updating silence handling to {config:?}");
+
+    configuration::save(&config);
+}
+
+#[tauri::command]
+fn get_benchmark() -> serde_json::Value {
+    let config = configuration::BenchmarkConfig::default();
+
+    serde_json::json!({
+        "results": config.results,
+        "target_real_time_factor": config.target_real_time_factor,
+        "recommended": benchmark::recommend(&config.results, config.target_real_time_factor),
+    })
+}
+
+/// Benchmark downloaded models on the bundled clip, progress and results are emitted on the returned channel
+#[tauri::command]
+fn benchmark_models(window: Window, target_real_time_factor: Option<f32>) -> String {
+    let channel_name = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos().to_string();
+
+    let channel = channel_name.clone();
+    std::thread::spawn(move || {
+        let results = window.app_handle().path_resolver().resolve_resource(benchmark::CLIP)
+            .context("Benchmark clip is missing")
+            .and_then(|clip| benchmark::run(&clip, |model, index, count| {
+                window.emit(&channel, serde_json::json!({
+                    "type": "progress",
+                    "value": { "model": model.name(), "index": index, "count": count },
+                })).unwrap();
+            }));
+
+        let results = match results {
+            Ok(results) => results,
+            Err(err) => {
+                window.emit(&channel, serde_json::json!({
+                    "type": "error",
+                    "value": format!("{err:#}"),
+                })).unwrap();
+                return;
+            }
+        };
+
+        let mut config = configuration::BenchmarkConfig::default();
+        config.results = results;
+        if let Some(target_real_time_factor) = target_real_time_factor {
+            config.target_real_time_factor = target_real_time_factor;
+        }
+        configuration::save(&config);
+
+        window.emit(&channel, serde_json::json!({
+            "type": "done",
+            "value": get_benchmark(),
+        })).unwrap();
+    });
+
+    channel_name
+}
+
 #[tauri::command]
 fn list_model_categories() -> Vec<serde_json::Value> {
     transcriber::Category::iter()
@@ -331,6 +977,7 @@ fn main() {
     }));
 
     gst::init().unwrap();
+    gstfallbackswitch::plugin_register_static().expect("Can't register fallbackswitch plugin");
 
     let gst_registry = gst::Registry::get();
 
@@ -338,46 +985,91 @@ fn main() {
 
     let (record_tx, mut record_rx): (RecordChannel, _) = tauri::async_runtime::channel(128);
 
-    let transcriber = Arc::new(Mutex::new(transcriber::Transcriber::new(transcriber::Model::SmallWhisper)));
+    let transcriber = Arc::new(Mutex::new(transcriber::Transcriber::new(transcriber::Model::default())));
 
     let host = cpal::default_host();
 
-    let selected_device: SelectedDevice = Arc::new(Mutex::new(recorder::SelectedDevice {
-        microphone: host.default_input_device(),
-        speaker: host.default_output_device(),
-        screen: scrap::Display::all().unwrap().swap_remove(0),
-    }));
+    let selected_device: SelectedDevice = Arc::new(Mutex::new(configuration::DeviceConfig::default()));
 
     let general_config: GeneralConfig = Arc::new(Mutex::new(configuration::GeneralConfig::default()));
     let smtp_config: SMTPConfig = Arc::new(Mutex::new(configuration::SMTPConfig::default()));
+
+    let device_watch = DeviceWatch::default();
     
     tauri::Builder::default()
         .manage(selected_device)
         .manage(transcriber.clone())
-        .manage(record_tx)
+        .manage(record_tx.clone())
         .manage(host)
         .manage(general_config.clone())
         .manage(smtp_config.clone())
+        .manage(SubtitleEditor::default())
+        .manage(device_watch.clone())
         .invoke_handler(tauri::generate_handler![
             start_record,
             stop_record,
             pause_record,
             resume_record,
+            probe_media,
             start_transcription,
+            align_transcript,
             list_model,
             list_model_categories,
             download_model,
+            import_model,
+            delete_model,
+            model_storage,
+            clean_model_storage,
+            get_transcription_settings,
+            set_transcription_settings,
+            reset_transcription_settings,
+            get_vad_config,
+            set_vad_config,
+            get_benchmark,
+            benchmark_models,
             select_model,
             select_language,
             list_microphone,
             list_speaker,
             list_screen,
+            show_devices,
             select_microphone,
             select_speaker,
             select_screen,
             preview_screen,
             get_general_config,
             set_general_config,
+            get_record_config,
+            set_record_config,
+            get_vocabulary_config,
+            set_vocabulary_config,
+            get_speaker_labels,
+            set_speaker_labels,
+            get_diarization_config,
+            set_diarization_config,
+            download_diarization_model,
+            get_translation_config,
+            set_translation_config,
+            get_bilingual_config,
+            set_bilingual_config,
+            get_cue_rules,
+            set_cue_rules,
+            get_review_config,
+            set_review_config,
+            get_redaction_config,
+            set_redaction_config,
+            get_watch_folder_config,
+            set_watch_folder_config,
+            send_transcript_email,
+            open_subtitles,
+            edit_cue,
+            shift_subtitles,
+            scale_subtitles,
+            merge_cues,
+            split_cue,
+            save_subtitles,
+            is_translation_model_downloaded,
+            download_translation_model,
             get_smtp_config,
             set_smtp_config,
             show_file,
@@ -391,183 +1083,345 @@ fn main() {
             let smtp_config = smtp_config.clone();
             let transcriber = transcriber.clone();
 
+            recorder::watch_devices(device_watch.clone(), {
+                let window = window.clone();
+                let record_tx = record_tx.clone();
+
+                move |snapshot| {
+                    util::emit_all(&window, "app://devices_changed", snapshot.clone());
+
+                    let _ = record_tx.try_send(recorder::RecordCommand::DevicesChanged(snapshot));
+                }
+            });
+
+            watch::watch_folders({
+                let window = window.clone();
+                let transcriber = transcriber.clone();
+                let was_ready = atomic::AtomicBool::new(true);
+
+                move || {
+                    let is_ready = transcriber.lock().unwrap().model().is_downloaded();
+
+                    // Once per pause, not every time the folders would be checked
+                    if was_ready.swap(is_ready, atomic::Ordering::Relaxed) && !is_ready {
+                        util::emit_all(&window, "app://notification", serde_json::json!({
+                            "type": "error",
+                            "value": "Watched folders are paused until the selected model is downloaded"
+                        }));
+                    }
+
+                    is_ready
+                }
+            }, {
+                let window = window.clone();
+                let general_config = general_config.clone();
+                let smtp_config = smtp_config.clone();
+                let transcriber = transcriber.clone();
+
+                move |media_path| {
+                    let config = configuration::WatchFolderConfig::default();
+                    let general_config = general_config.lock().unwrap().clone();
+
+                    let directory = match config.output {
+                        configuration::WatchOutput::NextToFile => media_path.parent().unwrap_or(Path::new("")).to_owned(),
+                        configuration::WatchOutput::TranscriptSaveTo => general_config.transcript_save_to.save_path.clone(),
+                    };
+
+                    let buffer = media::probe(media_path)
+                        .and_then(|info| media::MediaSelection::default().check(&info))
+                        .and_then(|()| std::fs::read(media_path).context("Failed reading the file"));
+
+                    let buffer = match buffer {
+                        Ok(buffer) => buffer,
+                        Err(err) => {
+                            // Trying again won't help a file that isn't media
+                            util::emit_all(&window, "app://notification", serde_json::json!({
+                                "type": "error",
+                                "value": format!("Can't transcribe {} from a watched folder because: {err:#}", media_path.display())
+                            }));
+                            return true;
+                        }
+                    };
+
+                    util::emit_all(&window, "app://notification", serde_json::json!({
+                        "type": "info",
+                        "value": format!("Transcribing {} from a watched folder", media_path.display())
+                    }));
+
+                    let source_levels = speaker::SourceLevels::load(media_path);
+                    let smtp_config = smtp_config.lock().unwrap().clone();
+                    let save_to = transcription_path(&directory, media_path);
+
+                    let transcription = transcriber.lock().unwrap()
+                        .transcribe(&window, buffer, media::MediaSelection::default(), source_levels, general_config, smtp_config, save_to, config.email);
+
+                    // One file at a time, the next waits until this one is transcribed
+                    let Some(transcription) = transcription else { return false };
+
+                    transcription.join().unwrap_or(false)
+                }
+            });
+
+            let record_tx = record_tx.clone();
+
             std::thread::spawn(move || {
                 let media_data = Arc::new(Mutex::new(Vec::<u8>::new()));
                 let mut output_name = String::new();
+                let mut output_path = PathBuf::new();
+                let mut running_mode = recorder::RecordMode::Screen;
 
                 let mut should_stop: Option<Arc<atomic::AtomicBool>> = None;
                 let mut running_pipeline: Option<gst::Pipeline> = None;
+                let mut running_capture_clock: Option<recorder::CaptureClock> = None;
+                let mut audio_sources: Vec<recorder::AudioSource> = Vec::new();
+                let mut running_levels: Option<Arc<Mutex<speaker::SourceLevels>>> = None;
         
                 loop {
                     let Some(command) = record_rx.blocking_recv() else { continue };
 
                     match command {
-                        recorder::RecordCommand::Start(selected_device) => {
-                            let recording_duration = std::time::Instant::now();
+                        recorder::RecordCommand::Start(selected_device, mode) => {
+                            let screen = match mode {
+                                recorder::RecordMode::Screen => {
+                                    let Some((screen_id, display)) = recorder::find_screen(selected_device.screen.as_ref()) else {
+                                        util::emit_all(&window, "app://notification", serde_json::json!({
+                                            "type": "error",
+                                            "value": "No screen is available for recording"
+                                        }));
+
+                                        continue;
+                                    };
+
+                                    notify_device_fallback(&window, selected_device.screen.as_ref(), Some(&screen_id));
+
+                                    Some((screen_id, display))
+                                },
+                                recorder::RecordMode::Audio(_) => None,
+                            };
+
+                            let microphone = recorder::find_microphone(selected_device.microphone.as_ref());
+                            let speaker = recorder::find_speaker(selected_device.speaker.as_ref());
+
+                            notify_device_fallback(&window, selected_device.microphone.as_ref(), microphone.as_ref().map(|(id, _)| id));
+                            notify_device_fallback(&window, selected_device.speaker.as_ref(), speaker.as_ref().map(|(id, _)| id));
+
+                            let capture_clock = recorder::CaptureClock::new();
 
                             should_stop = Some(Arc::new(atomic::AtomicBool::new(false)));
                             
                             let mut pipeline_description = Vec::new();
 
                             let mut input_callbacks: Vec<(String, gst_app::AppSrcCallbacks)> = Vec::new();
-                            let mut audio_streams: Vec<cpal::Stream> = Vec::new();
+                            let mut input_caps: Vec<(String, gst::Caps)> = Vec::new();
+                            let mut level_sources: Vec<(String, recorder::DeviceType)> = Vec::new();
 
                             output_name = format!(
                                 "{date}",
                                 date = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"));
 
-                            pipeline_description.push("audiomixer name=audio_mixer ! avenc_aac ! multiqueue name=q max-size-buffers=0 max-size-bytes=0 max-size-time=0 ! mux.".to_owned());
+                            pipeline_description.push(match mode {
+                                recorder::RecordMode::Screen => "audiomixer name=audio_mixer ! avenc_aac ! multiqueue name=q max-size-buffers=0 max-size-bytes=0 max-size-time=0 ! mux.".to_owned(),
+                                recorder::RecordMode::Audio(format) => format!("audiomixer name=audio_mixer ! {} ! filesink name=output", format.encoder()),
+                            });
 
-                            for (index, device) in [selected_device.microphone, selected_device.speaker].into_iter().enumerate() {
+                            let devices = [
+                                (recorder::DeviceType::Microphone, microphone),
+                                (recorder::DeviceType::Speaker, speaker),
+                            ];
+
+                            for (index, (device_type, device)) in devices.into_iter().enumerate() {
                                 let Some(device) = device else { continue };
 
-                                let config = match index {
-                                    0 => device.default_input_config().unwrap(),
-                                    1 => {
-                                        let config = device.default_output_config().unwrap();
-                                        
-                                        let channels = config.channels();
-
-                                        let stream = device.build_output_stream_raw(&config.config(), config.sample_format(), {
-                                            move |data, _: &_| {
-                                                let sample: Vec<u8> = match data.sample_format() {
-                                                    cpal::SampleFormat::I8 => bytemuck::cast_slice(&vec![0_i8; channels as _]).to_owned(),
-                                                    cpal::SampleFormat::U8 => bytemuck::cast_slice(&vec![0_u8; channels as _]).to_owned(),
-                                                    cpal::SampleFormat::I16 => bytemuck::cast_slice(&vec![0_i16; channels as _]).to_owned(),
-                                                    cpal::SampleFormat::U16 => bytemuck::cast_slice(&vec![0_u16; channels as _]).to_owned(),
-                                                    cpal::SampleFormat::I32 => bytemuck::cast_slice(&vec![0_i32; channels as _]).to_owned(),
-                                                    cpal::SampleFormat::U32 => bytemuck::cast_slice(&vec![0_u32; channels as _]).to_owned(),
-                                                    cpal::SampleFormat::F32 => bytemuck::cast_slice(&vec![0_f32; channels as _]).to_owned(),
-                                                    cpal::SampleFormat::F64 => bytemuck::cast_slice(&vec![0_f64; channels as _]).to_owned(),
-                                                    _ => { unreachable!() }
-                                                };
+                                let device_name = device.0.name.clone();
+                                let audio_input_name = format!("audio_{index:?}");
 
-                                                data.bytes_mut().write_all(&sample).unwrap();
-                                            }
-                                        }, |_| { }, None).unwrap();
+                                let on_lost: Arc<dyn Fn(recorder::DeviceType) + Send + Sync> = Arc::new({
+                                    let record_tx = record_tx.clone();
 
-                                        audio_streams.push(stream);
+                                    move |device_type| {
+                                        let _ = record_tx.try_send(recorder::RecordCommand::DeviceLost(device_type));
+                                    }
+                                });
 
-                                        config
-                                    },
-                                    _ => unreachable!(),
-                                };
+                                let result = recorder::AudioSource::open(device_type, device, audio_input_name.clone(), &capture_clock, on_lost)
+                                    .and_then(|(source, receiver)| Ok((source.caps()?, source, receiver)));
 
-                                let sample_format = config.sample_format();
-                                let channels = config.channels();
+                                let (caps, source, audio_tx) = match result {
+                                    Ok(result) => result,
+                                    Err(err) => {
+                                        eprintln!("Skipping {device_type:?} because: {err:?}");
 
-                                let audio_input_name = format!("audio_{index:?}");
+                                        util::emit_all(&window, "app://notification", serde_json::json!({
+                                            "type": "error",
+                                            "value": format!("{} \"{device_name}\" can't be recorded because: {err}", <&str>::from(device_type))
+                                        }));
+
+                                        continue;
+                                    },
+                                };
 
+                                // Silence takes over whenever the device stops delivering, e.g. when it's unplugged,
+                                // the level of each source is measured before mixing to tell who's talking
                                 pipeline_description.push(
                                     format!(
-                                       "appsrc name={audio_input_name} !
-                                            rawaudioparse pcm-format={format} sample-rate={rate} num-channels={channels} ! audioconvert ! audioresample !
-                                        {queue} ! audio_mixer.",
-                                            format =
-                                                // https://gstreamer.freedesktop.org/documentation/additional/design/mediatype-audio-raw.html#formats
-                                                // https://gstreamer.freedesktop.org/documentation/audio/audio-format.html#GstAudioFormat
-                                                match sample_format {
-                                                    cpal::SampleFormat::I8 => 2,
-                                                    cpal::SampleFormat::U8 => 3,
-                                                    cpal::SampleFormat::I16 => 4,
-                                                    cpal::SampleFormat::U16 => 6,
-                                                    cpal::SampleFormat::I32 => 12,
-                                                    cpal::SampleFormat::U32 => 14,
-                                                    cpal::SampleFormat::F32 => 28,
-                                                    cpal::SampleFormat::F64 => 30,
-                                                    format => unimplemented!("SampleFormat {format} is not supported yet")
-                                                },
-                                            rate = config.sample_rate().0,
+                                       "appsrc name={audio_input_name} format=time is-live=true ! audioconvert ! audioresample ! {mixer_caps} !
+                                            fallbackswitch name={audio_input_name}_switch timeout={timeout} immediate-fallback=true !
+                                            level name={audio_input_name}_level interval={interval} post-messages=true !
+                                        {queue} ! audio_mixer.
+                                        audiotestsrc wave=silence is-live=true ! {mixer_caps} ! {audio_input_name}_switch.",
+                                            mixer_caps = recorder::MIXER_AUDIO_CAPS,
+                                            timeout = recorder::AUDIO_FALLBACK_TIMEOUT.as_nanos(),
+                                            interval = speaker::LEVEL_INTERVAL.as_nanos(),
                                             queue = if input_callbacks.is_empty() { "multiqueue name=a" } else { "a. a." }
                                     )
                                 );
 
-                                let (audio_rx, audio_tx) = std::sync::mpsc::sync_channel::<Vec<u8>>(0);
-
-                                let stream = device.build_input_stream_raw(&config.into(), sample_format, {
-                                    move |data: &cpal::Data, _: &_| {
-                                        let _ = audio_rx.send(data.bytes().to_vec());
-                                    }
-                                }, |error| panic!("{error}"), None).unwrap();
+                                input_caps.push((audio_input_name.clone(), caps));
+                                level_sources.push((format!("{audio_input_name}_level"), device_type));
 
-                                audio_streams.push(stream);
-                                                        
                                 let audio_input_callbacks = gst_app::AppSrcCallbacks::builder()
                                     .need_data({
                                         let should_stop = should_stop.clone();
 
                                         move |source, _| {
-                                            let Ok(sample) = audio_tx.recv() else {
-                                                source.end_of_stream().unwrap();
-                                                return;
+                                            let (timestamp, sample) = loop {
+                                                if should_stop.clone().unwrap().load(atomic::Ordering::Acquire) {
+                                                    println!("Stopping audio recorder");
+                                                    source.end_of_stream().unwrap();
+                                                    return;
+                                                }
+
+                                                match audio_tx.recv_timeout(std::time::Duration::from_millis(100)) {
+                                                    Ok(sample) => break sample,
+                                                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
+                                                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                                                        source.end_of_stream().unwrap();
+                                                        return;
+                                                    },
+                                                }
                                             };
     
-                                            let pts = std::time::Instant::now() - recording_duration;
-    
                                             let mut buffer = gst::Buffer::from_slice(sample);
-                                            buffer.get_mut().unwrap().set_pts(Some(gst::ClockTime::from_seconds_f64(pts.as_secs_f64())));
+                                            {
+                                                let buffer = buffer.get_mut().unwrap();
+
+                                                buffer.set_pts(gst::ClockTime::try_from(timestamp.pts).ok());
+                                                buffer.set_duration(gst::ClockTime::try_from(timestamp.duration).ok());
+
+                                                if timestamp.discont {
+                                                    buffer.set_flags(gst::BufferFlags::DISCONT);
+                                                }
+                                            }
     
                                             let _ = source.push_buffer(buffer);
-                                        
-                                            if should_stop.clone().unwrap().load(atomic::Ordering::Acquire) {
-                                                println!("Stopping audio recorder");
-                                                source.end_of_stream().unwrap();
-                                            }
                                         }
                                     })
                                     .build();
 
                                 input_callbacks.push((audio_input_name, audio_input_callbacks));
+                                audio_sources.push(source);
                             }
 
-                            {
-                                let display = selected_device.screen;
+                            if mode != recorder::RecordMode::Screen && audio_sources.is_empty() {
+                                util::emit_all(&window, "app://notification", serde_json::json!({
+                                    "type": "error",
+                                    "value": "No audio device is available for recording"
+                                }));
+
+                                continue;
+                            }
 
+                            if let Some((screen_id, display)) = screen {
                                 let video_input_name = "video_0";
 
+                                let width = display.width();
+                                let height = display.height();
+
+                                let capturer = match scrap::Capturer::new(display) {
+                                    Ok(capturer) => capturer,
+                                    Err(err) => {
+                                        util::emit_all(&window, "app://notification", serde_json::json!({
+                                            "type": "error",
+                                            "value": format!("Screen \"{}\" can't be recorded because: {err}", screen_id.name)
+                                        }));
+
+                                        audio_sources.clear();
+
+                                        continue;
+                                    },
+                                };
+
                                 pipeline_description.push(format!(
-                                    "appsrc name=\"{video_input_name}\" ! rawvideoparse width={width} height={height} format=8 ! videoconvert ! x264enc speed-preset=veryfast tune=zerolatency ! video/x-h264,profile=baseline ! q. q. ! mux.",
-                                        width = display.width(),
-                                        height = display.height(),
+                                    "appsrc name=\"{video_input_name}\" format=time is-live=true ! rawvideoparse width={width} height={height} format=8 ! videoconvert ! x264enc speed-preset=veryfast tune=zerolatency ! video/x-h264,profile=baseline ! q. q. ! mux.",
                                 ));
 
-                                let capturer = Arc::new(atomic::AtomicPtr::new(Box::into_raw(Box::new(scrap::Capturer::new(display).unwrap()))));
+                                let capturer = Arc::new(atomic::AtomicPtr::new(Box::into_raw(Box::new(Some(capturer)))));
 
                                 let video_input_callbacks = gst_app::AppSrcCallbacks::builder()
                                     .need_data({
                                         let should_stop = should_stop.clone();
+                                        let capture_clock = capture_clock.clone();
+
+                                        let black_frame = vec![0u8; width * height * 4];
+                                        let last_reconnect = Mutex::new(std::time::Instant::now());
                                     
                                         move |source, _| {
-                                            let pixel_buffer = loop {
+                                            let capturer = unsafe { &mut *capturer.load(atomic::Ordering::Acquire) };
+
+                                            let (capture_time, data) = loop {
                                                 if should_stop.clone().unwrap().load(atomic::Ordering::Acquire) {
                                                     println!("Stopping video recorder");
                                                     source.end_of_stream().unwrap();
                                                     return;
                                                 }
 
-                                                let frame = match unsafe { &mut *capturer.load(atomic::Ordering::Acquire) }.frame(std::time::Duration::ZERO) {
-                                                    Ok(frame) => frame,
+                                                let Some(active_capturer) = capturer.as_mut() else {
+                                                    // The screen is gone, keep the recording going with black frames until it's back
+                                                    std::thread::sleep(recorder::BLACK_FRAME_INTERVAL);
+
+                                                    let mut last_reconnect = last_reconnect.lock().unwrap();
+
+                                                    if last_reconnect.elapsed() > recorder::SCREEN_RECONNECT_INTERVAL {
+                                                        *last_reconnect = std::time::Instant::now();
+                                                        *capturer = recorder::reconnect_screen(&screen_id, width, height);
+
+                                                        if capturer.is_some() {
+                                                            println!("Screen {screen_id} is reconnected");
+                                                            continue;
+                                                        }
+                                                    }
+
+                                                    break (capture_clock.now(), black_frame.clone());
+                                                };
+
+                                                let frame = match active_capturer.frame(std::time::Duration::ZERO) {
+                                                    Ok(frame) => Some((capture_clock.now(), frame)),
                                                     Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => continue,
                                                     Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
                                                         eprintln!("Received invalid data, skipping");
                                                         continue
                                                     },
-                                                    Err(err) => panic!("{err}"),
+                                                    Err(err) => {
+                                                        eprintln!("Screen {screen_id} can't be captured because: {err}, recording black frames");
+                                                        None
+                                                    },
                                                 };
 
-                                                let scrap::Frame::PixelBuffer(pixel_buffer) = frame else {
+                                                let Some(frame) = frame else {
+                                                    *capturer = None;
+                                                    continue
+                                                };
+
+                                                let (capture_time, scrap::Frame::PixelBuffer(pixel_buffer)) = frame else {
                                                     eprintln!("Received frame is not PixelBuffer, skipping");
                                                     continue
                                                 };
 
-                                                break pixel_buffer;
+                                                break (capture_time, pixel_buffer.data().to_vec());
                                             };
 
-                                            let pts = std::time::Instant::now() - recording_duration;
-
-                                            let data = pixel_buffer.data();
-                                            let mut buffer = gst::Buffer::from_slice(data.to_vec());
-                                            buffer.get_mut().unwrap().set_pts(Some(gst::ClockTime::from_seconds_f64(pts.as_secs_f64())));
+                                            let mut buffer = gst::Buffer::from_slice(data);
+                                            buffer.get_mut().unwrap().set_pts(gst::ClockTime::try_from(capture_time).ok());
 
                                             let _ = source.push_buffer(buffer);
                                         }
@@ -575,9 +1429,9 @@ fn main() {
                                     .build();
 
                                 input_callbacks.push((video_input_name.to_owned(), video_input_callbacks));
-                            }
 
-                            pipeline_description.push("mp4mux name=mux faststart=true ! appsink name=output".to_string());
+                                pipeline_description.push("mp4mux name=mux faststart=true ! appsink name=output sync=false".to_string());
+                            }
 
                             println!("Starting pipeline with description: {}", util::replace_multiple_whitespace(&pipeline_description.join("|")));
 
@@ -593,11 +1447,24 @@ fn main() {
                                 source.set_callbacks(callback);
                             }
 
-                            let output_path = general_config.lock().unwrap().save_to.save_path.join(format!("{output_name}.mp4"));
+                            for (name, caps) in input_caps {
+                                pipeline.by_name(&name).unwrap()
+                                    .dynamic_cast::<gst_app::AppSrc>().unwrap()
+                                    .set_caps(Some(&caps));
+
+                                recorder::prioritize_fallback_pads(&pipeline.by_name(&format!("{name}_switch")).unwrap());
+                            }
+
+                            output_path = general_config.lock().unwrap().save_to.save_path.join(format!("{output_name}.{}", mode.extension()));
+                            running_mode = mode;
 
-                            let mut output = File::create(output_path).unwrap();
+                            if let recorder::RecordMode::Audio(_) = mode {
+                                // Written straight to the file so the encoder can seek back to finalize its header
+                                pipeline.by_name("output").unwrap().set_property("location", &output_path);
+                            } else {
+                                let mut output = File::create(&output_path).unwrap();
 
-                            pipeline.by_name("output").unwrap().dynamic_cast::<gst_app::AppSink>().unwrap()
+                                pipeline.by_name("output").unwrap().dynamic_cast::<gst_app::AppSink>().unwrap()
                                 .set_callbacks(gst_app::AppSinkCallbacks::builder()
                                     .new_sample({
                                         let media_data = media_data.clone();
@@ -615,28 +1482,49 @@ fn main() {
                                         }
                                     })
                                     .build());
+                            }
+
+                            capture_clock.attach(&pipeline);
 
                             pipeline.set_state(gst::State::Playing).unwrap();
 
-                            let audio_streams = atomic::AtomicPtr::new(Box::into_raw(Box::new(audio_streams)));
+                            for source in &audio_sources {
+                                if let Err(err) = source.play() {
+                                    eprintln!("Can't play {:?} because: {err}", source.device_type);
+                                }
+                            }
+
+                            let levels = Arc::new(Mutex::new(speaker::SourceLevels::default()));
 
                             std::thread::spawn({
                                 let pipeline = pipeline.clone();
+                                let levels = levels.clone();
 
                                 move || {
-                                    let audio_streams = unsafe { Box::from_raw(audio_streams.load(atomic::Ordering::Acquire)) };
+                                    util::gstreamer_loop(pipeline, |message| {
+                                        let gst::MessageView::Element(element) = message.view() else { return false };
+                                        let Some(structure) = element.structure().filter(|structure| structure.name() == "level") else { return false };
 
-                                    for stream in audio_streams.iter() {
-                                        stream.play().unwrap();
-                                    }
+                                        let Some((_, device_type)) = message.src().and_then(|src| level_sources.iter().find(|(name, _)| name.as_str() == src.name().as_str())) else { return false };
 
-                                    util::gstreamer_loop(pipeline, |_| { false }).unwrap();
+                                        let (Ok(running_time), Ok(rms)) = (structure.get::<gst::ClockTime>("running-time"), structure.get::<gst::glib::ValueArray>("rms")) else { return false };
+
+                                        // Loudest channel, in dB
+                                        let db = rms.iter().filter_map(|value| value.get::<f64>().ok()).fold(f64::NEG_INFINITY, f64::max);
+
+                                        levels.lock().unwrap().push(*device_type, running_time.mseconds() as f32 / 1000.0, db as f32);
+
+                                        false
+                                    }).unwrap();
                                
                                     println!("Closing pipeline");
                                 }
                             });
 
                             running_pipeline = Some(pipeline);
+                            device_watch.is_recording.store(true, atomic::Ordering::Relaxed);
+                            running_capture_clock = Some(capture_clock);
+                            running_levels = Some(levels);
 
                             recorder_control_window.show().unwrap();
                             recorder_control_window.set_always_on_top(true).unwrap();
@@ -644,6 +1532,20 @@ fn main() {
 
                             window.emit("app://recording_state", "start").unwrap();
                         },
+                        recorder::RecordCommand::DeviceLost(device_type) => {
+                            let (Some(pipeline), Some(capture_clock)) = (&running_pipeline, &running_capture_clock) else { continue };
+
+                            for source in audio_sources.iter_mut().filter(|source| source.device_type == device_type && !source.is_lost) {
+                                recover_audio_source(&window, source, pipeline, capture_clock);
+                            }
+                        },
+                        recorder::RecordCommand::DevicesChanged(snapshot) => {
+                            let (Some(pipeline), Some(capture_clock)) = (&running_pipeline, &running_capture_clock) else { continue };
+
+                            for source in audio_sources.iter_mut().filter(|source| source.is_lost || !snapshot.contains(source.device_type, &source.id)) {
+                                recover_audio_source(&window, source, pipeline, capture_clock);
+                            }
+                        },
                         recorder::RecordCommand::Pause => {
                             if let Some(pipeline) = &running_pipeline {
                                 pipeline.set_state(gst::State::Paused).unwrap();
@@ -679,22 +1581,42 @@ fn main() {
                                 continue;
                             }
 
-                            let mut media_data = media_data.lock().unwrap();
+                            let data = match running_mode {
+                                recorder::RecordMode::Screen => {
+                                    let mut media_data = media_data.lock().unwrap();
 
-                            let mut data = vec![0u8; media_data.len()];
-                            media_data.swap_with_slice(&mut data);
-                            media_data.clear();
+                                    let mut data = vec![0u8; media_data.len()];
+                                    media_data.swap_with_slice(&mut data);
+                                    media_data.clear();
+
+                                    data
+                                },
+                                recorder::RecordMode::Audio(_) => std::fs::read(&output_path).unwrap_or_default(),
+                            };
 
                             running_pipeline = None;
+                            running_capture_clock = None;
+                            device_watch.is_recording.store(false, atomic::Ordering::Relaxed);
+                            audio_sources.clear();
+
+                            let source_levels = running_levels.take().map(|levels| levels.lock().unwrap().clone());
+
+                            if let Some(Err(err)) = source_levels.as_ref().map(|levels| levels.save(&output_path)) {
+                                eprintln!("Can't save audio levels of {} because: {err}", output_path.display());
+                            }
 
                             let general_config = general_config.lock().unwrap().clone();
-                            let video_output_path = &general_config.save_to.save_path.join(format!("{output_name}.mp4"));
+
+                            let recording_kind = match running_mode {
+                                recorder::RecordMode::Screen => "Screen",
+                                recorder::RecordMode::Audio(_) => "Audio",
+                            };
 
                             util::emit_all(&window, "app://notification", serde_json::json!({
                                 "type": "link",
                                 "value": serde_json::json!({
-                                    "message": format!("Screen recording is saved at\n{}", video_output_path.display()),
-                                    "at": video_output_path
+                                    "message": format!("{recording_kind} recording is saved at\n{}", output_path.display()),
+                                    "at": output_path
                                 })
                             }));
 
@@ -702,7 +1624,7 @@ fn main() {
                                 let transcription_path = general_config.save_to.save_path.join(format!("{output_name}.srt"));
 
                                 transcriber.lock().unwrap()
-                                    .transcribe(&window, data, general_config, smtp_config.lock().unwrap().clone(), transcription_path, true);
+                                    .transcribe(&window, data, media::MediaSelection::default(), source_levels, general_config, smtp_config.lock().unwrap().clone(), transcription_path, true);
                             }
                         },
                     }
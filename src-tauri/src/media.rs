@@ -0,0 +1,193 @@
+use std::path::Path;
+
+use anyhow::Context as _;
+
+use gst_pbutils::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::timestamp::Timestamp;
+
+/// How long discovering a file may take before it's taken as unreadable
+const DISCOVER_TIMEOUT_SECONDS: u64 = 15;
+
+fn codec(caps: Option<gst::Caps>) -> String {
+    caps.map_or_else(|| "unknown".to_owned(), |caps| gst_pbutils::pb_utils_get_codec_description(&caps).to_string())
+}
+
+/// Audio stream of a media file, numbered like [`MediaSelection::audio_stream`]
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioStream {
+    pub index: usize,
+    pub codec: String,
+    pub language: Option<String>,
+    pub channels: u32,
+    pub sample_rate: u32,
+    /// Bits per second, `None` when the file doesn't tell
+    pub bitrate: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoStream {
+    pub index: usize,
+    pub codec: String,
+    pub language: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    /// Frames per second, `None` for variable framerates
+    pub framerate: Option<f64>,
+}
+
+/// What the GStreamer discoverer found out about a media file
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaInfo {
+    pub duration: Option<Timestamp>,
+    /// Container format, e.g. "Matroska", `None` for bare streams
+    pub container: Option<String>,
+    pub seekable: bool,
+    pub audio_streams: Vec<AudioStream>,
+    pub video_streams: Vec<VideoStream>,
+    /// Seconds the chosen model needs for the whole file, `None` until a model is benchmarked
+    pub transcription_eta: Option<f32>,
+}
+
+/// Part of a media file to transcribe
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MediaSelection {
+    /// The start of the file when `None`
+    pub start: Option<Timestamp>,
+    /// The end of the file when `None`
+    pub end: Option<Timestamp>,
+    /// Index among the audio streams of the file in their container order, the first one when `None`
+    pub audio_stream: Option<usize>,
+}
+
+impl MediaSelection {
+    /// Make sure the range and stream exist in the file described by `info`
+    pub fn check(&self, info: &MediaInfo) -> anyhow::Result<()> {
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            anyhow::ensure!(start < end, "The range ends at {} before starting at {}", end.srt(), start.srt());
+        }
+
+        if let (Some(start), Some(duration)) = (self.start, info.duration) {
+            anyhow::ensure!(start < duration, "The range starts at {} after the file ends at {}", start.srt(), duration.srt());
+        }
+
+        anyhow::ensure!(!info.audio_streams.is_empty(), "The file has no audio");
+
+        if let Some(audio_stream) = self.audio_stream {
+            anyhow::ensure!(audio_stream < info.audio_streams.len(), "The file has no audio stream {audio_stream}, it has {}", info.audio_streams.len());
+        }
+
+        Ok(())
+    }
+
+    /// Samples of `pcm`, decoded at `sample_rate`, in the range
+    pub fn slice<'a>(&self, pcm: &'a [f32], sample_rate: usize) -> &'a [f32] {
+        let sample = |at: Timestamp| (at.since(Timestamp::default()) as usize * sample_rate / 1000).min(pcm.len());
+
+        let start = self.start.map_or(0, sample);
+        let end = self.end.map_or(pcm.len(), sample).max(start);
+
+        &pcm[start..end]
+    }
+}
+
+/// Duration, streams and codecs of the media file at `path`, without reading all of it
+pub fn probe(path: &Path) -> anyhow::Result<MediaInfo> {
+    let uri = gst::glib::filename_to_uri(path, None).with_context(|| format!("{} is not a valid path", path.display()))?;
+
+    let discoverer = gst_pbutils::Discoverer::new(gst::ClockTime::from_seconds(DISCOVER_TIMEOUT_SECONDS))?;
+    let info = discoverer.discover_uri(&uri).with_context(|| format!("Can't read {}", path.display()))?;
+
+    let audio_streams = info.audio_streams().iter()
+        .enumerate()
+        .map(|(index, stream)| AudioStream {
+            index,
+            codec: codec(stream.caps()),
+            language: stream.language().map(|language| language.to_string()),
+            channels: stream.channels(),
+            sample_rate: stream.sample_rate(),
+            bitrate: (stream.bitrate() > 0).then(|| stream.bitrate()),
+        })
+        .collect();
+
+    let video_streams = info.video_streams().iter()
+        // Cover art is listed as a video stream
+        .filter(|stream| !stream.is_image())
+        .enumerate()
+        .map(|(index, stream)| {
+            let framerate = stream.framerate();
+
+            VideoStream {
+                index,
+                codec: codec(stream.caps()),
+                language: stream.tags().and_then(|tags| tags.get::<gst::tags::LanguageCode>().map(|language| language.get().to_owned())),
+                width: stream.width(),
+                height: stream.height(),
+                framerate: (framerate.numer() > 0 && framerate.denom() > 0).then(|| framerate.numer() as f64 / framerate.denom() as f64),
+            }
+        })
+        .collect();
+
+    let container = info.stream_info()
+        .filter(|stream| stream.is::<gst_pbutils::DiscovererContainerInfo>())
+        .map(|stream| codec(stream.caps()));
+
+    Ok(MediaInfo {
+        duration: info.duration().map(|duration| Timestamp::from_millis(duration.mseconds() as i64)),
+        container,
+        seekable: info.is_seekable(),
+        audio_streams,
+        video_streams,
+        transcription_eta: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(duration: i64, audio_streams: usize) -> MediaInfo {
+        MediaInfo {
+            duration: Some(Timestamp::from_millis(duration)),
+            audio_streams: (0..audio_streams)
+                .map(|index| AudioStream { index, codec: "Opus".to_owned(), language: None, channels: 2, sample_rate: 48000, bitrate: None })
+                .collect(),
+            container: Some("Matroska".to_owned()),
+            seekable: true,
+            video_streams: Vec::new(),
+            transcription_eta: None,
+        }
+    }
+
+    #[test]
+    fn checks_the_selection_exists() {
+        let selection = |start: Option<i64>, end: Option<i64>, audio_stream| MediaSelection {
+            start: start.map(Timestamp::from_millis),
+            end: end.map(Timestamp::from_millis),
+            audio_stream,
+        };
+
+        assert!(MediaSelection::default().check(&info(10_000, 1)).is_ok());
+        assert!(selection(Some(2000), Some(5000), Some(1)).check(&info(10_000, 2)).is_ok());
+
+        assert!(MediaSelection::default().check(&info(10_000, 0)).is_err());
+        assert!(selection(None, None, Some(1)).check(&info(10_000, 1)).is_err());
+        assert!(selection(Some(5000), Some(2000), None).check(&info(10_000, 1)).is_err());
+        assert!(selection(Some(12_000), None, None).check(&info(10_000, 1)).is_err());
+    }
+
+    #[test]
+    fn slices_the_range() {
+        let pcm: Vec<f32> = (0..100).map(|sample| sample as f32).collect();
+
+        let selection = MediaSelection { start: Some(Timestamp::from_millis(200)), end: Some(Timestamp::from_millis(500)), audio_stream: None };
+        assert_eq!(selection.slice(&pcm, 100), &pcm[20..50]);
+
+        let selection = MediaSelection { start: Some(Timestamp::from_millis(900)), end: Some(Timestamp::from_millis(5000)), audio_stream: None };
+        assert_eq!(selection.slice(&pcm, 100), &pcm[90..]);
+
+        assert_eq!(MediaSelection::default().slice(&pcm, 100), &pcm[..]);
+    }
+}
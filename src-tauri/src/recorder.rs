@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use cpal::traits::DeviceTrait as _;
 use cpal::traits::HostTrait as _;
@@ -8,6 +11,8 @@ use scrap::Display;
 use scrap::TraitCapturer as _;
 use scrap::TraitPixelBuffer as _;
 
+use serde::{Deserialize, Serialize};
+
 use strum_macros::{EnumIter, IntoStaticStr};
 
 #[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, EnumIter, IntoStaticStr)]
@@ -16,67 +21,120 @@ pub enum DeviceType {
     Speaker,
 }
 
-pub struct SelectedDevice {
-    pub microphone: Option<Device>,
-    pub speaker: Option<Device>,
-    pub screen: Display,
+/// Host name used in the [`DeviceId`] of screens
+const SCREEN_HOST: &str = "Screen";
+
+/// Identity of a device that stays the same across launches
+///
+/// Devices sharing a name (e.g. two identical USB headsets) are told apart by their enumeration order,
+/// screen names are the RandR output names on X11.
+/// Serialized as `host:index:name` so the frontend can use it as a plain value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct DeviceId {
+    pub host: String,
+    pub name: String,
+    pub index: usize,
 }
 
-unsafe impl Send for SelectedDevice { }
+impl std::fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.host, self.index, self.name)
+    }
+}
 
-impl Clone for SelectedDevice {
-    fn clone(&self) -> Self {
-        Self {
-            microphone: self.microphone.clone(),
-            speaker: self.speaker.clone(),
-            screen: self.screen.clone_device(),
-        }
+impl std::str::FromStr for DeviceId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ':');
+
+        let (Some(host), Some(index), Some(name)) = (parts.next(), parts.next(), parts.next()) else {
+            anyhow::bail!("Invalid device id {s:?}");
+        };
+
+        Ok(Self {
+            host: host.to_owned(),
+            name: name.to_owned(),
+            index: index.parse()?,
+        })
     }
 }
 
-pub trait DeviceEq {
-    fn eq_device(&self, device: &Self) -> bool;
+impl From<DeviceId> for String {
+    fn from(id: DeviceId) -> Self {
+        id.to_string()
+    }
 }
 
-impl DeviceEq for Device {
-    fn eq_device(&self, device: &Device) -> bool {
-        let a = self.name().unwrap_or_default();
-        let b = device.name().unwrap_or_default();
+impl TryFrom<String> for DeviceId {
+    type Error = anyhow::Error;
 
-        a.eq(&b)
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
     }
 }
 
-impl DeviceEq for Display {
-    fn eq_device(&self, device: &Display) -> bool {
-        let a = self.name();
-        let b = device.name();
+/// Container and codec of audio-only recordings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum AudioFileFormat {
+    Opus,
+    Flac,
+    Wav,
+    M4a,
+}
 
-        a.eq(&b)
+impl AudioFileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFileFormat::Opus => "ogg",
+            AudioFileFormat::Flac => "flac",
+            AudioFileFormat::Wav => "wav",
+            AudioFileFormat::M4a => "m4a",
+        }
+    }
+
+    /// Elements encoding the mixed audio into this format
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            AudioFileFormat::Opus => "audioconvert ! opusenc ! oggmux",
+            AudioFileFormat::Flac => "audioconvert ! flacenc",
+            AudioFileFormat::Wav => "audioconvert ! wavenc",
+            AudioFileFormat::M4a => "audioconvert ! avenc_aac ! mp4mux faststart=true",
+        }
     }
 }
 
-pub trait DeviceClone {
-    fn clone_device(&self) -> Self;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecordMode {
+    /// Screen and audio into an MP4 file
+    Screen,
+    /// Only the selected audio sources, without capturing the screen
+    Audio(AudioFileFormat),
 }
 
-impl DeviceClone for Display {
-    fn clone_device(&self) -> Self {
-        list_screen().unwrap().into_iter()
-            .filter(|d| d.eq_device(self))
-            .nth(0).unwrap()
+impl RecordMode {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordMode::Screen => "mp4",
+            RecordMode::Audio(format) => format.extension(),
+        }
     }
 }
 
 pub enum RecordCommand {
-    Start(SelectedDevice),
+    Start(crate::configuration::DeviceConfig, RecordMode),
     Pause,
     Resume,
-    Stop
+    Stop,
+    /// An audio device of the running recording has been disconnected
+    DeviceLost(DeviceType),
+    DevicesChanged(DeviceSnapshot),
 }
 
 #[derive(serde::Serialize)]
 pub struct DeviceResult {
+    pub id: DeviceId,
     pub name: String,
     pub is_selected: bool,
 }
@@ -88,42 +146,538 @@ fn all_hosts() -> Vec<Host> {
         .collect()
 }
 
-pub fn list_microphone() -> Vec<Device> {
-    all_hosts().into_iter()
-        .map(|host| host.input_devices())
-        .filter_map(|devices| devices.ok())
-        .flat_map(|devices| devices.collect::<Vec<Device>>())
+/// Assign a [`DeviceId`] to every `(host, name, device)`, in enumeration order
+fn identify<D>(devices: impl IntoIterator<Item = (String, String, D)>) -> Vec<(DeviceId, D)> {
+    let mut occurrences: HashMap<(String, String), usize> = HashMap::new();
+
+    devices.into_iter()
+        .map(|(host, name, device)| {
+            let occurrence = occurrences.entry((host.clone(), name.clone())).or_default();
+
+            let id = DeviceId { host, name, index: *occurrence };
+            *occurrence += 1;
+
+            (id, device)
+        })
         .collect()
 }
 
-pub fn list_speaker() -> Vec<Device> {
-    all_hosts().into_iter()
-        .map(|host| host.output_devices())
-        .filter_map(|devices| devices.ok())
-        .flat_map(|devices| devices.collect::<Vec<Device>>())
-        .collect()
+/// Find `id` in `devices`. When the device has disappeared, fall back to one with the same name
+/// (it might have been plugged into another port), then to the one matching `is_default`
+fn resolve<D>(mut devices: Vec<(DeviceId, D)>, id: Option<&DeviceId>, is_default: impl Fn(&DeviceId, &D) -> bool) -> Option<(DeviceId, D)> {
+    let saved = id.and_then(|id| {
+        devices.iter().position(|(device_id, _)| device_id == id)
+            .or_else(|| devices.iter().position(|(device_id, _)| device_id.host == id.host && device_id.name == id.name))
+            .or_else(|| devices.iter().position(|(device_id, _)| device_id.name == id.name))
+    });
+
+    if let (Some(id), None) = (id, saved) {
+        eprintln!("Saved device {id} is not available anymore, falling back to the default device");
+    }
+
+    let position = saved
+        .or_else(|| devices.iter().position(|(device_id, device)| is_default(device_id, device)))
+        .or(if devices.is_empty() { None } else { Some(0) })?;
+
+    Some(devices.swap_remove(position))
+}
+
+pub fn list_microphone() -> Vec<(DeviceId, Device)> {
+    identify(all_hosts().into_iter()
+        .filter_map(|host| Some((host.id(), host.input_devices().ok()?)))
+        .flat_map(|(host_id, devices)| devices.map(move |device| (host_id.name().to_owned(), device.name().unwrap_or_default(), device))))
+}
+
+pub fn list_speaker() -> Vec<(DeviceId, Device)> {
+    identify(all_hosts().into_iter()
+        .filter_map(|host| Some((host.id(), host.output_devices().ok()?)))
+        .flat_map(|(host_id, devices)| devices.map(move |device| (host_id.name().to_owned(), device.name().unwrap_or_default(), device))))
+}
+
+pub fn list_screen() -> anyhow::Result<Vec<(DeviceId, Display)>> {
+    Ok(identify(scrap::Display::all()?.into_iter()
+        .map(|display| (SCREEN_HOST.to_owned(), display.name(), display))))
+}
+
+/// Matches the host's default device, looked up once instead of for every device
+fn is_default_audio_device(default_device: Option<Device>) -> impl Fn(&DeviceId, &Device) -> bool {
+    let host = cpal::default_host().id().name();
+    let name = default_device.and_then(|device| device.name().ok());
+
+    move |id, _| id.host == host && name.as_ref() == Some(&id.name)
+}
+
+/// Saved microphone, or the default one when it's not available
+pub fn find_microphone(id: Option<&DeviceId>) -> Option<(DeviceId, Device)> {
+    resolve(list_microphone(), id, is_default_audio_device(cpal::default_host().default_input_device()))
+}
+
+/// Saved speaker, or the default one when it's not available
+pub fn find_speaker(id: Option<&DeviceId>) -> Option<(DeviceId, Device)> {
+    resolve(list_speaker(), id, is_default_audio_device(cpal::default_host().default_output_device()))
+}
+
+/// Ids of every connected device
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceSnapshot {
+    pub microphone: Vec<DeviceId>,
+    pub speaker: Vec<DeviceId>,
+    pub screen: Vec<DeviceId>,
+}
+
+impl DeviceSnapshot {
+    pub fn take() -> Self {
+        Self {
+            microphone: list_microphone().into_iter().map(|(id, _)| id).collect(),
+            speaker: list_speaker().into_iter().map(|(id, _)| id).collect(),
+            screen: list_screen().unwrap_or_default().into_iter().map(|(id, _)| id).collect(),
+        }
+    }
+
+    pub fn contains(&self, device_type: DeviceType, id: &DeviceId) -> bool {
+        match device_type {
+            DeviceType::Microphone => self.microphone.contains(id),
+            DeviceType::Speaker => self.speaker.contains(id),
+        }
+    }
+}
+
+/// How often connected devices are listed to detect hotplugging
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What needs to know about plugged and unplugged devices, they are only listed while something does
+#[derive(Debug, Default)]
+pub struct DeviceWatch {
+    /// The device selection is on screen
+    pub is_shown: AtomicBool,
+    /// A recording is running and may have to switch devices
+    pub is_recording: AtomicBool,
+}
+
+impl DeviceWatch {
+    fn is_needed(&self) -> bool {
+        self.is_shown.load(Ordering::Relaxed) || self.is_recording.load(Ordering::Relaxed)
+    }
+}
+
+/// Call `on_change` whenever a device is plugged or unplugged while `watch` needs it
+///
+/// cpal and scrap don't provide hotplug notifications, so the devices are polled. Changes made while
+/// nothing needed them are reported as soon as something does
+pub fn watch_devices(watch: Arc<DeviceWatch>, on_change: impl Fn(DeviceSnapshot) + Send + 'static) {
+    std::thread::spawn(move || {
+        let mut snapshot = DeviceSnapshot::take();
+
+        loop {
+            std::thread::sleep(DEVICE_POLL_INTERVAL);
+
+            if !watch.is_needed() {
+                continue;
+            }
+
+            let current = DeviceSnapshot::take();
+
+            if current != snapshot {
+                snapshot = current.clone();
+
+                on_change(current);
+            }
+        }
+    });
+}
+
+/// Saved screen, or the primary one when it's not available
+pub fn find_screen(id: Option<&DeviceId>) -> Option<(DeviceId, Display)> {
+    resolve(list_screen().unwrap_or_default(), id, |_, display| display.is_primary())
+}
+
+/// Sample rate requested from devices that support a range of rates
+const PREFERRED_SAMPLE_RATE: cpal::SampleRate = cpal::SampleRate(48000);
+
+/// Map cpal sample format to its GStreamer counterpart, cpal always uses native endianness
+///
+/// Refer to this:
+/// - https://gstreamer.freedesktop.org/documentation/audio/audio-format.html#GstAudioFormat
+pub fn audio_format(sample_format: cpal::SampleFormat) -> Option<gst_audio::AudioFormat> {
+    match sample_format {
+        cpal::SampleFormat::I8 => Some(gst_audio::AudioFormat::S8),
+        cpal::SampleFormat::U8 => Some(gst_audio::AudioFormat::U8),
+        cpal::SampleFormat::I16 => Some(gst_audio::AUDIO_FORMAT_S16),
+        cpal::SampleFormat::U16 => Some(gst_audio::AUDIO_FORMAT_U16),
+        cpal::SampleFormat::I32 => Some(gst_audio::AUDIO_FORMAT_S32),
+        cpal::SampleFormat::U32 => Some(gst_audio::AUDIO_FORMAT_U32),
+        cpal::SampleFormat::F32 => Some(gst_audio::AUDIO_FORMAT_F32),
+        cpal::SampleFormat::F64 => Some(gst_audio::AUDIO_FORMAT_F64),
+        _ => None,
+    }
+}
+
+/// Stream configuration negotiated with an audio device
+#[derive(Debug, Clone)]
+pub struct AudioConfig {
+    pub config: cpal::StreamConfig,
+    pub sample_format: cpal::SampleFormat,
+    pub format: gst_audio::AudioFormat,
+}
+
+impl AudioConfig {
+    /// Caps describing the raw samples produced by the device
+    pub fn caps(&self) -> anyhow::Result<gst::Caps> {
+        let info = gst_audio::AudioInfo::builder(self.format, self.config.sample_rate.0, self.config.channels as u32)
+            .build()?;
+
+        Ok(info.to_caps()?)
+    }
+
+    pub fn format_info(&self) -> gst_audio::AudioFormatInfo {
+        gst_audio::AudioFormatInfo::from_format(self.format)
+    }
+}
+
+/// Pick the best stream configuration supported by both the device and GStreamer
+///
+/// Prefers the device's default configuration, then float samples at [`PREFERRED_SAMPLE_RATE`]
+pub fn negotiate_audio_config(device: &Device, device_type: DeviceType) -> anyhow::Result<AudioConfig> {
+    let device_name = device.name().unwrap_or_default();
+
+    let default_config = match device_type {
+        DeviceType::Microphone => device.default_input_config().ok(),
+        DeviceType::Speaker => device.default_output_config().ok(),
+    };
+
+    let supported_configs: Vec<cpal::SupportedStreamConfigRange> = match device_type {
+        DeviceType::Microphone => device.supported_input_configs().map(|configs| configs.collect()),
+        DeviceType::Speaker => device.supported_output_configs().map(|configs| configs.collect()),
+    }.unwrap_or_else(|err| {
+        eprintln!("Can't list supported configs of {device_name:?} because: {err}");
+
+        Vec::new()
+    });
+
+    let format_rank = |sample_format: cpal::SampleFormat| match sample_format {
+        cpal::SampleFormat::F32 => 0,
+        cpal::SampleFormat::I16 => 1,
+        cpal::SampleFormat::I32 => 2,
+        _ => 3,
+    };
+
+    let best_config = supported_configs.into_iter()
+        .filter(|range| audio_format(range.sample_format()).is_some())
+        .map(|range| {
+            let rate = match &default_config {
+                Some(default) if (range.min_sample_rate()..=range.max_sample_rate()).contains(&default.sample_rate()) => default.sample_rate(),
+                _ => PREFERRED_SAMPLE_RATE.clamp(range.min_sample_rate(), range.max_sample_rate()),
+            };
+
+            range.with_sample_rate(rate)
+        })
+        .min_by_key(|config| {
+            let is_default = default_config.as_ref().is_some_and(|default| {
+                default.sample_format() == config.sample_format()
+                    && default.channels() == config.channels()
+                    && default.sample_rate() == config.sample_rate()
+            });
+
+            (
+                !is_default,
+                format_rank(config.sample_format()),
+                config.sample_rate().0.abs_diff(PREFERRED_SAMPLE_RATE.0),
+                config.channels().abs_diff(2),
+            )
+        })
+        .or(default_config.filter(|config| audio_format(config.sample_format()).is_some()));
+
+    let Some(config) = best_config else {
+        anyhow::bail!("Device {device_name:?} doesn't provide any supported audio format");
+    };
+
+    Ok(AudioConfig {
+        config: config.config(),
+        sample_format: config.sample_format(),
+        format: audio_format(config.sample_format()).unwrap(),
+    })
+}
+
+/// Maximum distance between a buffer's capture time and its sample-counted timestamp before resyncing,
+/// matches the default `alignment-threshold` of `audiomixer`
+const MAX_AUDIO_DRIFT: Duration = Duration::from_millis(40);
+
+/// Clock shared by every capture source of a recording
+///
+/// Its time is the running time of the recording pipeline, so a timestamp taken at capture time
+/// can be used as PTS directly
+#[derive(Clone)]
+pub struct CaptureClock {
+    clock: gst::Clock,
+    base_time: gst::ClockTime,
+}
+
+impl CaptureClock {
+    pub fn new() -> Self {
+        use gst::prelude::*;
+
+        let clock = gst::SystemClock::obtain();
+        let base_time = clock.time().unwrap_or(gst::ClockTime::ZERO);
+
+        Self { clock, base_time }
+    }
+
+    /// Make the running time of `pipeline` follow this clock
+    pub fn attach(&self, pipeline: &gst::Pipeline) {
+        use gst::prelude::*;
+
+        pipeline.use_clock(Some(&self.clock));
+        pipeline.set_base_time(self.base_time);
+        pipeline.set_start_time(gst::ClockTime::NONE);
+    }
+
+    /// Time elapsed since the recording started
+    pub fn now(&self) -> Duration {
+        use gst::prelude::*;
+
+        let now = self.clock.time().unwrap_or(self.base_time);
+
+        now.saturating_sub(self.base_time).into()
+    }
+
+    /// Map the capture instant reported by cpal onto this clock
+    pub fn audio_capture_time(&self, info: &cpal::InputCallbackInfo) -> Duration {
+        let timestamp = info.timestamp();
+        let latency = timestamp.callback.duration_since(&timestamp.capture).unwrap_or_default();
+
+        self.now().saturating_sub(latency)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioTimestamp {
+    pub pts: Duration,
+    pub duration: Duration,
+    /// Set when the timestamps were resynced to the capture clock
+    pub discont: bool,
+}
+
+/// Timestamps audio buffers by counting samples from the first capture time
+///
+/// Callback scheduling jitter is ignored, but once the device clock drifted further than
+/// [`MAX_AUDIO_DRIFT`] away from the capture clock the timestamps are resynced
+pub struct AudioTimestamper {
+    sample_rate: u32,
+    base: Option<Duration>,
+    frames: u64,
+}
+
+impl AudioTimestamper {
+    pub fn new(sample_rate: u32) -> Self {
+        Self { sample_rate, base: None, frames: 0 }
+    }
+
+    fn frames_duration(&self, frames: u64) -> Duration {
+        Duration::from_nanos((frames as u128 * 1_000_000_000 / self.sample_rate as u128) as u64)
+    }
+
+    /// Timestamp a buffer of `frames` frames whose first frame was captured at `capture_time`
+    pub fn timestamp(&mut self, capture_time: Duration, frames: u64) -> AudioTimestamp {
+        let base = *self.base.get_or_insert(capture_time);
+        let expected = base + self.frames_duration(self.frames);
+
+        let drift = if capture_time > expected { capture_time - expected } else { expected - capture_time };
+        let discont = drift > MAX_AUDIO_DRIFT;
+
+        if discont {
+            eprintln!("Audio drifted {drift:?} away from the capture clock, resyncing");
+
+            self.base = Some(capture_time.saturating_sub(self.frames_duration(self.frames)));
+        }
+
+        let pts = self.base.unwrap() + self.frames_duration(self.frames);
+        let duration = self.frames_duration(self.frames + frames) - self.frames_duration(self.frames);
+
+        self.frames += frames;
+
+        AudioTimestamp { pts, duration, discont }
+    }
+}
+
+/// Format every audio source is converted to before mixing, shared with its silence fallback
+pub const MIXER_AUDIO_CAPS: &str = "audio/x-raw,format=F32LE,rate=48000,channels=2,layout=interleaved";
+
+/// How long an audio source may stay silent before its silence fallback takes over
+pub const AUDIO_FALLBACK_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub type AudioSender = std::sync::mpsc::SyncSender<(AudioTimestamp, Vec<u8>)>;
+pub type AudioReceiver = std::sync::mpsc::Receiver<(AudioTimestamp, Vec<u8>)>;
+
+/// Audio device being recorded into the `appsrc` named `input_name`
+///
+/// The device can be switched while recording, samples keep flowing into the same `appsrc`
+pub struct AudioSource {
+    pub device_type: DeviceType,
+    pub id: DeviceId,
+    pub input_name: String,
+    /// Set once the device is disconnected, the recording receives silence until it's switched
+    pub is_lost: bool,
+    config: AudioConfig,
+    sender: AudioSender,
+    streams: Vec<cpal::Stream>,
+    on_lost: Arc<dyn Fn(DeviceType) + Send + Sync>,
+}
+
+impl AudioSource {
+    pub fn open(
+        device_type: DeviceType,
+        (id, device): (DeviceId, Device),
+        input_name: String,
+        capture_clock: &CaptureClock,
+        on_lost: Arc<dyn Fn(DeviceType) + Send + Sync>,
+    ) -> anyhow::Result<(Self, AudioReceiver)> {
+        let (sender, receiver) = std::sync::mpsc::sync_channel(64);
+
+        let (config, streams) = Self::open_streams(device_type, &device, capture_clock, sender.clone(), on_lost.clone())?;
+
+        println!("Recording {device_type:?} {id} with {config:?}");
+
+        Ok((Self { device_type, id, input_name, is_lost: false, config, sender, streams, on_lost }, receiver))
+    }
+
+    fn open_streams(
+        device_type: DeviceType,
+        device: &Device,
+        capture_clock: &CaptureClock,
+        sender: AudioSender,
+        on_lost: Arc<dyn Fn(DeviceType) + Send + Sync>,
+    ) -> anyhow::Result<(AudioConfig, Vec<cpal::Stream>)> {
+        use anyhow::Context as _;
+
+        let audio_config = negotiate_audio_config(device, device_type)?;
+
+        let mut streams = Vec::new();
+
+        let on_error = move |error| match error {
+            cpal::StreamError::DeviceNotAvailable => {
+                eprintln!("{device_type:?} is disconnected");
+
+                on_lost(device_type);
+            },
+            error => eprintln!("{device_type:?} stream error: {error}"),
+        };
+
+        if device_type == DeviceType::Speaker {
+            // Keep the output device busy with silence so loopback capture always receives data
+            let format_info = audio_config.format_info();
+
+            let stream = device.build_output_stream_raw(&audio_config.config, audio_config.sample_format, {
+                move |data: &mut cpal::Data, _: &_| {
+                    format_info.fill_silence(data.bytes_mut());
+                }
+            }, |_| { }, None).context("Can't open the speaker output stream")?;
+
+            streams.push(stream);
+        }
+
+        let stream = device.build_input_stream_raw(&audio_config.config, audio_config.sample_format, {
+            let capture_clock = capture_clock.clone();
+            let channels = audio_config.config.channels as usize;
+            let mut timestamper = AudioTimestamper::new(audio_config.config.sample_rate.0);
+
+            move |data: &cpal::Data, info: &cpal::InputCallbackInfo| {
+                let capture_time = capture_clock.audio_capture_time(info);
+                let timestamp = timestamper.timestamp(capture_time, (data.len() / channels) as u64);
+
+                // Never block the device's realtime thread, drop the samples instead
+                if sender.try_send((timestamp, data.bytes().to_vec())).is_err() {
+                    eprintln!("Audio buffer at {:?} is dropped", timestamp.pts);
+                }
+            }
+        }, on_error, None).context("Can't open the audio input stream")?;
+
+        streams.push(stream);
+
+        Ok((audio_config, streams))
+    }
+
+    /// Caps of the samples sent into the `appsrc`
+    pub fn caps(&self) -> anyhow::Result<gst::Caps> {
+        self.config.caps()
+    }
+
+    pub fn play(&self) -> anyhow::Result<()> {
+        use cpal::traits::StreamTrait as _;
+
+        for stream in &self.streams {
+            stream.play()?;
+        }
+
+        Ok(())
+    }
+
+    /// Stop receiving samples from the current device, the recording receives silence
+    pub fn mark_lost(&mut self) {
+        self.is_lost = true;
+        self.streams.clear();
+    }
+
+    /// Continue recording from `device`, the caps of `source` are updated when its format differs
+    pub fn switch_device(&mut self, (id, device): (DeviceId, Device), capture_clock: &CaptureClock, source: &gst_app::AppSrc) -> anyhow::Result<()> {
+        self.streams.clear();
+
+        let (config, streams) = Self::open_streams(self.device_type, &device, capture_clock, self.sender.clone(), self.on_lost.clone())?;
+
+        println!("Switching {:?} from {} to {id} with {config:?}", self.device_type, self.id);
+
+        source.set_caps(Some(&config.caps()?));
+
+        self.id = id;
+        self.config = config;
+        self.streams = streams;
+        self.is_lost = false;
+
+        self.play()
+    }
 }
 
-pub fn list_screen() -> anyhow::Result<Vec<Display>> {
-    Ok(scrap::Display::all()?)
+/// Prefer the first linked sink pad of a `fallbackswitch`, the following ones are fallbacks
+pub fn prioritize_fallback_pads(switch: &gst::Element) {
+    use gst::prelude::*;
+
+    for (priority, pad) in switch.sink_pads().iter().enumerate() {
+        pad.set_property("priority", priority as u32);
+    }
+}
+
+/// Pace of the black frames recorded while the screen is disconnected
+pub const BLACK_FRAME_INTERVAL: Duration = Duration::from_millis(33);
+
+/// How often a disconnected screen is looked up again
+pub const SCREEN_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Capturer for the screen `id` once it's connected again with the same resolution
+pub fn reconnect_screen(id: &DeviceId, width: usize, height: usize) -> Option<scrap::Capturer> {
+    let (_, display) = list_screen().ok()?.into_iter().find(|(screen_id, _)| screen_id == id)?;
+
+    if display.width() != width || display.height() != height {
+        return None;
+    }
+
+    scrap::Capturer::new(display).ok()
 }
 
 pub struct Screen {
-    name: String,
+    id: DeviceId,
     display: Display,
 }
 
 impl Screen {
-    pub fn name(&self) -> &String {
-        &self.name
+    pub fn id(&self) -> &DeviceId {
+        &self.id
     }
 
     /// Return PNG encoded screen preview
-    pub fn preview(&self) -> anyhow::Result<Vec<u8>> {
+    pub fn preview(self) -> anyhow::Result<Vec<u8>> {
         use gst::prelude::*;
         use scrap::Capturer;
 
-        let mut capturer = Capturer::new(self.display.clone_device()).unwrap();
+        let mut capturer = Capturer::new(self.display)?;
 
         let width;
         let height;
@@ -137,7 +691,7 @@ impl Screen {
                     eprintln!("Received invalid data, skipping");
                     continue
                 },
-                Err(err) => panic!("{err}"),
+                Err(err) => return Err(err.into()),
             };
 
             let scrap::Frame::PixelBuffer(pixel_buffer) = frame else {
@@ -199,12 +753,99 @@ impl Screen {
 
         let preview = preview.lock().unwrap();
 
-        Ok(preview.clone().unwrap())
+        preview.clone().ok_or_else(|| anyhow::anyhow!("The screen gave no preview"))
     }
 
     pub fn all() -> anyhow::Result<Vec<Self>> {
         Ok(list_screen()?.into_iter()
-            .map(|d| Self { name: d.name(), display: d }).collect())
+            .map(|(id, display)| Self { id, display }).collect())
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::AudioTimestamper;
+
+    const SAMPLE_RATE: u32 = 48000;
+    const FRAMES_PER_CALLBACK: u64 = 480;
+    const VIDEO_FPS: u64 = 30;
+    const RECORDING_LENGTH: Duration = Duration::from_secs(60 * 60);
+
+    /// Deterministic callback latency between 0 and 3ms
+    fn jitter(seed: &mut u64) -> Duration {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+
+        Duration::from_micros((*seed >> 33) % 3000)
+    }
+
+    /// Synthetic audio source whose clock runs at `device_speed` compared to the capture clock,
+    /// returns the real capture time and the assigned PTS of every buffer
+    fn record_audio(device_speed: f64) -> Vec<(Duration, Duration)> {
+        let mut timestamper = AudioTimestamper::new(SAMPLE_RATE);
+        let mut seed = 1;
+        let mut captured_frames = 0;
+        let mut buffers = Vec::new();
+
+        loop {
+            let capture_time = Duration::from_secs_f64(captured_frames as f64 / (SAMPLE_RATE as f64 * device_speed));
+
+            if capture_time > RECORDING_LENGTH { break };
+
+            let timestamp = timestamper.timestamp(capture_time + jitter(&mut seed), FRAMES_PER_CALLBACK);
+
+            buffers.push((capture_time, timestamp.pts));
+
+            captured_frames += FRAMES_PER_CALLBACK;
+        }
+
+        buffers
+    }
+
+    /// Largest offset between audio and video captured at the same moment, video frames are
+    /// stamped with their capture time
+    fn max_av_offset(audio: &[(Duration, Duration)]) -> Duration {
+        (0..RECORDING_LENGTH.as_secs() * VIDEO_FPS)
+            .map(|frame| Duration::from_secs_f64(frame as f64 / VIDEO_FPS as f64))
+            .map(|video_pts| {
+                let index = audio.partition_point(|(capture_time, _)| *capture_time <= video_pts).saturating_sub(1);
+                let (capture_time, pts) = audio[index];
+
+                let audio_pts = pts + (video_pts - capture_time);
+
+                if audio_pts > video_pts { audio_pts - video_pts } else { video_pts - audio_pts }
+            })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn jitter_does_not_resync() {
+        let mut timestamper = AudioTimestamper::new(SAMPLE_RATE);
+        let mut seed = 7;
+
+        for buffer in 0..10_000 {
+            let capture_time = Duration::from_millis(buffer * 10) + jitter(&mut seed);
+            let timestamp = timestamper.timestamp(capture_time, FRAMES_PER_CALLBACK);
+
+            assert!(!timestamp.discont, "buffer {buffer} was resynced");
+            assert_eq!(timestamp.duration, Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn av_offset_stays_bounded_on_matching_clocks() {
+        assert!(max_av_offset(&record_audio(1.0)) < Duration::from_millis(5));
+    }
+
+    #[test]
+    fn av_offset_stays_bounded_on_drifting_clocks() {
+        for device_speed in [0.999, 1.001] {
+            let offset = max_av_offset(&record_audio(device_speed));
+
+            assert!(offset < Duration::from_millis(45), "A/V offset {offset:?} with device speed {device_speed}");
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use std::{ops::Range, path::{Path, PathBuf}, sync::{Arc, Mutex}};
+
+use anyhow::Context as _;
+
+use byte_slice_cast::AsMutSliceOf as _;
+
+use regex::Regex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{timestamp::Timestamp, transcript::{Segment, Word}};
+
+/// What replaces personal data in transcripts
+const REDACTED: &str = "[REDACTED]";
+
+const EMAIL: &str = r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b";
+/// Only numbers shaped like phone numbers, with a country code, a parenthesised area code,
+/// or three groups split by the same separator, so years, amounts and versions are left alone
+const PHONE_NUMBER: &str = concat!(
+    r"\+\d{1,3}(?:[ .-]?\(\d{1,4}\))?(?:[ .-]?\d{2,4}){2,4}\b",
+    r"|\(\d{1,4}\)[ .-]?\d{3,4}[ .-]?\d{3,4}\b",
+    r"|\b\d{2,4}(?:(?: \d{3,4}){2}|(?:-\d{3,4}){2}|(?:\.\d{3,4}){2})\b",
+);
+const CARD_NUMBER: &str = r"\b\d(?:[ -]?\d){12,18}\b";
+
+/// Whisper's word timestamps are loose, bleeps reach this far around the words
+const AUDIO_PADDING_MILLIS: i64 = 150;
+const BLEEP_FREQUENCY: f32 = 1000.0;
+const BLEEP_VOLUME: f32 = 0.25;
+
+/// How the spoken personal data is hidden in the recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioRedaction {
+    /// Only the transcript is redacted
+    Keep,
+    Bleep,
+    Silence,
+}
+
+/// Personal data found in a transcript, the data itself is never kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redaction {
+    pub kind: String,
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+/// Whether `chars` go on with more digits, maybe after a separator
+fn continues_number(mut chars: impl Iterator<Item = char>) -> bool {
+    match chars.next() {
+        Some(' ' | '.' | '-') => chars.next().is_some_and(|char| char.is_ascii_digit()),
+        Some(char) => char.is_ascii_digit(),
+        None => false,
+    }
+}
+
+/// Numbers aren't matched in the middle of a longer run of digits
+fn is_whole_number(text: &str, range: &Range<usize>) -> bool {
+    !continues_number(text[..range.start].chars().rev()) && !continues_number(text[range.end..].chars())
+}
+
+/// Card numbers pass the Luhn checksum, other long numbers are left to the phone pattern
+fn is_card_number(text: &str) -> bool {
+    let digits: Vec<u32> = text.chars().filter_map(|char| char.to_digit(10)).collect();
+
+    let sum: u32 = digits.iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 1 { if digit * 2 > 9 { digit * 2 - 9 } else { digit * 2 } } else { *digit })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
+
+struct Pattern {
+    kind: String,
+    regex: Regex,
+    /// Whether a match in the text really is this kind of data
+    is_valid: fn(&str, Range<usize>) -> bool,
+}
+
+pub struct Redactor {
+    patterns: Vec<Pattern>,
+}
+
+impl Redactor {
+    pub fn new(config: &crate::configuration::RedactionConfig) -> anyhow::Result<Self> {
+        let mut patterns = Vec::new();
+
+        // Card numbers go first, they'd pass for phone numbers too
+        if config.card_numbers {
+            patterns.push(Pattern {
+                kind: "card number".to_owned(),
+                regex: Regex::new(CARD_NUMBER)?,
+                // A leading `+` makes it an international phone number
+                is_valid: |text, range| is_whole_number(text, &range) && !text[..range.start].ends_with('+') && is_card_number(&text[range]),
+            });
+        }
+
+        if config.phone_numbers {
+            patterns.push(Pattern { kind: "phone number".to_owned(), regex: Regex::new(PHONE_NUMBER)?, is_valid: |text, range| is_whole_number(text, &range) });
+        }
+
+        if config.emails {
+            patterns.push(Pattern { kind: "email".to_owned(), regex: Regex::new(EMAIL)?, is_valid: |_, _| true });
+        }
+
+        for pattern in config.custom_patterns.iter().filter(|pattern| !pattern.is_empty()) {
+            let regex = Regex::new(pattern).map_err(|err| anyhow::anyhow!("Invalid pattern {pattern:?}: {err}"))?;
+
+            patterns.push(Pattern { kind: format!("custom pattern {pattern}"), regex, is_valid: |_, _| true });
+        }
+
+        Ok(Self { patterns })
+    }
+
+    /// Where personal data is in `text` and what kind it is, in order,
+    /// where matches overlap the earlier pattern wins
+    fn find(&self, text: &str) -> Vec<(Range<usize>, &str)> {
+        let mut found: Vec<(Range<usize>, &str)> = Vec::new();
+
+        for pattern in &self.patterns {
+            for found_match in pattern.regex.find_iter(text) {
+                let range = found_match.range();
+
+                if range.is_empty() || !(pattern.is_valid)(text, range.clone()) || found.iter().any(|(other, _)| range.start < other.end && other.start < range.end) {
+                    continue;
+                }
+
+                found.push((range, pattern.kind.as_str()));
+            }
+        }
+
+        found.sort_by_key(|(range, _)| range.start);
+        found
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut redacted = String::new();
+        let mut last = 0;
+
+        for (range, _) in self.find(text) {
+            redacted.push_str(&text[last..range.start]);
+            redacted.push_str(REDACTED);
+            last = range.end;
+        }
+
+        redacted.push_str(&text[last..]);
+        redacted
+    }
+
+    /// Redact the text and words of `segments`, the words of each match become one redacted word.
+    /// Matches are timed by their words, or by their segment when whisper's words don't show them
+    pub fn redact_segments(&self, segments: &mut [Segment]) -> Vec<Redaction> {
+        let mut redactions = Vec::new();
+
+        for segment in segments {
+            let text_matches = self.find(&segment.text);
+
+            if text_matches.is_empty() {
+                continue;
+            }
+
+            segment.text = self.redact(&segment.text);
+
+            let mut joined = String::new();
+            let mut word_ranges = Vec::new();
+
+            for word in &segment.words {
+                if !joined.is_empty() {
+                    joined.push(' ');
+                }
+
+                word_ranges.push(joined.len()..joined.len() + word.text.len());
+                joined.push_str(&word.text);
+            }
+
+            let mut words: Vec<Word> = Vec::new();
+            let mut next_word = 0;
+            let mut word_matches = 0;
+
+            for (range, kind) in self.find(&joined) {
+                let first = word_ranges.iter().position(|word| word.end > range.start).unwrap().max(next_word);
+                let last = word_ranges.iter().rposition(|word| word.start < range.end).unwrap();
+
+                word_matches += 1;
+
+                // Within a word already redacted for an earlier match, it stays one redacted word
+                if last < next_word {
+                    let word = &words[words.len() - 1];
+                    redactions.push(Redaction { kind: kind.to_owned(), start: word.start, end: word.end });
+                    continue;
+                }
+
+                // Only the space between two words, no word is left to redact
+                if first > last {
+                    redactions.push(Redaction { kind: kind.to_owned(), start: segment.words[last].end, end: segment.words[first].start });
+                    continue;
+                }
+
+                words.extend(segment.words[next_word..first].iter().cloned());
+
+                let (start, end) = (segment.words[first].start, segment.words[last].end);
+
+                words.push(Word {
+                    text: self.redact(&joined[word_ranges[first].start..word_ranges[last].end]),
+                    start,
+                    end,
+                    // Nothing is left to proofread
+                    confidence: 1.0,
+                });
+
+                redactions.push(Redaction { kind: kind.to_owned(), start, end });
+
+                next_word = last + 1;
+            }
+
+            words.extend(segment.words[next_word..].iter().cloned());
+            segment.words = words;
+
+            for (_, kind) in text_matches.iter().skip(word_matches) {
+                redactions.push(Redaction { kind: (*kind).to_owned(), start: segment.start, end: segment.end });
+            }
+        }
+
+        redactions
+    }
+}
+
+/// Extension of audit logs, in place of the transcript's
+pub const AUDIT_EXTENSION: &str = "redactions.log";
+
+/// Where the redactions of `name.srt` are listed, `name.redactions.log`
+pub fn audit_path(transcription_path: &Path) -> PathBuf {
+    transcription_path.with_extension(AUDIT_EXTENSION)
+}
+
+/// Where the recording with its personal data hidden is saved, `name.redacted.m4a`
+pub fn redacted_audio_path(transcription_path: &Path) -> PathBuf {
+    transcription_path.with_extension(format!("redacted.{}", crate::recorder::AudioFileFormat::M4a.extension()))
+}
+
+/// When and what kind of data was redacted from `transcription_path`, and where the redacted recording is
+pub fn audit_log(transcription_path: &Path, redactions: &[Redaction], audio: Option<(AudioRedaction, &Path)>) -> String {
+    let mut log = format!("Redacted {} items from {}\n", redactions.len(), transcription_path.display());
+
+    if let Some((audio, path)) = audio {
+        log.push_str(&format!("Audio: {audio:?} in {}\n", path.display()));
+    }
+
+    for redaction in redactions {
+        log.push_str(&format!("{} --> {}\t{}\n", redaction.start.srt(), redaction.end.srt(), redaction.kind));
+    }
+
+    log
+}
+
+/// Hide the parts of `samples`, interleaved `channels` starting `start` into the recording, that fall in `ranges`
+pub fn mask_samples(samples: &mut [f32], channels: usize, rate: u32, start: std::time::Duration, ranges: &[(Timestamp, Timestamp)], audio: AudioRedaction) {
+    if audio == AudioRedaction::Keep || channels == 0 || rate == 0 {
+        return;
+    }
+
+    for (frame, samples) in samples.chunks_mut(channels).enumerate() {
+        let seconds = start.as_secs_f64() + frame as f64 / rate as f64;
+        let at = Timestamp::from_millis((seconds * 1000.0) as i64);
+
+        if !ranges.iter().any(|(range_start, range_end)| *range_start <= at && at < *range_end) {
+            continue;
+        }
+
+        let value = match audio {
+            AudioRedaction::Bleep => BLEEP_VOLUME * (std::f64::consts::TAU * BLEEP_FREQUENCY as f64 * seconds).sin() as f32,
+            _ => 0.0,
+        };
+
+        samples.fill(value);
+    }
+}
+
+/// Re-encode the `audio_stream`th audio stream of `media_data` to `path` with the spoken `redactions` bleeped or silenced
+pub fn redact_audio(media_data: Arc<[u8]>, audio_stream: Option<usize>, redactions: &[Redaction], audio: AudioRedaction, path: &Path) -> anyhow::Result<()> {
+    use gst::prelude::*;
+
+    let ranges: Vec<(Timestamp, Timestamp)> = redactions.iter()
+        .map(|redaction| (redaction.start.offset(-AUDIO_PADDING_MILLIS), redaction.end.offset(AUDIO_PADDING_MILLIS)))
+        .collect();
+
+    let pipeline =
+        gst::parse::launch(&format!(
+            "appsrc name=audio-in stream-type=2 size={} ! decodebin name=decode audioconvert name=convert ! audio/x-raw,format=F32LE,layout=interleaved ! identity name=redact ! {} ! filesink name=output",
+            media_data.len(),
+            crate::recorder::AudioFileFormat::M4a.encoder(),
+        ))?
+            .dynamic_cast::<gst::Pipeline>().unwrap();
+
+    crate::transcriber::feed_media(&pipeline.by_name("audio-in").unwrap().dynamic_cast::<gst_app::AppSrc>().unwrap(), media_data);
+    crate::transcriber::link_audio_stream(&pipeline.by_name("decode").unwrap(), &pipeline.by_name("convert").unwrap(), audio_stream);
+
+    pipeline.by_name("output").unwrap().set_property("location", path.to_path_buf());
+
+    // Whisper's times count from the first decoded sample
+    let first_pts = Mutex::new(None);
+
+    pipeline.by_name("redact").unwrap().static_pad("src").unwrap()
+        .add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let Some(caps) = pad.current_caps() else { return gst::PadProbeReturn::Ok };
+            let Ok(audio_info) = gst_audio::AudioInfo::from_caps(&caps) else { return gst::PadProbeReturn::Ok };
+            let Some(buffer) = info.buffer_mut() else { return gst::PadProbeReturn::Ok };
+
+            let pts = buffer.pts().unwrap_or(gst::ClockTime::ZERO);
+            let first = *first_pts.lock().unwrap().get_or_insert(pts);
+            let start = std::time::Duration::from_nanos(pts.saturating_sub(first).nseconds());
+
+            let Ok(mut mapped_buffer) = buffer.make_mut().map_writable() else { return gst::PadProbeReturn::Ok };
+            let Ok(samples) = mapped_buffer.as_mut_slice().as_mut_slice_of::<f32>() else { return gst::PadProbeReturn::Ok };
+
+            mask_samples(samples, audio_info.channels() as usize, audio_info.rate(), start, &ranges, audio);
+
+            gst::PadProbeReturn::Ok
+        })
+        .context("Failed watching the decoded audio")?;
+
+    crate::util::gstreamer_loop(pipeline, |_| { false })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::RedactionConfig;
+    use crate::transcript::fixtures::spoken;
+
+    fn config(custom_patterns: &[&str]) -> RedactionConfig {
+        RedactionConfig {
+            enabled: true,
+            emails: true,
+            phone_numbers: true,
+            card_numbers: true,
+            custom_patterns: custom_patterns.iter().map(|pattern| (*pattern).to_owned()).collect(),
+            audio: AudioRedaction::Keep,
+        }
+    }
+
+    fn redactor(custom_patterns: &[&str]) -> Redactor {
+        Redactor::new(&config(custom_patterns)).unwrap()
+    }
+
+    #[test]
+    fn redacts_personal_data() {
+        let redactor = redactor(&[r"(?i)\bproject falcon\b"]);
+
+        assert_eq!(
+            redactor.redact("Mail jane.doe@example.co.uk or call +1 (555) 123-4567, card 4111 1111 1111 1111, about Project Falcon."),
+            "Mail [REDACTED] or call [REDACTED], card [REDACTED], about [REDACTED].",
+        );
+
+        // Fails the Luhn checksum, still too long for a phone number
+        assert_eq!(redactor.redact("Order 1234 5678 9012 3456 7 shipped in 2024"), "Order 1234 5678 9012 3456 7 shipped in 2024");
+        assert_eq!(redactor.redact("Order 4111111111111112"), "Order 4111111111111112");
+
+        assert_eq!(
+            redactor.redact("Call 555.123.4567, +44 20 7946 0958, +4915112345678 or (020) 7946 0958"),
+            "Call [REDACTED], [REDACTED], [REDACTED] or [REDACTED]",
+        );
+
+        for text in ["In 2024 1500 people came", "Pi is 3.14159265", "Update to 10.15.7 or build 2.1.1234", "Ticket 20240115123456", "Order ab12-3456-7890"] {
+            assert_eq!(redactor.redact(text), text);
+        }
+
+        assert!(Redactor::new(&config(&["("])).is_err());
+    }
+
+    #[test]
+    fn times_redactions_by_their_words() {
+        let mut segments = [
+            spoken(0, &[("Call", 0.9), ("555", 0.9), ("123", 0.9), ("4567,", 0.9), ("thanks", 0.9)], None),
+            spoken(3000, &[("Nothing", 0.9), ("here", 0.9)], None),
+            Segment { words: Vec::new(), ..spoken(5000, &[("Write", 0.9), ("me@example.com", 0.9)], None) },
+        ];
+
+        let redactions = redactor(&[]).redact_segments(&mut segments);
+
+        assert_eq!(segments[0].text, "Call [REDACTED], thanks");
+        assert_eq!(segments[0].words.iter().map(|word| word.text.as_str()).collect::<Vec<_>>(), ["Call", "[REDACTED],", "thanks"]);
+        assert_eq!(segments[1].text, "Nothing here");
+        assert_eq!(segments[2].text, "Write [REDACTED]");
+
+        assert_eq!(redactions, [
+            Redaction { kind: "phone number".to_owned(), start: Timestamp::from_millis(500), end: Timestamp::from_millis(2000) },
+            Redaction { kind: "email".to_owned(), start: Timestamp::from_millis(5000), end: Timestamp::from_millis(6000) },
+        ]);
+
+        assert_eq!(
+            audit_log(Path::new("/tmp/call.srt"), &redactions, None),
+            "Redacted 2 items from /tmp/call.srt\n00:00:00,500 --> 00:00:02,000\tphone number\n00:00:05,000 --> 00:00:06,000\temail\n",
+        );
+    }
+
+    #[test]
+    fn merges_matches_within_one_word() {
+        let mut segments = [spoken(0, &[("Call", 0.9), ("555-123-4567/555-987-6543", 0.9), ("today", 0.9)], None)];
+
+        let redactions = redactor(&[]).redact_segments(&mut segments);
+
+        assert_eq!(segments[0].text, "Call [REDACTED]/[REDACTED] today");
+        assert_eq!(segments[0].words.iter().map(|word| word.text.as_str()).collect::<Vec<_>>(), ["Call", "[REDACTED]/[REDACTED]", "today"]);
+        assert_eq!(redactions.iter().map(|redaction| (redaction.start, redaction.end)).collect::<Vec<_>>(), [(Timestamp::from_millis(500), Timestamp::from_millis(1000)); 2]);
+
+        // Matches only the space between words
+        let mut segments = [spoken(0, &[("Call", 0.9), ("me", 0.9)], None)];
+
+        let redactions = redactor(&[r"\s"]).redact_segments(&mut segments);
+        assert_eq!(segments[0].words.iter().map(|word| word.text.as_str()).collect::<Vec<_>>(), ["Call", "me"]);
+        assert_eq!(redactions[0], Redaction { kind: r"custom pattern \s".to_owned(), start: Timestamp::from_millis(500), end: Timestamp::from_millis(500) });
+    }
+
+    #[test]
+    fn masks_samples_in_ranges() {
+        let ranges = [(Timestamp::from_millis(1000), Timestamp::from_millis(1500))];
+
+        // Two channels at 10 samples a second, starting a second into the recording
+        let mut samples = vec![1.0; 20];
+        mask_samples(&mut samples, 2, 10, std::time::Duration::from_secs(1), &ranges, AudioRedaction::Silence);
+
+        assert_eq!(samples[..10], [0.0; 10]);
+        assert_eq!(samples[10..], [1.0; 10]);
+
+        let mut samples = vec![1.0; 20];
+        mask_samples(&mut samples, 2, 10, std::time::Duration::from_secs(1), &ranges, AudioRedaction::Bleep);
+
+        assert!(samples[..10].iter().all(|sample| sample.abs() <= BLEEP_VOLUME));
+        assert_eq!(samples[10..], [1.0; 10]);
+
+        let mut samples = vec![1.0; 20];
+        mask_samples(&mut samples, 2, 10, std::time::Duration::from_secs(1), &ranges, AudioRedaction::Keep);
+
+        assert_eq!(samples, [1.0; 20]);
+    }
+
+    #[test]
+    fn names_redaction_files() {
+        assert_eq!(audit_path(Path::new("/tmp/meeting.srt")), PathBuf::from("/tmp/meeting.redactions.log"));
+        assert_eq!(redacted_audio_path(Path::new("/tmp/meeting.srt")), PathBuf::from("/tmp/meeting.redacted.m4a"));
+    }
+}
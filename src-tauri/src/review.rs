@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{timestamp::Timestamp, transcript::Segment};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReviewWord {
+    pub text: String,
+    pub confidence: f32,
+    pub doubtful: bool,
+}
+
+/// Cue with a word whisper wasn't sure about
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReviewCue {
+    /// Position of the cue in the saved subtitles
+    pub index: usize,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub speaker: Option<String>,
+    pub words: Vec<ReviewWord>,
+}
+
+/// Payload of `app://transcript_review`, sent when a transcript should be proofread
+#[derive(Debug, Clone, Serialize)]
+pub struct Review {
+    pub path: PathBuf,
+    pub threshold: f32,
+    /// Emailing waits until the transcript is proofread
+    pub email: bool,
+    pub cues: Vec<ReviewCue>,
+}
+
+/// Whether a word of `segment` is less likely than `threshold`
+pub fn is_doubtful(segment: &Segment, threshold: f32) -> bool {
+    segment.words.iter().any(|word| word.confidence < threshold)
+}
+
+/// Every cue of `segments` with a doubtful word
+pub fn doubtful_cues(segments: &[Segment], threshold: f32) -> Vec<ReviewCue> {
+    segments.iter()
+        .enumerate()
+        .filter(|(_, segment)| is_doubtful(segment, threshold))
+        .map(|(index, segment)| ReviewCue {
+            index,
+            start: segment.start,
+            end: segment.end,
+            speaker: segment.speaker.clone(),
+            words: segment.words.iter()
+                .map(|word| ReviewWord { text: word.text.clone(), confidence: word.confidence, doubtful: word.confidence < threshold })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Extension of reviews, in place of the transcript's
+pub const REVIEW_EXTENSION: &str = "review.txt";
+
+/// Where the review of `name.srt` is saved, `name.review.txt`
+pub fn review_path(transcription_path: &Path) -> PathBuf {
+    transcription_path.with_extension(REVIEW_EXTENSION)
+}
+
+/// Every cue like in SRT, cues to proofread are flagged with their least likely word
+/// and doubtful words are marked like `[?word 42%?]`
+pub fn review_export(segments: &[Segment], threshold: f32) -> String {
+    segments.iter()
+        .enumerate()
+        .map(|(index, segment)| {
+            let lowest = segment.words.iter().map(|word| word.confidence).min_by(f32::total_cmp);
+
+            let flag = match lowest {
+                Some(lowest) if lowest < threshold => format!(" [REVIEW {:.0}%]", lowest * 100.0),
+                _ => String::new(),
+            };
+
+            let text = if segment.words.is_empty() {
+                segment.labelled_text()
+            } else {
+                let words: Vec<String> = segment.words.iter()
+                    .map(|word| if word.confidence < threshold { format!("[?{} {:.0}%?]", word.text, word.confidence * 100.0) } else { word.text.clone() })
+                    .collect();
+
+                match &segment.speaker {
+                    Some(speaker) => format!("{speaker}: {}", words.join(" ")),
+                    None => words.join(" "),
+                }
+            };
+
+            format!("{}\n{} --> {}{flag}\n{text}\n", index + 1, segment.start.srt(), segment.end.srt())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::fixtures::spoken;
+
+    #[test]
+    fn flags_doubtful_cues() {
+        let segments = [
+            spoken(0, &[("Good", 0.95), ("morning", 0.9)], Some("Me")),
+            spoken(2000, &[("The", 0.9), ("Kubernetes", 0.31), ("cluster", 0.8)], None),
+        ];
+
+        let cues = doubtful_cues(&segments, 0.5);
+
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].index, 1);
+        assert_eq!(cues[0].words.iter().filter(|word| word.doubtful).map(|word| word.text.as_str()).collect::<Vec<_>>(), ["Kubernetes"]);
+
+        assert_eq!(review_export(&segments, 0.5), "1\n00:00:00,000 --> 00:00:01,000\nMe: Good morning\n\n\
+            2\n00:00:02,000 --> 00:00:03,500 [REVIEW 31%]\nThe [?Kubernetes 31%?] cluster\n");
+
+        assert!(doubtful_cues(&segments, 0.3).is_empty());
+    }
+
+    #[test]
+    fn names_review_files() {
+        assert_eq!(review_path(Path::new("/tmp/meeting.srt")), PathBuf::from("/tmp/meeting.review.txt"));
+    }
+}
@@ -0,0 +1,282 @@
+use crate::{configuration::CueRulesConfig, subtitle::wrap, transcript::Segment};
+
+fn is_sentence_end(word: &str) -> bool {
+    word.ends_with(['.', '!', '?', '…', '。', '！', '？'])
+}
+
+fn is_clause_end(word: &str) -> bool {
+    word.ends_with([',', ';', ':', '、', '，'])
+}
+
+/// Clause ends are preferred over a split this much closer to the middle, as a fraction of the text
+const CLAUSE_PREFERENCE: f64 = 0.25;
+
+fn length(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Words of `text` grouped by sentence
+pub fn sentences(text: &str) -> Vec<Vec<&str>> {
+    let mut sentences = vec![Vec::new()];
+
+    for word in text.split_whitespace() {
+        sentences.last_mut().unwrap().push(word);
+
+        if is_sentence_end(word) {
+            sentences.push(Vec::new());
+        }
+    }
+
+    sentences.retain(|sentence| !sentence.is_empty());
+    sentences
+}
+
+/// Lines of `text` shown with the speaker label in front, the label isn't part of the returned lines
+fn lines(speaker: Option<&str>, text: &str, rules: &CueRulesConfig) -> Vec<String> {
+    let Some(speaker) = speaker else { return wrap(text, rules.max_line_length) };
+
+    let prefix = format!("{speaker}: ");
+    let mut lines = wrap(&format!("{prefix}{text}"), rules.max_line_length);
+
+    if let Some(first) = lines.first_mut() {
+        *first = first.strip_prefix(prefix.trim_end()).unwrap_or(first).trim_start().to_owned();
+    }
+
+    lines.retain(|line| !line.is_empty());
+    lines
+}
+
+/// Splits the text of one whisper segment into cues that fit the rules
+struct Splitter<'a> {
+    rules: &'a CueRulesConfig,
+    speaker: Option<&'a str>,
+    /// Milliseconds per character of the segment
+    pace: f64,
+}
+
+impl Splitter<'_> {
+    fn fits(&self, text: &str) -> bool {
+        lines(self.speaker, text, self.rules).len() <= self.rules.max_lines.max(1)
+            && (length(text) as f64 * self.pace) as i64 <= self.rules.max_duration
+    }
+
+    /// Halve `words` where both sides are about as long, preferring the end of a clause
+    fn split(&self, words: &[&str]) -> Vec<String> {
+        let text = words.join(" ");
+
+        if self.fits(&text) {
+            return vec![text];
+        }
+
+        if words.len() == 1 {
+            // A single word too long for a line, e.g. text without spaces
+            let lines = wrap(&text, self.rules.max_line_length);
+
+            return lines.chunks(self.rules.max_lines.max(1)).map(|lines| lines.concat()).collect();
+        }
+
+        let total = length(&text) as f64;
+        let mut before = 0;
+
+        let (split, _) = (1..words.len())
+            .map(|index| {
+                before += length(words[index - 1]) + 1;
+
+                let balance = (before as f64 / total - 0.5).abs();
+                let score = if is_clause_end(words[index - 1]) { balance - CLAUSE_PREFERENCE } else { balance };
+
+                (index, score)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap();
+
+        let mut pieces = self.split(&words[..split]);
+        pieces.extend(self.split(&words[split..]));
+        pieces
+    }
+
+    /// Pieces of text that each fit a cue, whole sentences are kept together when they fit
+    fn pieces(&self, text: &str) -> Vec<String> {
+        let mut pieces: Vec<String> = Vec::new();
+
+        for piece in sentences(text).iter().flat_map(|sentence| self.split(sentence)) {
+            match pieces.last_mut() {
+                Some(last) if self.fits(&format!("{last} {piece}")) => {
+                    last.push(' ');
+                    last.push_str(&piece);
+                }
+                _ => pieces.push(piece),
+            }
+        }
+
+        pieces
+    }
+}
+
+/// Split `segments` into cues that fit the line, length and duration limits of `rules`, then adjust
+/// their timing for reading speed, minimum duration and the gap between cues
+pub fn shape_cues(segments: &[Segment], rules: &CueRulesConfig) -> Vec<Segment> {
+    let mut cues: Vec<Segment> = Vec::new();
+
+    for segment in segments {
+        let text = segment.text.trim();
+        let total = length(text).max(1) as i64;
+
+        let splitter = Splitter {
+            rules,
+            speaker: segment.speaker.as_deref(),
+            pace: segment.end.since(segment.start).max(0) as f64 / total as f64,
+        };
+
+        let mut elapsed = 0;
+        let first_piece = cues.len();
+
+        for piece in splitter.pieces(text) {
+            let start = segment.start.lerp(segment.end, elapsed, total);
+            elapsed = (elapsed + length(&piece) as i64 + 1).min(total);
+            let end = segment.start.lerp(segment.end, elapsed, total);
+
+            cues.push(Segment {
+                start,
+                end,
+                text: lines(segment.speaker.as_deref(), &piece, rules).join("\n"),
+                speaker: segment.speaker.clone(),
+                words: Vec::new(),
+            });
+        }
+
+        // Words go to the cue they start in
+        let pieces = &mut cues[first_piece..];
+
+        for word in &segment.words {
+            let piece = pieces.iter().rposition(|piece| piece.start <= word.start).unwrap_or(0);
+
+            pieces[piece].words.push(word.clone());
+        }
+    }
+
+    cues.sort_by_key(|cue| cue.start);
+
+    for index in 0..cues.len() {
+        let next_start = cues.get(index + 1).map(|next| next.start);
+        let cue = &mut cues[index];
+
+        let characters = length(&cue.text.replace('\n', " "));
+        let reading_time = (characters as f32 / rules.max_chars_per_second.max(1.0) * 1000.0).ceil() as i64;
+
+        let wanted = cue.start.offset(reading_time.max(rules.min_duration).min(rules.max_duration));
+        let longest = cue.start.offset(rules.max_duration);
+
+        cue.end = cue.end.max(wanted).min(longest);
+
+        if let Some(next_start) = next_start {
+            cue.end = cue.end.min(next_start.offset(-rules.min_gap)).max(cue.start);
+        }
+    }
+
+    cues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{timestamp::Timestamp, transcript::{fixtures::segment, Word}};
+
+    fn rules() -> CueRulesConfig {
+        CueRulesConfig {
+            enabled: true,
+            max_line_length: 42,
+            max_lines: 2,
+            max_chars_per_second: 17.0,
+            min_duration: 1000,
+            max_duration: 7000,
+            min_gap: 80,
+        }
+    }
+
+    #[test]
+    fn splits_long_segments_at_sentences() {
+        let text = "Welcome everyone to the weekly sync. Today we are going to review the recorder release, \
+            the transcription backlog and the translation work that started last week. Let's begin.";
+
+        let cues = shape_cues(&[segment(0, 15_000, text, None)], &rules());
+
+        assert!(cues.len() >= 3, "{cues:#?}");
+        assert_eq!(cues[0].text, "Welcome everyone to the weekly sync.");
+        assert!(cues.last().unwrap().text.ends_with("Let's begin."), "{cues:#?}");
+
+        for cue in &cues {
+            let lines: Vec<&str> = cue.text.lines().collect();
+
+            assert!(lines.len() <= 2, "{cue:?}");
+            assert!(lines.iter().all(|line| line.chars().count() <= 42), "{cue:?}");
+            assert!(cue.end.since(cue.start) <= 7000, "{cue:?}");
+        }
+
+        for pair in cues.windows(2) {
+            assert!(pair[1].start.since(pair[0].end) >= 80, "{pair:?}");
+        }
+
+        let words: Vec<&str> = cues.iter().flat_map(|cue| cue.text.split_whitespace()).collect();
+        assert_eq!(words, text.split_whitespace().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn prefers_clause_ends() {
+        let cues = shape_cues(&[segment(0, 6000, "When the meeting ends, the recorder stops and the transcription starts right away", None)], &CueRulesConfig { max_lines: 1, ..rules() });
+
+        assert_eq!(cues[0].text, "When the meeting ends,");
+    }
+
+    #[test]
+    fn keeps_short_sentences_together() {
+        let cues = shape_cues(&[segment(0, 3000, "Yes. Okay. Sounds good.", None)], &rules());
+
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].text, "Yes. Okay. Sounds good.");
+    }
+
+    #[test]
+    fn extends_short_cues_up_to_the_next_one() {
+        let cues = shape_cues(&[
+            segment(0, 300, "Hi", None),
+            segment(700, 900, "This line needs quite a bit more time to read", None),
+            segment(10_000, 10_500, "Bye", None),
+        ], &rules());
+
+        // Minimum duration, cut short by the gap before the next cue
+        assert_eq!((cues[0].start, cues[0].end), (Timestamp::from_millis(0), Timestamp::from_millis(620)));
+        // Reading speed, 45 characters at 17 per second
+        assert_eq!(cues[1].end, Timestamp::from_millis(700 + 2648));
+        assert_eq!(cues[2].end, Timestamp::from_millis(11_000));
+    }
+
+    #[test]
+    fn keeps_words_with_their_cue() {
+        let word = |text: &str, start: i64| Word { text: text.to_owned(), start: Timestamp::from_millis(start), end: Timestamp::from_millis(start + 400), confidence: 0.5 };
+
+        let segment = Segment {
+            words: vec![word("Yes.", 0), word("Then", 9000), word("again.", 9500)],
+            ..segment(0, 10_000, "Yes. Then again.", None)
+        };
+
+        let cues = shape_cues(&[segment], &rules());
+
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].words, [word("Yes.", 0)]);
+        assert_eq!(cues[1].words, [word("Then", 9000), word("again.", 9500)]);
+    }
+
+    #[test]
+    fn counts_speaker_labels_and_breaks_long_words() {
+        let cues = shape_cues(&[segment(0, 5000, "Recordscript transcribes meetings quickly", Some("Somebody"))], &CueRulesConfig { max_line_length: 24, ..rules() });
+
+        assert_eq!(cues[0].labelled_text(), "Somebody: Recordscript\ntranscribes");
+        assert_eq!(cues[1].labelled_text(), "Somebody: meetings\nquickly");
+
+        let cues = shape_cues(&[segment(0, 5000, &"あ".repeat(100), None)], &CueRulesConfig { max_line_length: 20, ..rules() });
+
+        assert_eq!(cues.len(), 3);
+        assert!(cues.iter().all(|cue| cue.text.chars().count() <= 41));
+    }
+}
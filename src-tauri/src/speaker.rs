@@ -0,0 +1,168 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::recorder::DeviceType;
+
+/// How often the recorder measures the level of each audio source
+pub const LEVEL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Levels are clamped to this, `level` reports digital silence as negative infinity
+const MIN_LEVEL_DB: f32 = -100.0;
+
+/// Segments where both sources are quieter than this aren't attributed to anyone
+const SILENCE_DB: f32 = -60.0;
+
+/// Who is talking, told apart by whether the microphone or the system audio is louder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Speaker {
+    /// Heard through our microphone
+    Me,
+    /// Heard through the speaker, e.g. the remote participants of a call
+    Others,
+}
+
+/// RMS levels of the microphone and the system audio over a recording, as `(seconds, dB)`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SourceLevels {
+    microphone: Vec<(f32, f32)>,
+    speaker: Vec<(f32, f32)>,
+}
+
+fn mean_db(levels: &[(f32, f32)], start: f32, end: f32) -> Option<f32> {
+    if levels.is_empty() {
+        return None;
+    }
+
+    let first = levels.partition_point(|(time, _)| *time < start);
+    let last = levels.partition_point(|(time, _)| *time <= end);
+
+    // Segments shorter than a measurement use the one they fall in
+    let window = if first < last {
+        &levels[first..last]
+    } else {
+        let nearest = first.min(levels.len() - 1);
+        &levels[nearest..=nearest]
+    };
+
+    let power = window.iter().map(|(_, db)| 10f32.powf(db / 10.0)).sum::<f32>() / window.len() as f32;
+
+    Some(10.0 * power.max(1e-10).log10())
+}
+
+impl SourceLevels {
+    pub fn push(&mut self, device_type: DeviceType, time: f32, db: f32) {
+        let db = if db.is_nan() { MIN_LEVEL_DB } else { db.max(MIN_LEVEL_DB) };
+
+        match device_type {
+            DeviceType::Microphone => self.microphone.push((time, db)),
+            DeviceType::Speaker => self.speaker.push((time, db)),
+        }
+    }
+
+    /// Who is talking between `start` and `end` seconds, `None` when a source wasn't recorded or both are silent
+    pub fn speaker_at(&self, start: f32, end: f32) -> Option<Speaker> {
+        let microphone = mean_db(&self.microphone, start, end)?;
+        let speaker = mean_db(&self.speaker, start, end)?;
+
+        if microphone.max(speaker) < SILENCE_DB {
+            return None;
+        }
+
+        Some(if microphone >= speaker { Speaker::Me } else { Speaker::Others })
+    }
+
+    /// Recordings with the same name in different folders don't share levels, `None` when `media_path` doesn't exist
+    fn key(media_path: &Path) -> Option<String> {
+        use sha2::{Digest as _, Sha256};
+
+        let canonical_path = media_path.canonicalize().ok()?;
+        let digest = Sha256::digest(canonical_path.as_os_str().as_encoded_bytes());
+
+        Some(digest.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
+
+    /// Levels are kept in the cache directory, keyed by the full path of the recording they were measured on
+    fn path(media_path: &Path) -> Option<PathBuf> {
+        Some(crate::project_directory().cache_dir().join("levels").join(format!("{}.json", Self::key(media_path)?)))
+    }
+
+    pub fn save(&self, media_path: &Path) -> anyhow::Result<()> {
+        let path = Self::path(media_path).ok_or_else(|| anyhow::anyhow!("Invalid recording path {}", media_path.display()))?;
+
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, serde_json::to_vec(self)?)?;
+
+        Ok(())
+    }
+
+    /// Levels measured while recording `media_path`, `None` for files that weren't recorded by us
+    pub fn load(media_path: &Path) -> Option<Self> {
+        let data = std::fs::read(Self::path(media_path)?).ok()?;
+
+        serde_json::from_slice(&data).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Levels measured every 100ms, `microphone` and `speaker` return the dB at a time
+    fn levels(length: f32, microphone: impl Fn(f32) -> f32, speaker: impl Fn(f32) -> f32) -> SourceLevels {
+        let mut levels = SourceLevels::default();
+
+        for index in 0..(length * 10.0) as usize {
+            let time = index as f32 / 10.0;
+
+            levels.push(DeviceType::Microphone, time, microphone(time));
+            levels.push(DeviceType::Speaker, time, speaker(time));
+        }
+
+        levels
+    }
+
+    #[test]
+    fn attributes_louder_source() {
+        // We talk for the first 5s, the remote side answers while our microphone picks up its echo
+        let levels = levels(
+            10.0,
+            |time| if time < 5.0 { -25.0 } else { -50.0 },
+            |time| if time < 5.0 { f32::NEG_INFINITY } else { -20.0 },
+        );
+
+        assert_eq!(levels.speaker_at(0.5, 4.5), Some(Speaker::Me));
+        assert_eq!(levels.speaker_at(5.5, 9.0), Some(Speaker::Others));
+        assert_eq!(levels.speaker_at(1.0, 6.0), Some(Speaker::Me));
+        assert_eq!(levels.speaker_at(7.01, 7.02), Some(Speaker::Others));
+        assert_eq!(levels.speaker_at(20.0, 21.0), Some(Speaker::Others));
+    }
+
+    #[test]
+    fn keys_levels_by_full_path() {
+        let dir = std::env::temp_dir().join(format!("recordscript-levels-{}", std::process::id()));
+
+        for folder in ["monday", "tuesday"] {
+            std::fs::create_dir_all(dir.join(folder)).unwrap();
+            std::fs::write(dir.join(folder).join("call.mp4"), b"").unwrap();
+        }
+
+        let monday = SourceLevels::key(&dir.join("monday/call.mp4")).unwrap();
+
+        assert_ne!(monday, SourceLevels::key(&dir.join("tuesday/call.mp4")).unwrap());
+        assert_eq!(Some(monday), SourceLevels::key(&dir.join("tuesday/../monday/./call.mp4")));
+        assert_eq!(SourceLevels::key(&dir.join("monday/missing.mp4")), None);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn skips_silence_and_missing_sources() {
+        let silent = levels(5.0, |_| -80.0, |_| f32::NEG_INFINITY);
+        assert_eq!(silent.speaker_at(1.0, 2.0), None);
+
+        let mut microphone_only = SourceLevels::default();
+        microphone_only.push(DeviceType::Microphone, 0.0, -20.0);
+        assert_eq!(microphone_only.speaker_at(0.0, 1.0), None);
+    }
+}
@@ -0,0 +1,528 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{configuration::BilingualConfig, timestamp::Timestamp, transcript::Segment};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+}
+
+impl SubtitleFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+            SubtitleFormat::Ass => "ass",
+        }
+    }
+
+    /// Format of a subtitle file by its extension, SSA is read like ASS
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            "ass" | "ssa" => Some(SubtitleFormat::Ass),
+            _ => None,
+        }
+    }
+}
+
+/// Cue text can't contain the arrow separating cue timings
+fn escape_cue(text: &str) -> String {
+    text.replace("-->", "->")
+}
+
+pub fn to_srt(segments: &[Segment]) -> String {
+    segments.iter()
+        .enumerate()
+        .map(|(index, segment)| format!(
+            "\n{}\n{} --> {}\n{}\n",
+            index + 1,
+            segment.start.srt(),
+            segment.end.srt(),
+            escape_cue(&segment.labelled_text())
+        ))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn to_vtt(segments: &[Segment]) -> String {
+    let cues: String = segments.iter()
+        .map(|segment| format!(
+            "\n{} --> {}\n{}\n",
+            segment.start.vtt(),
+            segment.end.vtt(),
+            escape_cue(&segment.labelled_text())
+        ))
+        .collect();
+
+    format!("WEBVTT\n{cues}")
+}
+
+/// Lines are separated by `\N` and braces would start override tags
+fn ass_text(text: &str) -> String {
+    text.trim().replace('{', "(").replace('}', ")").replace('\n', "\\N")
+}
+
+const ASS_HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 384
+PlayResY: 288
+WrapStyle: 2
+ScaledBorderAndShadow: yes
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Original,Arial,16,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,1,0,2,10,10,10,1
+Style: Translation,Arial,14,&H0000E5FF,&H000000FF,&H00000000,&H80000000,0,1,0,0,100,100,0,0,1,1,0,2,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+fn ass_dialogue(segment: &Segment, text: &str) -> String {
+    format!(
+        "Dialogue: 0,{},{},Original,{},0,0,0,,{text}\n",
+        segment.start.ass(),
+        segment.end.ass(),
+        segment.speaker.as_deref().unwrap_or_default().replace(',', " "),
+    )
+}
+
+pub fn to_ass(segments: &[Segment]) -> String {
+    let events: String = segments.iter()
+        .map(|segment| ass_dialogue(segment, &ass_text(&segment.labelled_text())))
+        .collect();
+
+    format!("{ASS_HEADER}{events}")
+}
+
+/// Render `segments` in `format`
+pub fn to_string(segments: &[Segment], format: SubtitleFormat) -> String {
+    match format {
+        SubtitleFormat::Srt => to_srt(segments),
+        SubtitleFormat::Vtt => to_vtt(segments),
+        SubtitleFormat::Ass => to_ass(segments),
+    }
+}
+
+/// Cues of SRT and WebVTT, both are blocks separated by blank lines with a `start --> end` line
+/// before the text, blocks without one like the WebVTT header, notes and styles are skipped
+fn parse_text_cues(content: &str) -> anyhow::Result<Vec<Segment>> {
+    let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n").replace('\r', "\n");
+    let mut segments = Vec::new();
+    let mut block: Vec<&str> = Vec::new();
+
+    for line in content.lines().chain([""]) {
+        if !line.trim().is_empty() {
+            block.push(line);
+            continue;
+        }
+
+        let Some(timing) = block.iter().position(|line| line.contains("-->")) else {
+            block.clear();
+            continue;
+        };
+
+        let (start, end) = block[timing].split_once("-->").unwrap();
+        // WebVTT cue settings like `align:start` follow the end time
+        let end = end.split_whitespace().next().unwrap_or_default();
+
+        let (Some(start), Some(end)) = (Timestamp::parse(start), Timestamp::parse(end)) else {
+            anyhow::bail!("Invalid cue timing {:?} in cue {}", block[timing], segments.len() + 1);
+        };
+
+        segments.push(Segment { start, end, text: block[timing + 1..].join("\n"), speaker: None, words: Vec::new() });
+        block.clear();
+    }
+
+    Ok(segments)
+}
+
+pub fn parse_srt(content: &str) -> anyhow::Result<Vec<Segment>> {
+    parse_text_cues(content)
+}
+
+pub fn parse_vtt(content: &str) -> anyhow::Result<Vec<Segment>> {
+    anyhow::ensure!(content.trim_start_matches('\u{feff}').starts_with("WEBVTT"), "Not a WebVTT file, it doesn't start with WEBVTT");
+
+    let mut segments = parse_text_cues(content)?;
+
+    for segment in &mut segments {
+        // Voice spans name the speaker, `<v Somebody>Hello</v>`
+        let Some(voice) = segment.text.strip_prefix("<v").filter(|voice| voice.starts_with([' ', '.'])) else { continue };
+        let Some((tag, text)) = voice.split_once('>') else { continue };
+
+        let speaker = tag.split_once(' ').map(|(_, name)| name.trim()).unwrap_or_default();
+
+        if !speaker.is_empty() {
+            segment.speaker = Some(speaker.to_owned());
+        }
+
+        segment.text = text.replace("</v>", "");
+    }
+
+    Ok(segments)
+}
+
+/// Text of an ASS dialogue without override tags, with its line breaks and hard spaces
+fn ass_plain_text(text: &str) -> String {
+    let mut plain = String::new();
+    let mut depth = 0;
+
+    for char in text.chars() {
+        match char {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            _ if depth == 0 => plain.push(char),
+            _ => {}
+        }
+    }
+
+    plain.replace("\\N", "\n").replace("\\n", "\n").replace("\\h", " ")
+}
+
+pub fn parse_ass(content: &str) -> anyhow::Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut in_events = false;
+    let mut format: Vec<String> = Vec::new();
+
+    for line in content.trim_start_matches('\u{feff}').lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+
+        if !in_events { continue };
+
+        if let Some(fields) = line.strip_prefix("Format:") {
+            format = fields.split(',').map(|field| field.trim().to_ascii_lowercase()).collect();
+            continue;
+        }
+
+        let Some(fields) = line.strip_prefix("Dialogue:") else { continue };
+
+        anyhow::ensure!(!format.is_empty(), "Dialogue before the Format line of the events");
+
+        // The text is last and may contain commas
+        let fields: Vec<&str> = fields.trim_start().splitn(format.len(), ',').collect();
+        let field = |name: &str| format.iter().position(|field| field == name).and_then(|index| fields.get(index)).copied();
+
+        let (Some(start), Some(end), Some(text)) = (field("start").and_then(Timestamp::parse), field("end").and_then(Timestamp::parse), field("text")) else {
+            anyhow::bail!("Invalid dialogue {:?}", line);
+        };
+
+        let speaker = field("name").map(str::trim).filter(|name| !name.is_empty()).map(str::to_owned);
+        let mut text = ass_plain_text(text);
+
+        // Our own files repeat the speaker in front of the text
+        if let Some(unlabelled) = speaker.as_ref().and_then(|speaker| text.strip_prefix(&format!("{speaker}: "))) {
+            text = unlabelled.to_owned();
+        }
+
+        segments.push(Segment { start, end, text, speaker, words: Vec::new() });
+    }
+
+    // Events don't have to be in order
+    segments.sort_by_key(|segment| segment.start);
+
+    Ok(segments)
+}
+
+/// Read subtitles written in `format`
+pub fn parse(content: &str, format: SubtitleFormat) -> anyhow::Result<Vec<Segment>> {
+    match format {
+        SubtitleFormat::Srt => parse_srt(content),
+        SubtitleFormat::Vtt => parse_vtt(content),
+        SubtitleFormat::Ass => parse_ass(content),
+    }
+}
+
+/// Wrap `text` into lines of at most `max_length` characters, words longer than that
+/// (or text without spaces like Japanese) are broken anywhere
+pub fn wrap(text: &str, max_length: usize) -> Vec<String> {
+    let max_length = max_length.max(1);
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+
+        while !word.is_empty() {
+            let line_length = line.chars().count();
+            let separator = usize::from(line_length > 0);
+
+            if line_length + separator + word.len() <= max_length {
+                if separator == 1 { line.push(' ') };
+                line.extend(word.drain(..));
+            } else if line_length > 0 {
+                lines.push(std::mem::take(&mut line));
+            } else {
+                line.extend(word.drain(..max_length));
+                lines.push(std::mem::take(&mut line));
+            }
+        }
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Cut `text` into `parts` pieces of about equal length, at spaces when there are any
+fn divide(text: &str, parts: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+
+    if words.len() < parts {
+        // Not enough words, split by characters instead
+        let chars: Vec<char> = text.trim().chars().collect();
+        let size = chars.len().div_ceil(parts).max(1);
+
+        let mut pieces: Vec<String> = chars.chunks(size).map(|chunk| chunk.iter().collect::<String>().trim().to_owned()).collect();
+        pieces.resize(parts, String::new());
+
+        return pieces;
+    }
+
+    let total: usize = words.iter().map(|word| word.chars().count() + 1).sum();
+    let mut pieces = vec![Vec::new(); parts];
+    let mut length = 0;
+
+    for (index, word) in words.iter().enumerate() {
+        // Leave at least one word for each remaining piece
+        let by_length = length * parts / total;
+        let piece = by_length.max((parts + index).saturating_sub(words.len())).min(parts - 1);
+
+        pieces[piece].push(*word);
+        length += word.chars().count() + 1;
+    }
+
+    pieces.into_iter().map(|words| words.join(" ")).collect()
+}
+
+/// Original and translated text of a cue, wrapped to the line length
+#[derive(Debug, Clone, PartialEq)]
+pub struct BilingualCue {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub speaker: Option<String>,
+    pub original: Vec<String>,
+    pub translation: Vec<String>,
+}
+
+/// Pair every segment with its translation, cues with more lines than allowed are split into
+/// consecutive cues sharing the time proportionally to their length
+pub fn bilingual_cues(original: &[Segment], translated: &[Segment], config: &BilingualConfig) -> Vec<BilingualCue> {
+    let max_lines = config.max_lines.max(2);
+
+    original.iter()
+        .zip(translated)
+        .flat_map(|(original, translated)| {
+            let original_text = original.labelled_text();
+            let translated_text = translated.text.trim().to_owned();
+
+            let fits = |originals: &[String], translations: &[String]| originals.iter()
+                .zip(translations)
+                .all(|(original, translation)| wrap(original, config.max_line_length).len() + wrap(translation, config.max_line_length).len() <= max_lines);
+
+            // A single character per cue always fits
+            let most_parts = original_text.chars().count().max(translated_text.chars().count()).max(1);
+
+            let mut parts = 1;
+            let (originals, translations) = loop {
+                let originals = divide(&original_text, parts);
+                let translations = divide(&translated_text, parts);
+
+                if parts >= most_parts || fits(&originals, &translations) {
+                    break (originals, translations);
+                }
+
+                parts += 1;
+            };
+
+            let total = originals.iter().map(|text| text.chars().count().max(1)).sum::<usize>() as i64;
+            let mut start = original.start;
+            let mut elapsed = 0;
+
+            originals.into_iter()
+                .zip(translations)
+                .map(|(original_part, translation_part)| {
+                    elapsed += original_part.chars().count().max(1) as i64;
+
+                    let end = original.start.lerp(original.end, elapsed, total);
+
+                    let cue = BilingualCue {
+                        start,
+                        end,
+                        speaker: original.speaker.clone(),
+                        original: wrap(&original_part, config.max_line_length),
+                        translation: wrap(&translation_part, config.max_line_length),
+                    };
+
+                    start = end;
+
+                    cue
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Two-line cues, the original above its translation
+pub fn bilingual_to_text_cues(cues: &[BilingualCue]) -> Vec<Segment> {
+    cues.iter()
+        .map(|cue| Segment {
+            start: cue.start,
+            end: cue.end,
+            text: cue.original.iter().chain(&cue.translation).cloned().collect::<Vec<_>>().join("\n"),
+            // Already part of the original text
+            speaker: None,
+            words: Vec::new(),
+        })
+        .collect()
+}
+
+/// ASS with the original in the `Original` style and the translation in the `Translation` style below it
+pub fn bilingual_to_ass(cues: &[BilingualCue]) -> String {
+    let events: String = cues.iter()
+        .map(|cue| {
+            let segment = Segment { start: cue.start, end: cue.end, text: String::new(), speaker: cue.speaker.clone(), words: Vec::new() };
+            let text = format!("{}\\N{{\\rTranslation}}{}", ass_text(&cue.original.join("\n")), ass_text(&cue.translation.join("\n")));
+
+            ass_dialogue(&segment, &text)
+        })
+        .collect();
+
+    format!("{ASS_HEADER}{events}")
+}
+
+/// Where the bilingual subtitles of `name.srt` are saved, e.g. `name.en-de.ass`
+pub fn bilingual_path(transcription_path: &std::path::Path, source: &str, target: &str, format: SubtitleFormat) -> std::path::PathBuf {
+    transcription_path.with_extension(format!("{source}-{target}.{}", format.extension()))
+}
+
+/// Render `cues` in `format`
+pub fn bilingual_to_string(cues: &[BilingualCue], format: SubtitleFormat) -> String {
+    match format {
+        SubtitleFormat::Srt => to_srt(&bilingual_to_text_cues(cues)),
+        SubtitleFormat::Vtt => to_vtt(&bilingual_to_text_cues(cues)),
+        SubtitleFormat::Ass => bilingual_to_ass(cues),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::fixtures::segment;
+
+    fn config(max_line_length: usize, max_lines: usize) -> BilingualConfig {
+        BilingualConfig { enabled: true, format: SubtitleFormat::Srt, max_line_length, max_lines }
+    }
+
+    #[test]
+    fn parses_srt() {
+        let srt = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\nHello\r\nthere\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\nMe: Bye\r\n";
+
+        let segments = parse_srt(srt).unwrap();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].start, segments[0].end), (Timestamp::from_millis(1000), Timestamp::from_millis(2500)));
+        assert_eq!(segments[0].text, "Hello\nthere");
+        assert_eq!(segments[1].labelled_text(), "Me: Bye");
+
+        assert_eq!(parse_srt(&to_srt(&segments)).unwrap().iter().map(Segment::labelled_text).collect::<Vec<_>>(), ["Hello\nthere", "Me: Bye"]);
+
+        let err = parse_srt("1\n00:00:01,000 --> soon\nHello\n").unwrap_err();
+        assert!(err.to_string().contains("cue 1"), "{err}");
+    }
+
+    #[test]
+    fn parses_vtt() {
+        let vtt = "WEBVTT - Meeting\n\nNOTE written by hand\n\nintro\n00:01.000 --> 00:02.000 align:start\n<v Somebody>Hello</v>\n\n00:00:03.000 --> 00:00:04.000\nBye\n";
+
+        let segments = parse_vtt(vtt).unwrap();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].speaker.as_deref(), Some("Somebody"));
+        assert_eq!(segments[0].text, "Hello");
+        assert_eq!(segments[1].end, Timestamp::from_millis(4000));
+
+        assert!(parse_vtt("1\n00:00:01,000 --> 00:00:02,000\nHello\n").is_err());
+    }
+
+    #[test]
+    fn parses_ass() {
+        let segments = [segment(61_000, 62_000, "Good morning", Some("Me")), segment(1000, 3000, "First, of all\nthis", None)];
+
+        let parsed = parse_ass(&to_ass(&segments)).unwrap();
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].text, "First, of all\nthis");
+        assert_eq!(parsed[1].speaker.as_deref(), Some("Me"));
+        assert_eq!(parsed[1].text, "Good morning");
+        assert_eq!((parsed[1].start, parsed[1].end), (Timestamp::from_millis(61_000), Timestamp::from_millis(62_000)));
+
+        let bilingual = bilingual_to_ass(&bilingual_cues(&[segment(0, 1000, "Hello", None)], &[segment(0, 1000, "Hallo", None)], &config(42, 4)));
+        assert_eq!(parse_ass(&bilingual).unwrap()[0].text, "Hello\nHallo");
+    }
+
+    #[test]
+    fn wraps_words_and_unspaced_text() {
+        assert_eq!(wrap("the quick brown fox jumps", 10), ["the quick", "brown fox", "jumps"]);
+        assert_eq!(wrap("おはようございます", 4).iter().map(|line| line.chars().count()).collect::<Vec<_>>(), [4, 4, 1]);
+        assert!(wrap("  ", 10).is_empty());
+    }
+
+    #[test]
+    fn pairs_short_lines_in_one_cue() {
+        let cues = bilingual_cues(&[segment(1000, 3000, " Good morning", Some("Me"))], &[segment(1000, 3000, "Guten Morgen", Some("Me"))], &config(42, 4));
+
+        assert_eq!(cues, [BilingualCue {
+            start: Timestamp::from_millis(1000),
+            end: Timestamp::from_millis(3000),
+            speaker: Some("Me".to_owned()),
+            original: vec!["Me: Good morning".to_owned()],
+            translation: vec!["Guten Morgen".to_owned()],
+        }]);
+
+        let srt = bilingual_to_string(&cues, SubtitleFormat::Srt);
+        assert_eq!(srt, "\n1\n00:00:01,000 --> 00:00:03,000\nMe: Good morning\nGuten Morgen\n");
+
+        let vtt = bilingual_to_string(&cues, SubtitleFormat::Vtt);
+        assert!(vtt.starts_with("WEBVTT\n"));
+        assert!(vtt.contains("00:00:01.000 --> 00:00:03.000\nMe: Good morning\nGuten Morgen\n"), "{vtt}");
+
+        let ass = bilingual_to_string(&cues, SubtitleFormat::Ass);
+        assert!(ass.contains("Style: Translation,"));
+        assert!(ass.ends_with("Dialogue: 0,0:00:01.00,0:00:03.00,Original,Me,0,0,0,,Me: Good morning\\N{\\rTranslation}Guten Morgen\n"), "{ass}");
+    }
+
+    #[test]
+    fn splits_cues_over_the_line_limit() {
+        let original = "We shipped the new recorder last week and the feedback so far has been very positive";
+        let translation = "Wir haben den neuen Rekorder letzte Woche veröffentlicht und das Feedback war bisher sehr positiv";
+
+        let cues = bilingual_cues(&[segment(0, 10_000, original, None)], &[segment(0, 10_000, translation, None)], &config(30, 4));
+
+        assert!(cues.len() > 1);
+        assert_eq!(cues.first().unwrap().start, Timestamp::from_millis(0));
+        assert_eq!(cues.last().unwrap().end, Timestamp::from_millis(10_000));
+
+        for pair in cues.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+
+        for cue in &cues {
+            assert!(cue.original.len() + cue.translation.len() <= 4, "{cue:?}");
+            assert!(cue.original.iter().chain(&cue.translation).all(|line| line.chars().count() <= 30), "{cue:?}");
+            assert!(!cue.original.is_empty() && !cue.translation.is_empty(), "{cue:?}");
+        }
+
+        let rejoined: Vec<String> = cues.iter().flat_map(|cue| cue.original.clone()).collect();
+        assert_eq!(rejoined.join(" "), original);
+    }
+}
@@ -0,0 +1,83 @@
+//! Minimal HTTP server for tests that talk to remote services
+
+use std::io::{BufRead as _, BufReader, Read as _, Write as _};
+use std::net::TcpListener;
+
+pub struct Request {
+    /// How many requests were answered before this one
+    pub index: usize,
+    /// Header names are lowercased
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct Response {
+    pub status: &'static str,
+    pub headers: Vec<String>,
+    pub body: Vec<u8>,
+    /// Closes the connection after sending this many bytes of `body`
+    pub cut_after: Option<usize>,
+}
+
+impl Response {
+    pub fn new(status: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self { status, headers: Vec::new(), body: body.into(), cut_after: None }
+    }
+
+    pub fn json(status: &'static str, value: serde_json::Value) -> Self {
+        Self { headers: vec!["Content-Type: application/json".to_owned()], ..Self::new(status, value.to_string()) }
+    }
+}
+
+/// Answers every request with `handler` on a background thread, returns the server's base URL
+pub fn serve(handler: impl Fn(&Request) -> Response + Send + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    std::thread::spawn(move || {
+        for (index, stream) in listener.incoming().enumerate() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+
+                if let Some((key, value)) = line.split_once(':') {
+                    headers.push((key.trim().to_ascii_lowercase(), value.trim().to_owned()));
+                }
+            }
+
+            let mut request = Request { index, headers, body: Vec::new() };
+
+            let content_length = request.header("content-length").and_then(|length| length.parse().ok()).unwrap_or(0);
+            request.body = vec![0; content_length];
+            if reader.read_exact(&mut request.body).is_err() {
+                continue;
+            }
+
+            let response = handler(&request);
+            let headers: String = response.headers.iter().map(|header| format!("{header}\r\n")).collect();
+
+            let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\n{headers}Connection: close\r\n\r\n", response.status, response.body.len());
+
+            let sent = match response.cut_after {
+                Some(cut_after) => &response.body[..cut_after],
+                None => &response.body,
+            };
+
+            let _ = stream.write_all(sent);
+        }
+    });
+
+    url
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+/// Position in a recording in whole milliseconds, never negative
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    pub fn from_millis(milliseconds: i64) -> Self {
+        Self(milliseconds.max(0))
+    }
+
+    /// Whisper timestamps count 10ms units
+    pub fn from_centiseconds(centiseconds: i64) -> Self {
+        Self::from_millis(centiseconds.saturating_mul(10))
+    }
+
+    pub fn as_secs_f32(&self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+
+    /// Reads the notation of SRT, WebVTT and ASS: `01:02:03,045`, `02:03.045` or `1:02:03.04`
+    pub fn parse(text: &str) -> Option<Self> {
+        let (clock, fraction) = text.trim().split_once([',', '.']).unwrap_or((text.trim(), ""));
+
+        let number = |digits: &str| Some(digits)
+            .filter(|digits| (1..=3).contains(&digits.len()) && digits.bytes().all(|byte| byte.is_ascii_digit()))
+            .and_then(|digits| digits.parse::<i64>().ok());
+
+        let mut parts = clock.split(':').rev().map(number);
+        let seconds = parts.next()??;
+        let minutes = parts.next().unwrap_or(Some(0))?;
+        let hours = parts.next().unwrap_or(Some(0))?;
+
+        if parts.next().is_some() || seconds >= 60 || minutes >= 60 {
+            return None;
+        }
+
+        // Fractions are decimal, `.5` is half a second whether written with one, two or three digits
+        let milliseconds = match fraction.len() {
+            0 => 0,
+            length => number(fraction)? * 10i64.pow(3u32.checked_sub(length as u32)?),
+        };
+
+        Some(Self((hours * 3600 + minutes * 60 + seconds) * 1000 + milliseconds))
+    }
+
+    fn parts(&self) -> (i64, i64, i64, i64) {
+        (self.0 / 3_600_000, self.0 / 60_000 % 60, self.0 / 1000 % 60, self.0 % 1000)
+    }
+
+    /// `00:01:02,345`
+    pub fn srt(&self) -> String {
+        let (hours, minutes, seconds, milliseconds) = self.parts();
+
+        format!("{hours:02}:{minutes:02}:{seconds:02},{milliseconds:03}")
+    }
+
+    /// `00:01:02.345`
+    pub fn vtt(&self) -> String {
+        let (hours, minutes, seconds, milliseconds) = self.parts();
+
+        format!("{hours:02}:{minutes:02}:{seconds:02}.{milliseconds:03}")
+    }
+
+    /// `0:01:02.34`
+    pub fn ass(&self) -> String {
+        let (hours, minutes, seconds, milliseconds) = self.parts();
+
+        format!("{hours}:{minutes:02}:{seconds:02}.{:02}", milliseconds / 10)
+    }
+
+    /// `self` moved `milliseconds` later, or earlier when negative, stopping at zero
+    pub fn offset(&self, milliseconds: i64) -> Self {
+        Self::from_millis(self.0.saturating_add(milliseconds))
+    }
+
+    /// `self` multiplied by `factor`, e.g. 25 / 23.976 for subtitles timed at the wrong framerate
+    pub fn scale(&self, factor: f64) -> Self {
+        Self::from_millis((self.0 as f64 * factor).round() as i64)
+    }
+
+    /// Milliseconds from `earlier` to `self`, negative when `earlier` is later
+    pub fn since(&self, earlier: Timestamp) -> i64 {
+        self.0 - earlier.0
+    }
+
+    /// The point `numerator / denominator` of the way from `self` to `end`, rounded down
+    pub fn lerp(&self, end: Timestamp, numerator: i64, denominator: i64) -> Self {
+        if denominator == 0 {
+            return *self;
+        }
+
+        Self::from_millis(self.0 + (end.0 - self.0) * numerator / denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_every_notation() {
+        let timestamp = Timestamp::from_millis(3_723_045);
+
+        assert_eq!(timestamp.srt(), "01:02:03,045");
+        assert_eq!(timestamp.vtt(), "01:02:03.045");
+        assert_eq!(timestamp.ass(), "1:02:03.04");
+
+        assert_eq!(Timestamp::default().srt(), "00:00:00,000");
+        assert_eq!(Timestamp::from_millis(100 * 3_600_000).vtt(), "100:00:00.000");
+    }
+
+    #[test]
+    fn stays_exact_on_long_recordings() {
+        // f32 only has 24 bits of mantissa, 10 hours in milliseconds doesn't fit
+        let timestamp = Timestamp::from_centiseconds(3_599_999);
+
+        assert_eq!(timestamp.srt(), "09:59:59,990");
+        assert_eq!(Timestamp::from_millis(36_000_001).srt(), "10:00:00,001");
+    }
+
+    #[test]
+    fn clamps_at_zero() {
+        assert_eq!(Timestamp::from_millis(-5), Timestamp::default());
+        assert_eq!(Timestamp::from_millis(300).offset(-500), Timestamp::default());
+        assert_eq!(Timestamp::from_millis(300).offset(200), Timestamp::from_millis(500));
+        assert_eq!(Timestamp::from_millis(300).since(Timestamp::from_millis(500)), -200);
+    }
+
+    #[test]
+    fn parses_every_notation() {
+        assert_eq!(Timestamp::parse("01:02:03,045"), Some(Timestamp::from_millis(3_723_045)));
+        assert_eq!(Timestamp::parse("01:02:03.045"), Some(Timestamp::from_millis(3_723_045)));
+        assert_eq!(Timestamp::parse("02:03.045"), Some(Timestamp::from_millis(123_045)));
+        assert_eq!(Timestamp::parse("1:02:03.04"), Some(Timestamp::from_millis(3_723_040)));
+        assert_eq!(Timestamp::parse(" 0:00:01.5 "), Some(Timestamp::from_millis(1500)));
+        assert_eq!(Timestamp::parse("100:00:00.000"), Some(Timestamp::from_millis(360_000_000)));
+
+        for invalid in ["", "1:2:3:4", "00:61:00,000", "00:00:00,0000", "00:00:-1,000", "aa:00:00,000", "00::00,000"] {
+            assert_eq!(Timestamp::parse(invalid), None, "{invalid}");
+        }
+
+        let timestamp = Timestamp::from_millis(3_723_045);
+        assert_eq!(Timestamp::parse(&timestamp.srt()), Some(timestamp));
+        assert_eq!(Timestamp::parse(&timestamp.vtt()), Some(timestamp));
+    }
+
+    #[test]
+    fn scales() {
+        assert_eq!(Timestamp::from_millis(23_976).scale(25.0 / 23.976), Timestamp::from_millis(25_000));
+        assert_eq!(Timestamp::from_millis(1000).scale(0.5), Timestamp::from_millis(500));
+    }
+
+    #[test]
+    fn interpolates() {
+        let (start, end) = (Timestamp::from_millis(1000), Timestamp::from_millis(2000));
+
+        assert_eq!(start.lerp(end, 1, 3), Timestamp::from_millis(1333));
+        assert_eq!(start.lerp(end, 3, 3), end);
+        assert_eq!(start.lerp(end, 1, 0), start);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{io::{Cursor, Read}, path::PathBuf, sync::{Arc, Mutex}};
+use std::{collections::HashMap, io::{Cursor, Read}, path::{Path, PathBuf}, sync::{Arc, Mutex}};
 
 use anyhow::Context as _;
 
@@ -24,10 +24,11 @@ impl ModelDirectory for directories::ProjectDirs {
     }
 }
 
-#[derive(Debug, EnumIter, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, Serialize, Deserialize)]
 pub enum Category {
     Recommended,
     Other,
+    Imported,
 }
 
 impl Category {
@@ -35,13 +36,16 @@ impl Category {
         match self {
             Category::Recommended => "==== Recommended Models ====",
             Category::Other => "==== Other Models ====",
+            Category::Imported => "==== Imported Models ====",
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Type {
     Whisper,
     Quantized,
+    Custom,
 }
 
 impl Type {
@@ -49,131 +53,252 @@ impl Type {
         match self {
             Type::Whisper => "Whisper",
             Type::Quantized => "Quantized",
+            Type::Custom => "Custom",
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, EnumIter)]
-pub enum Model {
-    SmallDiarize,
-    TinyWhisper,
-    TinyEnWhisper,
-    TinyQuantized,
-    TinyEnQuantized,
-    BaseWhisper,
-    BaseEnWhisper,
-    BaseQuantized,
-    BaseEnQuantized,
-    SmallWhisper,
-    SmallEnWhisper,
-    SmallQuantized,
-    SmallEnQuantized,
-    MediumWhisper,
-    MediumQuantized,
-    MediumEnQuantized,
-    LargeWhisper,
-    LargeQuantized,
-}
-
-// https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny-q5_1.bin?download=true
-impl Model {
-    pub fn name(&self) -> &'static str {
-        match self {
-            Model::TinyWhisper => "Tiny",
-            Model::TinyEnWhisper => "Tiny English",
-            Model::TinyQuantized => "Tiny",
-            Model::TinyEnQuantized => "Tiny English",
-            Model::BaseWhisper => "Base",
-            Model::BaseEnWhisper => "Base English",
-            Model::BaseQuantized => "Base",
-            Model::BaseEnQuantized => "Base English",
-            Model::SmallWhisper => "Small",
-            Model::SmallEnWhisper => "Small English",
-            Model::SmallQuantized => "Small",
-            Model::SmallEnQuantized => "Small English",
-            Model::SmallDiarize => "Small Diarize",
-            Model::MediumWhisper => "Medium",
-            Model::MediumQuantized => "Medium",
-            Model::MediumEnQuantized => "Medium English",
-            Model::LargeWhisper => "Large",
-            Model::LargeQuantized => "Large",
+/// Model list shipped with the app
+const BUNDLED_MANIFEST: &str = include_str!("../models.json");
+
+/// Model selected when nothing else is
+const DEFAULT_MODEL: &str = "SmallWhisper";
+
+/// Entry of the model manifest
+///
+/// Refer to this for the memory and disk usage:
+/// - https://huggingface.co/ggerganov/whisper.cpp
+/// - https://huggingface.co/akashmjn/tinydiarize-whisper.cpp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub name: String,
+    /// Download URL, relative to the manifest's `base_url` unless it's absolute
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Use this file name when saving to file system
+    pub file_name: String,
+    /// Average memory usage in MB
+    pub memory_usage: usize,
+    /// Disk usage in MB
+    pub disk_usage: usize,
+    /// Languages the model is restricted to, `None` supports every language
+    #[serde(default)]
+    pub languages: Option<Vec<String>>,
+    pub category: Category,
+    pub r#type: Type,
+    #[serde(default)]
+    pub description: String,
+    /// SHA-256 of the model file, required for downloading it
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Whether the model marks speaker turns, like tinydiarize does
+    #[serde(default)]
+    pub speaker_turns: bool,
+    /// Default transcription settings, whisper.cpp's defaults when missing
+    #[serde(default)]
+    pub transcription: Option<crate::configuration::TranscriptionSettings>,
+}
+
+/// File downloaded as is from a fixed location, like the speaker embedding model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedFile {
+    pub url: String,
+    /// SHA-256 of the file, it's not downloaded without one
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// Opus-MT language pair exported to ONNX on the Hugging Face hub
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationModelInfo {
+    pub source: String,
+    pub target: String,
+    /// Repository on the hub, e.g. `Xenova/opus-mt-en-jap`, Opus-MT doesn't always name languages by their ISO code
+    pub repo: String,
+    /// Commit of `repo` the files are downloaded from, `main` moves
+    pub revision: String,
+    /// SHA-256 of every file by its path in the repository
+    pub sha256: HashMap<String, String>,
+}
+
+/// Models available for download
+///
+/// The bundled manifest is merged with `models.json` in the config directory when it exists,
+/// entries there replace the bundled ones with the same `id` and its `base_url` points downloads at a mirror.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelManifest {
+    pub base_url: String,
+    pub models: Vec<ModelInfo>,
+    pub speaker_model: PinnedFile,
+    /// Language pairs the offline translator can download
+    #[serde(default)]
+    pub translation_models: Vec<TranslationModelInfo>,
+}
+
+/// Override of the bundled manifest, every field is optional
+#[derive(Debug, Default, Deserialize)]
+struct ModelManifestOverride {
+    base_url: Option<String>,
+    #[serde(default)]
+    models: Vec<ModelInfo>,
+    speaker_model: Option<PinnedFile>,
+    #[serde(default)]
+    translation_models: Vec<TranslationModelInfo>,
+}
+
+static MANIFEST: std::sync::OnceLock<ModelManifest> = std::sync::OnceLock::new();
+
+impl ModelManifest {
+    pub fn user_path() -> PathBuf {
+        crate::project_directory().config_dir().join("models.json")
+    }
+
+    /// Loaded once, at startup
+    pub fn get() -> &'static ModelManifest {
+        MANIFEST.get_or_init(|| {
+            let mut manifest: ModelManifest = serde_json::from_str(BUNDLED_MANIFEST).expect("Bundled model manifest is invalid");
+
+            let user_path = Self::user_path();
+
+            let user_manifest = (|| {
+                let data = match std::fs::read(&user_path) {
+                    Ok(data) => data,
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(ModelManifestOverride::default()),
+                    Err(err) => return Err(err.into()),
+                };
+
+                anyhow::Ok(serde_json::from_slice::<ModelManifestOverride>(&data)?)
+            })();
+
+            match user_manifest {
+                Ok(user_manifest) => manifest.merge(user_manifest),
+                Err(err) => eprintln!("Ignoring model manifest at {user_path:?} because: {err:#}"),
+            }
+
+            manifest
+        })
+    }
+
+    fn merge(&mut self, user_manifest: ModelManifestOverride) {
+        if let Some(base_url) = user_manifest.base_url {
+            println!("Downloading models from {base_url}");
+            self.base_url = base_url;
+        }
+
+        for model in user_manifest.models {
+            match self.models.iter_mut().find(|bundled| bundled.id == model.id) {
+                Some(bundled) => *bundled = model,
+                None => self.models.push(model),
+            }
+        }
+
+        if let Some(speaker_model) = user_manifest.speaker_model {
+            self.speaker_model = speaker_model;
+        }
+
+        for model in user_manifest.translation_models {
+            match self.translation_models.iter_mut().find(|bundled| bundled.source == model.source && bundled.target == model.target) {
+                Some(bundled) => *bundled = model,
+                None => self.translation_models.push(model),
+            }
         }
     }
 
-    /// Use [`TranscriberModelType::get_model_file_name`] as the file name when saving to file system
-    pub fn download_url(&self) -> &'static str {
-        match self {
-            Model::TinyWhisper => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.bin?download=true",
-            Model::TinyEnWhisper => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.en.bin?download=true",
-            Model::TinyQuantized => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny-q5_1.bin?download=true",
-            Model::TinyEnQuantized => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.en-q5_1.bin?download=true",
-            Model::BaseWhisper => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.bin?download=true",
-            Model::BaseEnWhisper => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.en.bin?download=true",
-            Model::BaseQuantized => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base-q5_1.bin?download=true",
-            Model::BaseEnQuantized => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.en-q5_1.bin?download=true",
-            Model::SmallWhisper => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.bin?download=true",
-            Model::SmallEnWhisper => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.en.bin?download=true",
-            Model::SmallQuantized => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small-q5_1.bin?download=true",
-            Model::SmallEnQuantized => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.en-q5_1.bin?download=true",
-            Model::SmallDiarize => "https://huggingface.co/akashmjn/tinydiarize-whisper.cpp/resolve/main/ggml-small.en-tdrz.bin?download=true",
-            Model::MediumWhisper => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-medium.bin?download=true",
-            Model::MediumQuantized => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-medium-q5_0.bin?download=true",
-            Model::MediumEnQuantized => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-medium.en-q5_0.bin?download=true",
-            Model::LargeWhisper => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3.bin?download=true",
-            Model::LargeQuantized => "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3-q5_0.bin?download=true",
+    pub fn translation_model(&self, source: &str, target: &str) -> Option<&TranslationModelInfo> {
+        self.translation_models.iter().find(|model| model.source == source && model.target == target)
+    }
+}
+
+/// Whisper model, passed to the frontend as its manifest `id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Model(ModelInfo);
+
+impl From<Model> for String {
+    fn from(model: Model) -> Self {
+        model.0.id
+    }
+}
+
+impl TryFrom<String> for Model {
+    type Error = anyhow::Error;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        Model::find(&id).context(format!("Unknown model {id}"))
+    }
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Model::find(DEFAULT_MODEL)
+            .or_else(|| Model::all().into_iter().next())
+            .expect("Model manifest is empty")
+    }
+}
+
+impl Model {
+    /// Models from the manifest followed by the imported ones
+    pub fn all() -> Vec<Model> {
+        let imported = crate::configuration::ImportedModelConfig::default().models.into_iter()
+            .map(|imported| {
+                let disk_usage = std::fs::metadata(crate::project_directory().transcriber_model_dir().join(&imported.id))
+                    .map_or(0, |metadata| metadata.len() as usize / 1000000);
+
+                ModelInfo {
+                    id: imported.id.clone(),
+                    name: imported.name,
+                    url: None,
+                    file_name: imported.id,
+                    memory_usage: imported.memory_usage,
+                    disk_usage,
+                    languages: imported.languages,
+                    category: Category::Imported,
+                    r#type: Type::Custom,
+                    description: String::new(),
+                    sha256: None,
+                    speaker_turns: false,
+                    transcription: None,
+                }
+            });
+
+        ModelManifest::get().models.iter().cloned()
+            .chain(imported)
+            .map(Model)
+            .collect()
+    }
+
+    pub fn find(id: &str) -> Option<Model> {
+        Model::all().into_iter().find(|model| model.id() == id)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// Use [`Model::file_name`] as the file name when saving to file system, imported models can't be downloaded
+    pub fn download_url(&self) -> Option<String> {
+        let url = self.0.url.as_ref()?;
+
+        if url.starts_with("http://") || url.starts_with("https://") {
+            return Some(url.clone());
         }
+
+        Some(format!("{}/{}", ModelManifest::get().base_url.trim_end_matches('/'), url.trim_start_matches('/')))
     }
 
     /// Use this file name when saving to file system
-    pub fn file_name(&self) -> &'static str {
-        match self {
-            Model::TinyWhisper => "tiny.bin",
-            Model::TinyEnWhisper => "tiny-en.bin",
-            Model::TinyQuantized => "tiny-q.bin",
-            Model::TinyEnQuantized => "tiny-en-q.bin",
-            Model::BaseWhisper => "base.bin",
-            Model::BaseEnWhisper => "base-en.bin",
-            Model::BaseQuantized => "base-q.bin",
-            Model::BaseEnQuantized => "base-en-q.bin",
-            Model::SmallWhisper => "small.bin",
-            Model::SmallEnWhisper => "small-en.bin",
-            Model::SmallQuantized => "small-q.bin",
-            Model::SmallEnQuantized => "small-en-q.bin",
-            Model::SmallDiarize => "small-diar.bin",
-            Model::MediumWhisper => "medium.bin",
-            Model::MediumQuantized => "medium-q.bin",
-            Model::MediumEnQuantized => "medium-en.bin",
-            Model::LargeWhisper => "large.bin",
-            Model::LargeQuantized => "large-q.bin",
-        }
+    pub fn file_name(&self) -> &str {
+        &self.0.file_name
     }
 
     /// Average memory usage of models in MB
-    ///
-    /// Refer to this:
-    /// - https://huggingface.co/ggerganov/whisper.cpp
-    /// - https://huggingface.co/akashmjn/tinydiarize-whisper.cpp 
     pub fn average_memory_usage(&self) -> usize {
-        match self {
-            Model::TinyWhisper => 390,
-            Model::TinyEnWhisper => 390,
-            Model::TinyQuantized => 390,
-            Model::TinyEnQuantized => 390,
-            Model::BaseWhisper => 500,
-            Model::BaseEnWhisper => 500,
-            Model::BaseQuantized => 500,
-            Model::BaseEnQuantized => 500,
-            Model::SmallWhisper => 1000,
-            Model::SmallEnWhisper => 1000,
-            Model::SmallQuantized => 1000,
-            Model::SmallEnQuantized => 1000,
-            Model::SmallDiarize => 1000,
-            Model::MediumWhisper => 2600,
-            Model::MediumQuantized => 2600,
-            Model::MediumEnQuantized => 2600,
-            Model::LargeWhisper => 4700,
-            Model::LargeQuantized => 4700,
-        }
+        self.0.memory_usage
     }
 
     /// Check if runtime machine has enough memory to run the model
@@ -189,168 +314,376 @@ impl Model {
     }
 
     /// Disk usage of models in MB
-    ///
-    /// Refer to this:
-    /// - https://huggingface.co/ggerganov/whisper.cpp
-    /// - https://huggingface.co/akashmjn/tinydiarize-whisper.cpp 
     pub fn disk_usage(&self) -> usize {
-        match self {
-            Model::TinyWhisper => 77,
-            Model::TinyEnWhisper => 77,
-            Model::TinyQuantized => 33,
-            Model::TinyEnQuantized => 33,
-            Model::BaseWhisper => 148,
-            Model::BaseEnWhisper => 148,
-            Model::BaseQuantized => 60,
-            Model::BaseEnQuantized => 60,
-            Model::SmallWhisper => 488,
-            Model::SmallEnWhisper => 488,
-            Model::SmallQuantized => 190,
-            Model::SmallEnQuantized => 190,
-            Model::SmallDiarize => 488,
-            Model::MediumWhisper => 1530,
-            Model::MediumQuantized => 539,
-            Model::MediumEnQuantized => 539,
-            Model::LargeWhisper => 3100,
-            Model::LargeQuantized => 1080,
-        }
+        self.0.disk_usage
     }
 
     pub fn path(&self) -> PathBuf {
         crate::project_directory().transcriber_model_dir().join(self.file_name())
     }
 
+    /// Pinned SHA-256 of the model file, `None` for imported models
+    pub fn sha256(&self) -> Option<&str> {
+        self.0.sha256.as_deref()
+    }
+
+    /// Downloads are only moved to [`Model::path`] after they're complete and verified
     pub fn is_downloaded(&self) -> bool {
         let model_file_path = self.path();
         
         model_file_path.exists()
     }
 
-    pub fn whitelisted_lang(&self) -> Option<Vec<&'static str>> {
-        match self {
-            Model::TinyEnWhisper => Some(vec!["en"]),
-            Model::TinyEnQuantized => Some(vec!["en"]),
-            Model::BaseEnWhisper => Some(vec!["en"]),
-            Model::BaseEnQuantized => Some(vec!["en"]),
-            Model::SmallEnWhisper => Some(vec!["en"]),
-            Model::SmallEnQuantized => Some(vec!["en"]),
-            Model::SmallDiarize => Some(vec!["en"]),
-            Model::MediumEnQuantized => Some(vec!["en"]),
-            _ => None
+    /// Bytes the model file actually takes, 0 when it's not downloaded
+    pub fn size_on_disk(&self) -> u64 {
+        std::fs::metadata(self.path()).map_or(0, |metadata| metadata.len())
+    }
+
+    /// Remove the model file and what's left of an interrupted download, imported models are forgotten
+    pub fn delete(&self) -> anyhow::Result<()> {
+        for path in [self.path(), crate::downloader::partial_path(&self.path())] {
+            match std::fs::remove_file(&path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(err).context(format!("Failed removing {path:?}"));
+                }
+                _ => {}
+            }
         }
+
+        if let Category::Imported = self.category() {
+            let mut config = crate::configuration::ImportedModelConfig::default();
+            config.models.retain(|imported| imported.id != self.id());
+            crate::configuration::save(&config);
+        }
+
+        Ok(())
+    }
+
+    pub fn whitelisted_lang(&self) -> Option<&[String]> {
+        self.0.languages.as_deref()
     }
 
     pub fn category(&self) -> Category {
-        match self {
-            Model::TinyWhisper => Category::Recommended,
-            Model::BaseWhisper => Category::Recommended,
-            Model::SmallWhisper => Category::Recommended,
-            Model::MediumWhisper => Category::Recommended,
-            Model::MediumQuantized => Category::Recommended,
-            Model::LargeWhisper => Category::Recommended,
-            Model::LargeQuantized => Category::Recommended,
-            Model::TinyEnWhisper => Category::Other,
-            Model::TinyQuantized => Category::Other,
-            Model::TinyEnQuantized => Category::Other,
-            Model::BaseEnWhisper => Category::Other,
-            Model::BaseQuantized => Category::Other,
-            Model::BaseEnQuantized => Category::Other,
-            Model::SmallEnWhisper => Category::Other,
-            Model::SmallQuantized => Category::Other,
-            Model::SmallEnQuantized => Category::Other,
-            Model::SmallDiarize => Category::Other,
-            Model::MediumEnQuantized => Category::Other,
-        }
+        self.0.category
     }
 
-    pub fn description(&self) -> &'static str {
-        match self {
-            Model::TinyWhisper => "Fastest model but with the lowest quality",
-            Model::TinyEnWhisper => "",
-            Model::TinyQuantized => "",
-            Model::TinyEnQuantized => "",
-            Model::BaseWhisper => "Base whisper model",
-            Model::BaseEnWhisper => "",
-            Model::BaseQuantized => "",
-            Model::BaseEnQuantized => "",
-            Model::SmallWhisper => "Default model. Select this for most cases",
-            Model::SmallEnWhisper => "",
-            Model::SmallQuantized => "",
-            Model::SmallEnQuantized => "",
-            Model::SmallDiarize => "Small model that can recognize speaker's turn, only supports English",
-            Model::MediumWhisper => "Better quality than the default small, but might take longer transcribing",
-            Model::MediumQuantized => r#""Lite" version of medium whisper"#,
-            Model::MediumEnQuantized => "",
-            Model::LargeWhisper => "Large v3 model, best quality but with the longest time to transcript",
-            Model::LargeQuantized => r#""Lite" version of Large v3 whisper"#,
-        }
+    pub fn description(&self) -> &str {
+        &self.0.description
     }
 
     pub fn r#type(&self) -> Type {
-        match self {
-            Model::TinyWhisper => Type::Whisper,
-            Model::TinyEnWhisper => Type::Whisper,
-            Model::BaseWhisper => Type::Whisper,
-            Model::BaseEnWhisper => Type::Whisper,
-            Model::MediumWhisper => Type::Whisper,
-            Model::SmallWhisper => Type::Whisper,
-            Model::SmallEnWhisper => Type::Whisper,
-            Model::LargeWhisper => Type::Whisper,
-            Model::TinyQuantized => Type::Quantized,
-            Model::TinyEnQuantized => Type::Quantized,
-            Model::BaseQuantized => Type::Quantized,
-            Model::BaseEnQuantized => Type::Quantized,
-            Model::SmallQuantized => Type::Quantized,
-            Model::SmallEnQuantized => Type::Quantized,
-            Model::SmallDiarize => Type::Quantized,
-            Model::MediumQuantized => Type::Quantized,
-            Model::MediumEnQuantized => Type::Quantized,
-            Model::LargeQuantized => Type::Quantized,
+        self.0.r#type
+    }
+
+    pub fn supports_speaker_turns(&self) -> bool {
+        self.0.speaker_turns
+    }
+
+    /// Settings used until the user changes them
+    pub fn default_settings(&self) -> crate::configuration::TranscriptionSettings {
+        let mut settings = self.0.transcription.clone().unwrap_or_else(|| crate::configuration::TranscriptionSettings {
+            speaker_turns: self.supports_speaker_turns(),
+            ..Default::default()
+        });
+
+        settings.speaker_turns &= self.supports_speaker_turns();
+
+        settings
+    }
+}
+
+/// Whisper parameters from `settings`, features `model` doesn't support are left off
+pub(crate) fn full_params<'a>(settings: &crate::configuration::TranscriptionSettings, model: &Model, language: &'a str) -> FullParams<'a, 'a> {
+    use crate::configuration::DecodingStrategy;
+
+    let strategy = match settings.strategy {
+        DecodingStrategy::Greedy { best_of } => SamplingStrategy::Greedy { best_of: best_of.max(1) },
+        DecodingStrategy::BeamSearch { beam_size } => SamplingStrategy::BeamSearch { beam_size: beam_size.max(1), patience: -1.0 },
+    };
+
+    let threads = match settings.threads {
+        0 => std::thread::available_parallelism().map_or(4, |threads| threads.get().min(8)),
+        threads => threads,
+    };
+
+    let mut params = FullParams::new(strategy);
+
+    params.set_language(Some(language));
+    params.set_n_threads(threads as _);
+    params.set_temperature(settings.temperature);
+    params.set_temperature_inc(settings.temperature_increment);
+    params.set_no_speech_thold(settings.no_speech_threshold);
+    params.set_logprob_thold(settings.log_prob_threshold);
+    params.set_max_len(settings.max_segment_length as _);
+    params.set_split_on_word(settings.max_segment_length > 0);
+    params.set_token_timestamps(settings.max_segment_length > 0);
+    params.set_suppress_blank(settings.suppress_blank);
+    params.set_suppress_non_speech_tokens(settings.suppress_non_speech);
+    params.set_tdrz_enable(settings.speaker_turns && model.supports_speaker_turns());
+
+    params
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum StrayKind {
+    /// Leftover of an interrupted download or import
+    Partial,
+    /// File that doesn't belong to any known model
+    Orphan,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StrayFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub kind: StrayKind,
+}
+
+/// What [`ModelDirectory::transcriber_model_dir`] holds, sizes are in bytes
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelStorage {
+    pub total_size: u64,
+    /// Downloaded models with the bytes they take
+    pub models: Vec<(Model, u64)>,
+    pub stray_files: Vec<StrayFile>,
+}
+
+impl ModelStorage {
+    pub fn scan() -> anyhow::Result<Self> {
+        let model_dir = crate::project_directory().transcriber_model_dir();
+        let known_models = Model::all();
+
+        let mut storage = Self {
+            total_size: 0,
+            models: Vec::new(),
+            stray_files: Vec::new(),
+        };
+
+        for entry in std::fs::read_dir(&model_dir).context("Failed reading the model directory")? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+
+            let size = if metadata.is_dir() { directory_size(&entry.path()) } else { metadata.len() };
+            storage.total_size += size;
+
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+
+            if let Some(model) = known_models.iter().find(|model| model.file_name() == file_name && metadata.is_file()) {
+                storage.models.push((model.clone(), size));
+                continue;
+            }
+
+            let kind = if file_name.ends_with(".part") { StrayKind::Partial } else { StrayKind::Orphan };
+
+            // A running download resumes from it
+            if matches!(kind, StrayKind::Partial) && crate::downloader::is_downloading(&entry.path()) {
+                continue;
+            }
+
+            storage.stray_files.push(StrayFile { path: entry.path(), size, kind });
         }
+
+        Ok(storage)
+    }
+
+    /// Remove every stray file and forget imported models whose file is gone, returns the freed bytes
+    pub fn clean(&self) -> anyhow::Result<u64> {
+        let mut freed = 0;
+
+        for stray_file in &self.stray_files {
+            println!("Removing {:?} stray file {:?}", stray_file.kind, stray_file.path);
+
+            let result = if stray_file.path.is_dir() {
+                std::fs::remove_dir_all(&stray_file.path)
+            } else {
+                std::fs::remove_file(&stray_file.path)
+            };
+
+            result.context(format!("Failed removing {:?}", stray_file.path))?;
+
+            freed += stray_file.size;
+        }
+
+        let mut config = crate::configuration::ImportedModelConfig::default();
+        let imported_len = config.models.len();
+
+        config.models.retain(|imported| crate::project_directory().transcriber_model_dir().join(&imported.id).exists());
+
+        if config.models.len() != imported_len {
+            crate::configuration::save(&config);
+        }
+
+        Ok(freed)
     }
 }
 
-/// Decode any media for whisper
-fn decode_audio(media_data: Arc<[u8]>) -> anyhow::Result<Vec<f32>> {
-    use gst::prelude::*;
+fn directory_size(path: &Path) -> u64 {
+    std::fs::read_dir(path).into_iter().flatten().flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => directory_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Rough memory usage in MB of a model taking `disk_usage` MB, fitted on the official whisper.cpp models
+fn estimate_memory_usage(disk_usage: usize) -> usize {
+    disk_usage * 3 / 2 + 250
+}
+
+/// Magic at the start of whisper.cpp GGML model files, "ggml" read as a little endian u32
+const GGML_MAGIC: u32 = 0x67676d6c;
+
+/// Vocabulary size of the English-only whisper models, multilingual ones have more tokens
+const ENGLISH_VOCABULARY_SIZE: i32 = 51864;
+
+/// Hyperparameters from the header of a whisper.cpp GGML model
+#[derive(Debug)]
+pub struct GgmlHeader {
+    pub n_vocab: i32,
+    pub n_audio_layer: i32,
+    pub n_text_layer: i32,
+    pub n_mels: i32,
+}
+
+impl GgmlHeader {
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let mut header = [0u8; 12 * 4];
+        std::fs::File::open(path)?.read_exact(&mut header).context("File is too small to be a whisper model")?;
+
+        let fields: Vec<i32> = header.chunks_exact(4).map(|field| i32::from_le_bytes(field.try_into().unwrap())).collect();
+
+        anyhow::ensure!(fields[0] as u32 == GGML_MAGIC, "File is not a GGML model, convert it with whisper.cpp first");
+
+        // n_vocab, n_audio_ctx, n_audio_state, n_audio_head, n_audio_layer, n_text_ctx, n_text_state, n_text_head, n_text_layer, n_mels, ftype
+        let hparams = &fields[1..];
 
+        anyhow::ensure!(hparams[..9].iter().all(|&hparam| hparam > 0), "Model header is corrupted");
+        anyhow::ensure!(matches!(hparams[9], 80 | 128), "Model expects {} mel bands, whisper models use 80 or 128", hparams[9]);
+
+        Ok(Self {
+            n_vocab: hparams[0],
+            n_audio_layer: hparams[4],
+            n_text_layer: hparams[8],
+            n_mels: hparams[9],
+        })
+    }
+
+    pub fn is_multilingual(&self) -> bool {
+        self.n_vocab > ENGLISH_VOCABULARY_SIZE
+    }
+}
+
+/// Validate and copy a whisper.cpp GGML model into [`ModelDirectory::transcriber_model_dir`]
+///
+/// `languages` defaults to English for English-only models and `memory_usage` (MB) is estimated from the file size when not given.
+pub fn import_model(path: &Path, name: Option<String>, languages: Option<Vec<String>>, memory_usage: Option<usize>) -> anyhow::Result<Model> {
+    use crate::configuration::{ImportedModel, ImportedModelConfig};
+
+    let header = GgmlHeader::read(path)?;
+
+    println!("Importing model {path:?} with {header:?}");
+
+    let model_dir = crate::project_directory().transcriber_model_dir();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect::<String>();
+
+    let id = (0..)
+        .map(|n| if n == 0 { format!("imported-{stem}.bin") } else { format!("imported-{stem}-{n}.bin") })
+        .find(|id| !model_dir.join(id).exists())
+        .unwrap();
+
+    let destination = model_dir.join(&id);
+    let partial = crate::downloader::partial_path(&destination);
+    let _active = crate::downloader::ActiveDownload::start(&partial)?;
+
+    std::fs::copy(path, &partial).context("Failed copying the model")?;
+    std::fs::rename(&partial, &destination).context("Failed copying the model")?;
+
+    let disk_usage = std::fs::metadata(&destination)?.len() as usize / 1000000;
+
+    let imported = ImportedModel {
+        id: id.clone(),
+        name: name.filter(|name| !name.trim().is_empty()).unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().into_owned()),
+        languages: languages.or_else(|| (!header.is_multilingual()).then(|| vec!["en".to_owned()])),
+        memory_usage: memory_usage.unwrap_or_else(|| estimate_memory_usage(disk_usage)),
+    };
+
+    let mut config = ImportedModelConfig::default();
+    config.models.push(imported);
+    crate::configuration::save(&config);
+
+    Model::find(&id).context("Imported model is missing")
+}
+
+/// Push `media_data` into `source`, a seekable `appsrc` with `stream-type=2` and its `size` set
+pub(crate) fn feed_media(source: &gst_app::AppSrc, media_data: Arc<[u8]>) {
     let media_data_len = media_data.len();
     let media_data = Arc::new(Mutex::new(Cursor::new(media_data)));
 
-    let pipeline = 
-        gst::parse::launch(&format!("appsrc name=audio-in stream-type=2 size={media_data_len} ! decodebin ! audioconvert ! audio/x-raw,format=F32LE,channels=1 ! audioresample ! audio/x-raw,rate=16000 ! appsink name=pcm-out sync=false"))?
-            .dynamic_cast::<gst::Pipeline>().unwrap();
+    source.set_callbacks(gst_app::AppSrcCallbacks::builder()
+        .need_data({
+            let media_data = media_data.clone();
 
-    pipeline.by_name("audio-in").unwrap().dynamic_cast::<gst_app::AppSrc>().unwrap()
-        .set_callbacks(gst_app::AppSrcCallbacks::builder()
-            .need_data({
-                let media_data = media_data.clone();
+            move |source, length| {
+                let mut buffer = vec![0u8; length as _];
+                let position = media_data.lock().unwrap().position();
+                let _ = media_data.lock().unwrap().read(&mut buffer).unwrap();
 
-                move |source, length| {
-                    let mut buffer = vec![0u8; length as _];
-                    let position = media_data.lock().unwrap().position();
-                    let _ = media_data.lock().unwrap().read(&mut buffer).unwrap();
+                if position as usize >= media_data_len {
+                    println!("Ending stream");
+                    source.end_of_stream().unwrap();
+                    return;
+                }
 
-                    if position as usize >= media_data_len {
-                        println!("Ending stream");
-                        source.end_of_stream().unwrap();
-                        return;
-                    }
+                let buffer = gst::Buffer::from_slice(buffer);
+                let _ = source.push_buffer(buffer);
+            }
+        })
+        .seek_data({
+            let media_data = media_data.clone();
 
-                    let buffer = gst::Buffer::from_slice(buffer);
-                    let _ = source.push_buffer(buffer);
-                }
-            })
-            .seek_data({
-                let media_data = media_data.clone();
+            move |_, location| {
+                media_data.lock().unwrap().set_position(location);
 
-                move |_, location| {
-                    media_data.lock().unwrap().set_position(location);
+                true
+            }
+        })
+        .build());
+}
 
-                    true
-                }
-            })
-            .build());
+/// Link the `audio_stream`th audio pad `decodebin` adds to `sink`, the first one when `None`.
+/// Demuxers add their pads in container order, like the discoverer lists the streams
+pub(crate) fn link_audio_stream(decodebin: &gst::Element, sink: &gst::Element, audio_stream: Option<usize>) {
+    use gst::prelude::*;
+
+    let audio_pads = std::sync::atomic::AtomicUsize::new(0);
+    let sink_pad = sink.static_pad("sink").unwrap();
+
+    decodebin.connect_pad_added(move |_, pad| {
+        let is_audio = pad.current_caps().is_some_and(|caps| caps.structure(0).is_some_and(|structure| structure.name().starts_with("audio/")));
+
+        if is_audio && audio_pads.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == audio_stream.unwrap_or(0) {
+            if let Err(err) = pad.link(&sink_pad) {
+                println!("Failed linking audio stream {audio_stream:?}: {err:?}");
+            }
+        }
+    });
+}
+
+/// Decode any media for whisper, from its `audio_stream`th audio stream
+pub(crate) fn decode_audio(media_data: Arc<[u8]>, audio_stream: Option<usize>) -> anyhow::Result<Vec<f32>> {
+    use gst::prelude::*;
+
+    let pipeline = 
+        gst::parse::launch(&format!("appsrc name=audio-in stream-type=2 size={} ! decodebin name=decode audioconvert name=convert ! audio/x-raw,format=F32LE,channels=1 ! audioresample ! audio/x-raw,rate=16000 ! appsink name=pcm-out sync=false", media_data.len()))?
+            .dynamic_cast::<gst::Pipeline>().unwrap();
+
+    feed_media(&pipeline.by_name("audio-in").unwrap().dynamic_cast::<gst_app::AppSrc>().unwrap(), media_data);
+    link_audio_stream(&pipeline.by_name("decode").unwrap(), &pipeline.by_name("convert").unwrap(), audio_stream);
 
     let pcm_data = Arc::new(Mutex::new(Vec::new()));
 
@@ -379,6 +712,98 @@ fn decode_audio(media_data: Arc<[u8]>) -> anyhow::Result<Vec<f32>> {
     Ok(Mutex::into_inner(Arc::try_unwrap(pcm_data).unwrap()).unwrap())
 }
 
+/// Email `transcription` to the addresses of `general_config`, failures are notified
+pub fn send_transcript_email(window: &Window, general_config: &crate::configuration::GeneralConfig, smtp_config: &crate::configuration::SMTPConfig, transcription: String) {
+    let _ = (|| {
+        use lettre::Transport as _;
+        use lettre::message::{ header, Attachment, SinglePart, MultiPart };
+
+        let now = chrono::Local::now();
+        let readable_now = now.format("%A %B %d %Y");
+
+        let message = format!(r#"
+Hi,
+
+Here's attached the transcript for the meeting at {readable_now}.
+
+Have a good day!
+        "#).trim().to_owned();
+
+        let attachment = Attachment::new(now.format("transcription_%Y-%m-%d.srt").to_string())
+            .body(transcription, header::ContentType::TEXT_PLAIN);
+
+        let mut emails = general_config.transcription_email_to.split(',');
+
+        let email = {
+            let mut email = lettre::Message::builder()
+                .from(smtp_config.from.parse()?)
+                .to(emails.next().context("No emails were provided")?.trim().parse().context("Provided emails were invalid")?);
+
+            for mail in emails {
+                let mail = mail.trim();
+                email = email.cc(mail.parse().context(format!("Email '{mail}' is invalid"))?);
+            }
+
+            anyhow::Ok(email)
+        };
+
+        let email = match email {
+            Ok(email) => email,
+            Err(err) => {
+                crate::util::emit_all(window, "app://notification", serde_json::json!({
+                    "type": "error",
+                    "value": err.to_string()
+                }));
+
+                anyhow::bail!("");
+            },
+        };
+
+        let email = email.subject(format!("Meeting Transcript at {readable_now}"))
+            .multipart(
+                MultiPart::alternative()
+                    .singlepart(
+                        SinglePart::builder()
+                        .header(header::ContentType::TEXT_PLAIN)
+                        .body(message)
+                    )
+                    .singlepart(attachment)
+            )?;
+
+        let Ok(mailer) = smtp_config.auto_smtp_transport() else {
+            crate::util::emit_all(window, "app://notification", serde_json::json!({
+                "type": "error",
+                "value": "Unable to connect to the SMTP server using TLS, STARTTLS, or plaintext!"
+            }));
+            
+            anyhow::bail!("");
+        };
+
+        match mailer.send(&email) {
+            Ok(_) => {
+                println!("Sending email");
+            }
+            Err(e) => {
+                eprintln!("Failed to send email: {e}");
+                crate::util::emit_all(window, "app://notification", serde_json::json!({
+                    "type": "error",
+                    "value": format!("Failed to send transcription via email because:\n{e}")
+                }));
+            }
+        }
+
+        crate::util::emit_all(window, "app://notification", serde_json::json!({
+            "type": "info",
+            "value": "Transcription email is sent!"
+        }));
+
+        anyhow::Ok(())
+    })();
+}
+
+/// Words of the script given to whisper as its prompt when aligning, whisper only keeps the last 224 tokens
+const ALIGNMENT_PROMPT_WORDS: usize = 100;
+
 pub struct Transcriber {
     model: Model,
     language: String,
@@ -394,6 +819,10 @@ impl Transcriber {
         }
     }
 
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
     pub fn change_model(&mut self, model: Model) {
         self.model = model;
 
@@ -401,16 +830,32 @@ impl Transcriber {
         let _ = self.ctx.lock().unwrap().take();
     }
 
+    /// Drop the loaded context if it belongs to `model`, e.g. after its file was deleted
+    pub fn unload_model(&self, model: &Model) {
+        if self.model.id() == model.id() {
+            let _ = self.ctx.lock().unwrap().take();
+        }
+    }
+
     pub fn change_language(&mut self, language: String) {
         self.language = language;
     }
 
     /// `media_data` accept media in any format
-    pub fn transcribe(&self, window: &Window, media_data: Vec<u8>, general_config: super::configuration::GeneralConfig, smtp_config: super::configuration::SMTPConfig, save_to: PathBuf, email: bool) {
+    /// `source_levels` are the levels measured while recording `media_data`, used to tell our lines from the others'
+    /// `selection` is the part of `media_data` to transcribe, timestamps still count from the start of the file
+    /// Returns the thread transcribing, `None` when it couldn't start
+    #[allow(clippy::too_many_arguments)]
+    pub fn transcribe(&self, window: &Window, media_data: Vec<u8>, selection: crate::media::MediaSelection, source_levels: Option<crate::speaker::SourceLevels>, general_config: super::configuration::GeneralConfig, smtp_config: super::configuration::SMTPConfig, save_to: PathBuf, email: bool) -> Option<std::thread::JoinHandle<bool>> {
         println!("Using model {:?}", self.model);
 
         if !self.model.is_downloaded() {
-            panic!("Selected model is not downloaded");
+            crate::util::emit_all(window, "app://notification", serde_json::json!({
+                "type": "error",
+                "value": "Selected model is not downloaded, download it before transcribing"
+            }));
+
+            return None;
         }
 
         println!("Transcribing audio");
@@ -429,66 +874,261 @@ impl Transcriber {
         // drop(ctx);
 
         let language = self.language.clone();
+        let model = self.model.clone();
+        let settings = crate::configuration::TranscriptionConfig::default().settings_for(&model);
+        let vad_config = crate::configuration::VadConfig::default();
+
+        let vocabulary = crate::configuration::VocabularyConfig::default().active().cloned();
+        let speaker_labels = crate::configuration::SpeakerLabelConfig::default();
+        let diarization_config = crate::configuration::DiarizationConfig::default();
+        let translation_config = crate::configuration::TranslationConfig::default();
+        let bilingual_config = crate::configuration::BilingualConfig::default();
+        let cue_rules = crate::configuration::CueRulesConfig::default();
+        let review_config = crate::configuration::ReviewConfig::default();
+        let redaction_config = crate::configuration::RedactionConfig::default();
+        let diarize = diarization_config.enabled && crate::diarization::is_downloaded();
+
+        if diarization_config.enabled && !diarize {
+            crate::util::emit_all(window, "app://notification", serde_json::json!({
+                "type": "error",
+                "value": "Speaker model is not downloaded, transcribing without telling speakers apart"
+            }));
+        }
+
+        println!("Transcribing with {settings:?} and {vad_config:?}");
 
         let w = window.clone();
-        let whisper_context = WhisperContext::new_with_params(self.model.path().to_str().unwrap(), WhisperContextParameters::default()).unwrap();
-        std::thread::spawn(move || {
-            let mut state = whisper_context.create_state().unwrap();
-
-            let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 5 });
-
-            params.set_language(Some(&language));
-            params.set_translate(general_config.translate);
-            params.set_tdrz_enable(true);
-
-            // FIXME: TRANSCRIBE-PROGRESS find out why this doesn't work.
-            // params.set_progress_callback_safe({
-            //     let w = w.clone();
-            //
-            //     move |progress: i32| {
-            //         println!("Transcribing {}%", progress);
-            //         util::emit_all(&w, "update-state", serde_json::json!({
-            //             "type": "transcribe-progress",
-            //             "value": progress,
-            //         }));
-            //     }
-            // });
-            //
-            
+        Some(std::thread::spawn(move || {
             let transcription = (|| {
-                state.full(params, &decode_audio(media_data.into())?)?;
+                let whisper_context = WhisperContext::new_with_params(&model.path().to_string_lossy(), WhisperContextParameters::default())
+                    .context("Failed loading the model, try downloading it again")?;
+                let mut state = whisper_context.create_state()?;
+
+                let mut params = full_params(&settings, &model, &language);
+
+                params.set_translate(general_config.translate);
+                // Words are timed by their tokens
+                params.set_token_timestamps(true);
+
+                let initial_prompt = vocabulary.as_ref().and_then(crate::vocabulary::initial_prompt);
+                let replacer = crate::vocabulary::Replacer::new(vocabulary.as_ref().map_or(&[][..], |vocabulary| vocabulary.replacements.as_slice()))?;
+                let redactor = redaction_config.enabled.then(|| crate::redaction::Redactor::new(&redaction_config)).transpose()?;
+
+                if let Some(initial_prompt) = &initial_prompt {
+                    params.set_initial_prompt(initial_prompt);
+                }
+
+                // FIXME: TRANSCRIBE-PROGRESS find out why this doesn't work.
+                // params.set_progress_callback_safe({
+                //     let w = w.clone();
+                //
+                //     move |progress: i32| {
+                //         println!("Transcribing {}%", progress);
+                //         util::emit_all(&w, "update-state", serde_json::json!({
+                //             "type": "transcribe-progress",
+                //             "value": progress,
+                //         }));
+                //     }
+                // });
+                //
+
+                let media_data: Arc<[u8]> = media_data.into();
+                let pcm = decode_audio(media_data.clone(), selection.audio_stream)?;
+                let pcm = selection.slice(&pcm, crate::vad::SAMPLE_RATE).to_vec();
+                // Whisper counts from the start of the range
+                let offset = selection.start.map_or(0, |start| start.since(crate::timestamp::Timestamp::default()));
+
+                let speech = (vad_config.voice_activity_detection || vad_config.drop_non_speech_segments || diarize)
+                    .then(|| crate::vad::detect_speech(&pcm))
+                    // Speech too quiet to detect is still worth transcribing
+                    .filter(|speech| {
+                        if speech.is_empty() {
+                            println!("No speech detected, transcribing the whole recording");
+                        }
+
+                        !speech.is_empty()
+                    });
+
+                let diarization = match &speech {
+                    Some(speech) if diarize => Some(crate::diarization::diarize(&pcm, speech, &diarization_config)?),
+                    _ => None,
+                };
 
-                let mut fragments = Vec::new();
+                let (pcm, timeline) = match &speech {
+                    Some(speech) if vad_config.voice_activity_detection => {
+                        let (speech_pcm, timeline) = crate::vad::join_speech(&pcm, speech);
 
-                for s in 0..state.full_n_segments()? {
+                        println!("Transcribing {:.1}s of speech out of {:.1}s", speech_pcm.len() as f32 / crate::vad::SAMPLE_RATE as f32, pcm.len() as f32 / crate::vad::SAMPLE_RATE as f32);
+
+                        (speech_pcm, timeline)
+                    }
+                    _ => {
+                        let timeline = crate::vad::Timeline::identity(pcm.len());
+                        (pcm, timeline)
+                    }
+                };
+
+                // Whisper fails on empty input, an empty range has an empty transcription
+                if !pcm.is_empty() {
+                    state.full(params, &pcm)?;
+                }
+
+                let segments_count = if pcm.is_empty() { 0 } else { state.full_n_segments()? };
+
+                let mut segments = Vec::new();
+
+                for s in 0..segments_count {
                     let speaker_turn = state.full_get_segment_speaker_turn_next(s);
 
-                    let mut text = state.full_get_segment_text(s)?;
-                    let start = state.full_get_segment_t0(s)?;
-                    let stop = state.full_get_segment_t1(s)?;
+                    let mut text = replacer.apply(&state.full_get_segment_text(s)?);
+                    let start = timeline.map_start_centiseconds(state.full_get_segment_t0(s)?);
+                    let stop = timeline.map_end_centiseconds(state.full_get_segment_t1(s)?);
+
+                    if vad_config.drop_non_speech_segments && speech.as_ref().is_some_and(|speech| !crate::vad::overlaps_speech(speech, start, stop)) {
+                        println!("Dropping segment without speech: {text:?}");
+                        continue;
+                    }
 
                     if speaker_turn {
                         text.push_str(" [SPEAKER TURN]");
                     }
 
-                    let fragment = format!(
-                        "\n{s}\n{} --> {}\n{}\n",
-                        crate::util::format_timestamp(start, true, ","),
-                        crate::util::format_timestamp(stop, true, ","),
-                        text.trim().replace("-->", "->")
-                    );
+                    let (start_at, end_at) = (crate::timestamp::Timestamp::from_centiseconds(start).offset(offset), crate::timestamp::Timestamp::from_centiseconds(stop).offset(offset));
+
+                    // Clustered speakers are more specific than which side of the call is talking
+                    let speaker = match &diarization {
+                        Some(diarization) => diarization.speaker_at(start, stop),
+                        None => source_levels.as_ref()
+                            .and_then(|levels| levels.speaker_at(start_at.as_secs_f32(), end_at.as_secs_f32()))
+                            .map(|speaker| speaker_labels.label(speaker).to_owned()),
+                    };
+
+                    let end_of_text = whisper_context.token_eot();
+                    let mut tokens = Vec::new();
+
+                    for token in 0..state.full_n_tokens(s)? {
+                        // Timestamps and other special tokens come after the end of text token
+                        if state.full_get_token_id(s, token)? >= end_of_text { continue };
+
+                        // Characters split over several tokens aren't valid text on their own
+                        let Ok(text) = state.full_get_token_text(s, token) else { continue };
+                        let data = state.full_get_token_data(s, token)?;
+
+                        tokens.push(crate::transcript::Token {
+                            text,
+                            start: crate::timestamp::Timestamp::from_centiseconds(timeline.map_start_centiseconds(data.t0)).offset(offset),
+                            end: crate::timestamp::Timestamp::from_centiseconds(timeline.map_end_centiseconds(data.t1)).offset(offset),
+                            probability: data.p,
+                        });
+                    }
 
-                    fragments.push(fragment);
+                    let words = crate::transcript::words_from_tokens(tokens).into_iter()
+                        .map(|word| crate::transcript::Word { text: replacer.apply(&word.text), ..word })
+                        .collect();
+
+                    segments.push(crate::transcript::Segment { start: start_at, end: end_at, text, speaker, words });
                 }
 
-                let transcription = fragments.join("\n");
+                // Nothing saved, translated or emailed below holds the redacted data
+                if let Some(redactor) = &redactor {
+                    let redactions = redactor.redact_segments(&mut segments);
+                    let mut redacted_audio = None;
+
+                    if !redactions.is_empty() && redaction_config.audio != crate::redaction::AudioRedaction::Keep {
+                        let path = crate::redaction::redacted_audio_path(&save_to);
+
+                        match crate::redaction::redact_audio(media_data.clone(), selection.audio_stream, &redactions, redaction_config.audio, &path) {
+                            Ok(()) => redacted_audio = Some((redaction_config.audio, path)),
+                            Err(err) => crate::util::emit_all(&w, "app://notification", serde_json::json!({
+                                "type": "error",
+                                "value": format!("Failed redacting the recording because: {err:#}")
+                            })),
+                        }
+                    }
+
+                    println!("Redacted {} items from the transcription", redactions.len());
+
+                    let audit_log = crate::redaction::audit_log(&save_to, &redactions, redacted_audio.as_ref().map(|(audio, path)| (*audio, path.as_path())));
+                    std::fs::write(crate::redaction::audit_path(&save_to), audit_log).context("Failed writing the redaction log")?;
+                }
+
+                let shape = |segments: &[crate::transcript::Segment]| if cue_rules.enabled {
+                    crate::shaping::shape_cues(segments, &cue_rules)
+                } else {
+                    segments.to_vec()
+                };
+
+                let cues = shape(&segments);
+                let transcription = crate::subtitle::to_srt(&cues);
 
                 std::fs::write(&save_to, &transcription).context("Failed writing transcription file")?;
 
-                anyhow::Ok(transcription)
+                let mut needs_review = false;
+
+                if review_config.enabled {
+                    let review_path = crate::review::review_path(&save_to);
+
+                    std::fs::write(&review_path, crate::review::review_export(&cues, review_config.threshold)).context("Failed writing the review file")?;
+
+                    let doubtful = crate::review::doubtful_cues(&cues, review_config.threshold);
+
+                    if !doubtful.is_empty() {
+                        needs_review = true;
+
+                        println!("{} cues need review, see {}", doubtful.len(), review_path.display());
+
+                        crate::util::emit_all(&w, "app://transcript_review", crate::review::Review {
+                            path: save_to.clone(),
+                            threshold: review_config.threshold,
+                            email,
+                            cues: doubtful,
+                        });
+                    }
+                }
+
+                if !segments.is_empty() && !translation_config.target_languages.is_empty() {
+                    let source = if general_config.translate {
+                        "en".to_owned()
+                    } else if language != "auto" {
+                        language.clone()
+                    } else {
+                        whisper_rs::get_lang_str(state.full_lang_id_from_state()?).unwrap_or("en").to_owned()
+                    };
+
+                    let translator = translation_config.backend.translator();
+
+                    for target in translation_config.target_languages.iter().filter(|target| **target != source) {
+                        let path = crate::translation::translated_path(&save_to, target);
+
+                        println!("Translating transcription from {source} to {target} into {}", path.display());
+
+                        let result = crate::translation::translate_segments(translator.as_ref(), &segments, &source, target).and_then(|translated| {
+                            std::fs::write(&path, crate::subtitle::to_srt(&shape(&translated))).context("Failed writing translation file")?;
+
+                            if bilingual_config.enabled {
+                                let cues = crate::subtitle::bilingual_cues(&segments, &translated, &bilingual_config);
+                                let bilingual_path = crate::subtitle::bilingual_path(&save_to, &source, target, bilingual_config.format);
+
+                                std::fs::write(bilingual_path, crate::subtitle::bilingual_to_string(&cues, bilingual_config.format))
+                                    .context("Failed writing bilingual subtitles")?;
+                            }
+
+                            anyhow::Ok(())
+                        });
+
+                        if let Err(err) = result {
+                            crate::util::emit_all(&w, "app://notification", serde_json::json!({
+                                "type": "error",
+                                "value": format!("Failed translating into {target} because: {err:#}")
+                            }));
+                        }
+                    }
+                }
+
+                anyhow::Ok((transcription, needs_review))
             })();
 
-            let transcription = match transcription {
+            let (transcription, needs_review) = match transcription {
                 Ok(transcription) => transcription,
                 Err(err) => {
                     crate::util::emit_all(&w, "app://notification", serde_json::json!({
@@ -501,98 +1141,111 @@ impl Transcriber {
                         "value": ""
                     }));
 
-                    return;
+                    return false;
                 },
             };
 
-            let _ = (|| {
-                if !email { return Ok(()) };
+            // Proofread transcripts are emailed from the review
+            if email && !needs_review {
+                send_transcript_email(&w, &general_config, &smtp_config, transcription);
+            }
 
-                use lettre::Transport as _;
-                use lettre::message::{ header, Attachment, SinglePart, MultiPart };
+            crate::util::emit_all(&w, transcription_uuid, serde_json::json!({
+                "type": "finish",
+                "value": save_to
+            }));
 
-                let now = chrono::Local::now();
-                let readable_now = now.format("%A %B %d %Y");
+            true
+        }))
+    }
 
-                let message = format!(r#"
-Hi,
+    /// Time the sentences of `script`, the exact narration of `media_data`, by the words whisper hears in it
+    /// and save them as subtitles to `save_to`
+    pub fn align(&self, window: &Window, media_data: Vec<u8>, script: String, save_to: PathBuf) {
+        println!("Aligning with model {:?}", self.model);
 
-Here's attached the transcript for the meeting at {readable_now}.
+        if !self.model.is_downloaded() {
+            crate::util::emit_all(window, "app://notification", serde_json::json!({
+                "type": "error",
+                "value": "Selected model is not downloaded, download it before aligning"
+            }));
 
-Have a good day!
-                "#).trim().to_owned();
+            return;
+        }
 
-                let attachment = Attachment::new(now.format("transcription_%Y-%m-%d.srt").to_string())
-                    .body(transcription, header::ContentType::TEXT_PLAIN);
+        let transcription_uuid = gst::glib::uuid_string_random().to_string();
 
-                let mut emails = general_config.transcription_email_to.split(',');
+        crate::util::emit_all(window, "app://transcriber_start", transcription_uuid.clone());
 
-                let email = {
-                    let mut email = lettre::Message::builder()
-                        .from(smtp_config.from.parse()?)
-                        .to(emails.next().context("No emails were provided")?.trim().parse().context("Provided emails were invalid")?);
+        let language = self.language.clone();
+        let model = self.model.clone();
+        let settings = crate::configuration::TranscriptionConfig::default().settings_for(&model);
+        let cue_rules = crate::configuration::CueRulesConfig::default();
 
-                    for mail in emails {
-                        let mail = mail.trim();
-                        email = email.cc(mail.parse().context(format!("Email '{mail}' is invalid"))?);
-                    }
+        let w = window.clone();
+        std::thread::spawn(move || {
+            let result = (|| {
+                let whisper_context = WhisperContext::new_with_params(&model.path().to_string_lossy(), WhisperContextParameters::default())
+                    .context("Failed loading the model, try downloading it again")?;
+                let mut state = whisper_context.create_state()?;
 
-                    anyhow::Ok(email)
-                };
+                let mut params = full_params(&settings, &model, &language);
 
-                let email = match email {
-                    Ok(email) => email,
-                    Err(err) => {
-                        crate::util::emit_all(&w, "app://notification", serde_json::json!({
-                            "type": "error",
-                            "value": err.to_string()
-                        }));
+                params.set_token_timestamps(true);
 
-                        anyhow::bail!("");
-                    },
-                };
+                // The start of the script steers whisper to its wording
+                let prompt = script.split_whitespace().take(ALIGNMENT_PROMPT_WORDS).collect::<Vec<_>>().join(" ");
+                params.set_initial_prompt(&prompt);
 
-                let email = email.subject(format!("Meeting Transcript at {readable_now}"))
-                    .multipart(
-                        MultiPart::alternative()
-                            .singlepart(
-                                SinglePart::builder()
-                                .header(header::ContentType::TEXT_PLAIN)
-                                .body(message)
-                            )
-                            .singlepart(attachment)
-                    )?;
-
-                let Ok(mailer) = smtp_config.auto_smtp_transport() else {
-                    crate::util::emit_all(&w, "app://notification", serde_json::json!({
-                        "type": "error",
-                        "value": "Unable to connect to the SMTP server using TLS, STARTTLS, or plaintext!"
-                    }));
-                    
-                    anyhow::bail!("");
-                };
+                let pcm = decode_audio(media_data.into(), None)?;
 
-                match mailer.send(&email) {
-                    Ok(_) => {
-                        println!("Sending email");
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to send email: {e}");
-                        crate::util::emit_all(&w, "app://notification", serde_json::json!({
-                            "type": "error",
-                            "value": format!("Failed to send transcription via email because:\n{e}")
-                        }));
+                anyhow::ensure!(!pcm.is_empty(), "The recording has no audio to align the script to");
+
+                state.full(params, &pcm)?;
+
+                let end_of_text = whisper_context.token_eot();
+                let mut tokens = Vec::new();
+
+                for segment in 0..state.full_n_segments()? {
+                    for token in 0..state.full_n_tokens(segment)? {
+                        // Timestamps and other special tokens come after the end of text token
+                        if state.full_get_token_id(segment, token)? >= end_of_text { continue };
+
+                        // Characters split over several tokens aren't valid text on their own
+                        let Ok(text) = state.full_get_token_text(segment, token) else { continue };
+                        let data = state.full_get_token_data(segment, token)?;
+
+                        tokens.push(crate::transcript::Token {
+                            text,
+                            start: crate::timestamp::Timestamp::from_centiseconds(data.t0),
+                            end: crate::timestamp::Timestamp::from_centiseconds(data.t1),
+                            probability: data.p,
+                        });
                     }
                 }
 
-                crate::util::emit_all(&w, "app://notification", serde_json::json!({
-                    "type": "info",
-                    "value": "Transcription email is sent!"
-                }));
+                let segments = crate::alignment::align(&script, &crate::transcript::words_from_tokens(tokens))?;
+                let segments = if cue_rules.enabled { crate::shaping::shape_cues(&segments, &cue_rules) } else { segments };
+
+                std::fs::write(&save_to, crate::subtitle::to_srt(&segments)).context("Failed writing the aligned subtitles")?;
 
                 anyhow::Ok(())
             })();
-            
+
+            if let Err(err) = result {
+                crate::util::emit_all(&w, "app://notification", serde_json::json!({
+                    "type": "error",
+                    "value": format!("Failed aligning the script because: {err:#}")
+                }));
+
+                crate::util::emit_all(&w, transcription_uuid, serde_json::json!({
+                    "type": "finish_failed",
+                    "value": ""
+                }));
+
+                return;
+            }
+
             crate::util::emit_all(&w, transcription_uuid, serde_json::json!({
                 "type": "finish",
                 "value": save_to
@@ -600,3 +1253,56 @@ Have a good day!
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_manifest_is_valid() {
+        let manifest: ModelManifest = serde_json::from_str(BUNDLED_MANIFEST).unwrap();
+
+        assert!(manifest.models.iter().any(|model| model.id == DEFAULT_MODEL));
+
+        for (i, model) in manifest.models.iter().enumerate() {
+            assert!(model.url.is_some(), "{} has no download URL", model.id);
+            assert!(model.sha256.as_deref().is_some_and(crate::downloader::is_sha256), "{} has no SHA-256", model.id);
+            assert!(manifest.models[..i].iter().all(|other| other.id != model.id && other.file_name != model.file_name), "{} is duplicated", model.id);
+        }
+
+        assert!(manifest.speaker_model.sha256.as_deref().is_some_and(crate::downloader::is_sha256), "The speaker model has no SHA-256");
+
+        assert!(!manifest.translation_models.is_empty(), "No offline translation model is pinned");
+
+        for model in &manifest.translation_models {
+            assert!(manifest.translation_models.iter().filter(|other| other.source == model.source && other.target == model.target).count() == 1, "{} to {} is duplicated", model.source, model.target);
+
+            for file in crate::translation::TRANSLATION_MODEL_FILES {
+                assert!(model.sha256.get(file).is_some_and(|sha256| crate::downloader::is_sha256(sha256)), "{file} of {} to {} has no SHA-256", model.source, model.target);
+            }
+        }
+    }
+
+    #[test]
+    fn user_manifest_overrides_bundled_models() {
+        let mut manifest: ModelManifest = serde_json::from_str(BUNDLED_MANIFEST).unwrap();
+        let bundled_len = manifest.models.len();
+
+        let user_manifest: ModelManifestOverride = serde_json::from_str(r#"{
+            "base_url": "https://mirror.internal/hf",
+            "models": [
+                { "id": "TinyWhisper", "name": "Tiny (mirrored)", "url": "ggml-tiny.bin", "file_name": "tiny.bin", "memory_usage": 390, "disk_usage": 77, "category": "Recommended", "type": "Whisper", "sha256": "00" },
+                { "id": "Domain", "name": "Domain", "url": "https://models.internal/domain.bin", "file_name": "domain.bin", "memory_usage": 1000, "disk_usage": 488, "category": "Other", "type": "Custom" }
+            ]
+        }"#).unwrap();
+
+        manifest.merge(user_manifest);
+
+        assert_eq!(manifest.base_url, "https://mirror.internal/hf");
+        assert_eq!(manifest.models.len(), bundled_len + 1);
+
+        let tiny = manifest.models.iter().find(|model| model.id == "TinyWhisper").unwrap();
+        assert_eq!(tiny.name, "Tiny (mirrored)");
+        assert_eq!(tiny.sha256.as_deref(), Some("00"));
+    }
+}
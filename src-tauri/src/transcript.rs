@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use crate::timestamp::Timestamp;
+
+/// Piece of whisper's output with the timing and probability from its token data
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub text: String,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub probability: f32,
+}
+
+/// Word as whisper heard it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    /// Mean probability of its tokens, from 0 to 1
+    pub confidence: f32,
+}
+
+/// Join whisper tokens into words, a token starting with a space starts a new word,
+/// words of only punctuation are left out
+pub fn words_from_tokens(tokens: impl IntoIterator<Item = Token>) -> Vec<Word> {
+    let mut words: Vec<(Word, Vec<f32>)> = Vec::new();
+
+    for token in tokens {
+        match words.last_mut() {
+            Some((word, probabilities)) if !token.text.starts_with(char::is_whitespace) => {
+                word.text.push_str(&token.text);
+                word.end = word.end.max(token.end);
+                probabilities.push(token.probability);
+            }
+            _ => words.push((Word { text: token.text.trim_start().to_owned(), start: token.start, end: token.end, confidence: 0.0 }, vec![token.probability])),
+        }
+    }
+
+    words.into_iter()
+        .filter(|(word, _)| word.text.chars().any(char::is_alphanumeric))
+        .map(|(word, probabilities)| Word { confidence: probabilities.iter().sum::<f32>() / probabilities.len() as f32, ..word })
+        .collect()
+}
+
+/// Transcribed line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Segment {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub text: String,
+    /// Label of whoever is talking, shown in front of the text
+    pub speaker: Option<String>,
+    /// Words with their confidence when the line was transcribed, not when it was read from a file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<Word>,
+}
+
+impl Segment {
+    /// Text as it's written into subtitles, prefixed with the speaker
+    pub fn labelled_text(&self) -> String {
+        let text = self.text.trim();
+
+        match &self.speaker {
+            Some(speaker) => format!("{speaker}: {text}"),
+            None => text.to_owned(),
+        }
+    }
+}
+
+/// Transcripts for the tests of everything working on them
+#[cfg(test)]
+pub mod fixtures {
+    use super::*;
+
+    /// Segment without word data
+    pub fn segment(start: i64, end: i64, text: &str, speaker: Option<&str>) -> Segment {
+        Segment { start: Timestamp::from_millis(start), end: Timestamp::from_millis(end), text: text.to_owned(), speaker: speaker.map(str::to_owned), words: Vec::new() }
+    }
+
+    /// Segment of words half a second each, with their confidence
+    pub fn spoken(start: i64, words: &[(&str, f32)], speaker: Option<&str>) -> Segment {
+        let words: Vec<Word> = words.iter()
+            .enumerate()
+            .map(|(index, (text, confidence))| {
+                let start = Timestamp::from_millis(start + index as i64 * 500);
+
+                Word { text: (*text).to_owned(), start, end: start.offset(500), confidence: *confidence }
+            })
+            .collect();
+
+        Segment {
+            start: words[0].start,
+            end: words[words.len() - 1].end,
+            text: words.iter().map(|word| word.text.as_str()).collect::<Vec<_>>().join(" "),
+            speaker: speaker.map(str::to_owned),
+            words,
+        }
+    }
+
+    /// Words heard from start to end milliseconds
+    pub fn heard(words: &[(&str, i64, i64)]) -> Vec<Word> {
+        words.iter()
+            .map(|(text, start, end)| Word { text: (*text).to_owned(), start: Timestamp::from_millis(*start), end: Timestamp::from_millis(*end), confidence: 1.0 })
+            .collect()
+    }
+
+    /// Start and end of every segment in milliseconds
+    pub fn timings(segments: &[Segment]) -> Vec<(i64, i64)> {
+        let zero = Timestamp::default();
+
+        segments.iter().map(|segment| (segment.start.since(zero), segment.end.since(zero))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_tokens_into_words() {
+        let tokens = [(" Record", 0, 100, 0.9), ("script", 100, 300, 0.5), (" is", 300, 400, 1.0), (".", 400, 410, 0.8), (" ", 410, 420, 0.1)]
+            .map(|(text, start, end, probability)| Token { text: text.to_owned(), start: Timestamp::from_millis(start), end: Timestamp::from_millis(end), probability });
+
+        let words = words_from_tokens(tokens);
+
+        assert_eq!(words.iter().map(|word| (word.text.as_str(), word.start, word.end)).collect::<Vec<_>>(), [
+            ("Recordscript", Timestamp::from_millis(0), Timestamp::from_millis(300)),
+            ("is.", Timestamp::from_millis(300), Timestamp::from_millis(410)),
+        ]);
+        assert!((words[0].confidence - 0.7).abs() < 1e-6);
+        assert!((words[1].confidence - 0.9).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,283 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+
+use serde::{Deserialize, Serialize};
+
+use crate::transcript::Segment;
+
+/// Translates transcribed lines from one language into another, languages are ISO 639-1 codes like whisper's
+pub trait Translator {
+    /// Translation of every line of `texts`, in the same order
+    fn translate(&self, texts: &[String], source: &str, target: &str) -> anyhow::Result<Vec<String>>;
+}
+
+/// Copy of `segments` with their text translated, timing and speakers are kept
+pub fn translate_segments(translator: &dyn Translator, segments: &[Segment], source: &str, target: &str) -> anyhow::Result<Vec<Segment>> {
+    let texts: Vec<String> = segments.iter().map(|segment| segment.text.trim().to_owned()).collect();
+
+    let translations = translator.translate(&texts, source, target)?;
+
+    anyhow::ensure!(translations.len() == segments.len(), "Received {} translations for {} lines", translations.len(), segments.len());
+
+    Ok(segments.iter()
+        .zip(translations)
+        // Words with their confidence belong to the original language
+        .map(|(segment, text)| Segment { text, words: Vec::new(), ..segment.clone() })
+        .collect())
+}
+
+/// Where `name.srt` is translated to, e.g. `name.de.srt`
+pub fn translated_path(transcription_path: &std::path::Path, language: &str) -> PathBuf {
+    transcription_path.with_extension(format!("{language}.srt"))
+}
+
+/// Self-hosted server with the LibreTranslate API
+pub struct LibreTranslate {
+    pub url: String,
+    pub api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LibreTranslateRequest<'a> {
+    q: &'a [String],
+    source: &'a str,
+    target: &'a str,
+    format: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LibreTranslateResponse {
+    Translated {
+        #[serde(rename = "translatedText")]
+        translated_text: Vec<String>,
+    },
+    Error {
+        error: String,
+    },
+}
+
+impl Translator for LibreTranslate {
+    fn translate(&self, texts: &[String], source: &str, target: &str) -> anyhow::Result<Vec<String>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let url = format!("{}/translate", self.url.trim_end_matches('/'));
+
+        let response: LibreTranslateResponse = reqwest::blocking::Client::new()
+            .post(&url)
+            .json(&LibreTranslateRequest { q: texts, source, target, format: "text", api_key: self.api_key.as_deref() })
+            .send()
+            .with_context(|| format!("Can't reach the translation server at {url}"))?
+            .json()
+            .context("Translation server sent an invalid response")?;
+
+        match response {
+            LibreTranslateResponse::Translated { translated_text } => Ok(translated_text),
+            LibreTranslateResponse::Error { error } => anyhow::bail!("Translation server refused translating {source} to {target}: {error}"),
+        }
+    }
+}
+
+/// Lines longer than this many tokens are cut, subtitles are far shorter
+const MAX_TRANSLATION_TOKENS: usize = 256;
+
+/// Files of an Opus-MT language pair, as exported to ONNX on the Hugging Face hub
+pub const TRANSLATION_MODEL_FILES: [&str; 4] = ["config.json", "tokenizer.json", "onnx/encoder_model.onnx", "onnx/decoder_model.onnx"];
+
+fn translation_model_dir(source: &str, target: &str) -> PathBuf {
+    crate::project_directory().cache_dir().join("translation").join(format!("opus-mt-{source}-{target}"))
+}
+
+/// Download url, destination and SHA-256 of every file of the `source` to `target` model,
+/// only pairs pinned in the model manifest can be downloaded
+pub fn translation_model_files(source: &str, target: &str) -> anyhow::Result<Vec<(String, PathBuf, String)>> {
+    let manifest = crate::transcriber::ModelManifest::get();
+
+    let model = manifest.translation_model(source, target)
+        .with_context(|| format!("No offline model translating {source} to {target} is pinned in the model manifest"))?;
+
+    let dir = translation_model_dir(source, target);
+
+    TRANSLATION_MODEL_FILES.iter()
+        .map(|file| {
+            let sha256 = model.sha256.get(*file).with_context(|| format!("No SHA-256 is pinned for {file} of {}", model.repo))?;
+            let url = format!("{}/{}/resolve/{}/{file}?download=true", manifest.base_url.trim_end_matches('/'), model.repo, model.revision);
+
+            anyhow::Ok((url, dir.join(file), sha256.clone()))
+        })
+        .collect()
+}
+
+pub fn is_translation_model_downloaded(source: &str, target: &str) -> bool {
+    let dir = translation_model_dir(source, target);
+
+    TRANSLATION_MODEL_FILES.iter().all(|file| dir.join(file).exists())
+}
+
+#[derive(Deserialize)]
+struct MarianConfig {
+    decoder_start_token_id: i64,
+    eos_token_id: i64,
+    pad_token_id: i64,
+}
+
+/// Opus-MT model of one language pair
+struct Marian {
+    config: MarianConfig,
+    tokenizer: tokenizers::Tokenizer,
+    encoder: ort::session::Session,
+    decoder: ort::session::Session,
+}
+
+impl Marian {
+    fn load(source: &str, target: &str) -> anyhow::Result<Self> {
+        anyhow::ensure!(is_translation_model_downloaded(source, target), "Translation model from {source} to {target} is not downloaded");
+
+        let dir = translation_model_dir(source, target);
+
+        let config = serde_json::from_slice(&std::fs::read(dir.join("config.json"))?)?;
+        let tokenizer = tokenizers::Tokenizer::from_file(dir.join("tokenizer.json")).map_err(|err| anyhow::anyhow!("Invalid translation tokenizer: {err}"))?;
+
+        let encoder = ort::session::Session::builder()?.commit_from_file(dir.join("onnx/encoder_model.onnx"))?;
+        let decoder = ort::session::Session::builder()?.commit_from_file(dir.join("onnx/decoder_model.onnx"))?;
+
+        Ok(Self { config, tokenizer, encoder, decoder })
+    }
+
+    /// Greedy decoding, good enough for short subtitle lines
+    fn translate(&self, text: &str) -> anyhow::Result<String> {
+        use ort::value::Tensor;
+
+        if text.trim().is_empty() {
+            return Ok(String::new());
+        }
+
+        let encoding = self.tokenizer.encode(text, true).map_err(|err| anyhow::anyhow!("Can't tokenize {text:?}: {err}"))?;
+
+        let input_ids: Vec<i64> = encoding.get_ids().iter().take(MAX_TRANSLATION_TOKENS).map(|id| *id as i64).collect();
+        let attention_mask = vec![1i64; input_ids.len()];
+        let length = input_ids.len();
+
+        let encoded = self.encoder.run(ort::inputs![
+            "input_ids" => Tensor::from_array(([1, length], input_ids))?,
+            "attention_mask" => Tensor::from_array(([1, length], attention_mask.clone()))?,
+        ]?)?;
+
+        let (hidden_shape, hidden_states) = encoded["last_hidden_state"].try_extract_raw_tensor::<f32>()?;
+        let hidden_shape: Vec<usize> = hidden_shape.iter().map(|dimension| *dimension as usize).collect();
+        let hidden_states = hidden_states.to_vec();
+
+        let mut output_ids = vec![self.config.decoder_start_token_id];
+
+        while output_ids.len() <= MAX_TRANSLATION_TOKENS {
+            let decoded = self.decoder.run(ort::inputs![
+                "input_ids" => Tensor::from_array(([1, output_ids.len()], output_ids.clone()))?,
+                "encoder_hidden_states" => Tensor::from_array((hidden_shape.clone(), hidden_states.clone()))?,
+                "encoder_attention_mask" => Tensor::from_array(([1, length], attention_mask.clone()))?,
+            ]?)?;
+
+            let (logits_shape, logits) = decoded["logits"].try_extract_raw_tensor::<f32>()?;
+            let vocabulary = *logits_shape.last().context("Translation model returned no logits")? as usize;
+
+            let next = logits[logits.len() - vocabulary..].iter()
+                .enumerate()
+                .filter(|(id, _)| *id as i64 != self.config.pad_token_id)
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(id, _)| id as i64)
+                .context("Translation model returned empty logits")?;
+
+            if next == self.config.eos_token_id { break };
+
+            output_ids.push(next);
+        }
+
+        let output_ids: Vec<u32> = output_ids[1..].iter().map(|id| *id as u32).collect();
+
+        self.tokenizer.decode(&output_ids, true).map_err(|err| anyhow::anyhow!("Can't decode the translation: {err}"))
+    }
+}
+
+/// Offline translation with the downloaded Opus-MT model of each language pair
+pub struct LocalTranslator;
+
+impl Translator for LocalTranslator {
+    fn translate(&self, texts: &[String], source: &str, target: &str) -> anyhow::Result<Vec<String>> {
+        let model = Marian::load(source, target)?;
+
+        texts.iter().map(|text| model.translate(text)).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TranslationBackend {
+    Local,
+    LibreTranslate { url: String, api_key: Option<String> },
+}
+
+impl TranslationBackend {
+    pub fn translator(&self) -> Box<dyn Translator> {
+        match self {
+            TranslationBackend::Local => Box::new(LocalTranslator),
+            TranslationBackend::LibreTranslate { url, api_key } => Box::new(LibreTranslate { url: url.clone(), api_key: api_key.clone() }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, Response};
+    use crate::timestamp::Timestamp;
+    use crate::transcript::fixtures::segment;
+
+    /// LibreTranslate stand-in that answers with every line prefixed by the target language,
+    /// languages other than `de`, `ja` and `id` are refused
+    fn serve() -> String {
+        test_server::serve(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let target = request["target"].as_str().unwrap();
+
+            if ["de", "ja", "id"].contains(&target) {
+                let translated: Vec<String> = request["q"].as_array().unwrap().iter().map(|text| format!("[{target}] {}", text.as_str().unwrap())).collect();
+
+                Response::json("200 OK", serde_json::json!({ "translatedText": translated }))
+            } else {
+                Response::json("400 BAD REQUEST", serde_json::json!({ "error": format!("{target} is not supported") }))
+            }
+        })
+    }
+
+    #[test]
+    fn translates_segments_through_server() {
+        let translator = LibreTranslate { url: serve(), api_key: None };
+        let segments = [segment(0, 200, " Good morning", Some("Me")), segment(300, 500, "How are you?", None)];
+
+        let translated = translate_segments(&translator, &segments, "en", "ja").unwrap();
+
+        assert_eq!(translated.len(), 2);
+        assert_eq!(translated[0].text, "[ja] Good morning");
+        assert_eq!(translated[0].speaker.as_deref(), Some("Me"));
+        assert_eq!((translated[1].start, translated[1].end), (Timestamp::from_millis(300), Timestamp::from_millis(500)));
+        assert_eq!(translated[1].text, "[ja] How are you?");
+    }
+
+    #[test]
+    fn reports_server_errors() {
+        let translator = LibreTranslate { url: format!("{}/", serve()), api_key: Some("key".to_owned()) };
+
+        let err = translate_segments(&translator, &[segment(0, 200, "Hello", None)], "en", "xx").unwrap_err();
+        assert!(err.to_string().contains("xx is not supported"), "{err}");
+
+        assert!(translate_segments(&translator, &[], "en", "xx").unwrap().is_empty());
+    }
+
+    #[test]
+    fn names_translated_files() {
+        assert_eq!(translated_path(std::path::Path::new("/tmp/meeting (1).srt"), "de"), PathBuf::from("/tmp/meeting (1).de.srt"));
+    }
+}
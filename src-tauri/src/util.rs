@@ -1,29 +1,5 @@
-use std::ops::Div as _;
-
 use tauri::Window;
 
-pub fn format_timestamp(seconds: i64, always_include_hours: bool, decimal_marker: &str) -> String {
-    assert!(seconds >= 0, "non-negative timestamp expected");
-    let mut milliseconds = (seconds * 10) as f32;
-
-    let hours = milliseconds.div(3_600_000.0).floor();
-    milliseconds -= hours * 3_600_000.0;
-
-    let minutes = milliseconds.div(60_000.0).floor();
-    milliseconds -= minutes * 60_000.0;
-
-    let seconds = milliseconds.div(1_000.0).floor();
-    milliseconds -= seconds * 1_000.0;
-
-    let hours_marker = if always_include_hours || hours as usize != 0 {
-        format!("{hours}:")
-    } else {
-        String::new()
-    };
-
-    format!("{hours_marker}{minutes:02}:{seconds:02}{decimal_marker}{milliseconds:03}")
-}
-
 pub fn emit_all<S: std::fmt::Debug + serde::Serialize + Clone + Send + 'static>(
     window: &Window,
     channel: impl AsRef<str>,
@@ -0,0 +1,251 @@
+use std::ops::Range;
+
+/// Sample rate of the PCM produced by [`crate::transcriber::decode_audio`]
+pub const SAMPLE_RATE: usize = 16000;
+
+/// 30ms analysis frames
+const FRAME_LENGTH: usize = SAMPLE_RATE * 30 / 1000;
+
+/// Frames this much louder than the noise floor are speech
+const SPEECH_ABOVE_NOISE_DB: f32 = 10.0;
+
+/// Quieter frames are never speech, so a silent clip isn't taken for speech over its own noise
+const MIN_SPEECH_THRESHOLD_DB: f32 = -60.0;
+
+/// Frames this much louder than the noise floor are loud enough to bound the speech threshold
+const LOUD_ABOVE_NOISE_DB: f32 = SPEECH_ABOVE_NOISE_DB / 2.0;
+
+/// Kept around each speech region so word edges aren't cut
+const SPEECH_PADDING: usize = SAMPLE_RATE / 5;
+
+/// Pauses shorter than this stay inside the speech region
+const MIN_SILENCE: usize = SAMPLE_RATE / 2;
+
+/// Shorter bursts are clicks or bumps rather than speech
+const MIN_SPEECH: usize = SAMPLE_RATE / 10;
+
+/// Silence put between speech regions when they're joined, so whisper doesn't merge words across them
+const REGION_GAP: usize = SAMPLE_RATE / 5;
+
+fn frame_db(frame: &[f32]) -> f32 {
+    let power = frame.iter().map(|sample| sample * sample).sum::<f32>() / frame.len().max(1) as f32;
+
+    10.0 * power.max(1e-10).log10()
+}
+
+/// Level above which frames are speech
+///
+/// Speech is well above the noise floor, the 10th percentile. Quiet speech close to the floor is still kept by never asking
+/// for more than the 10th percentile of the loud frames, which are speech too when the recording barely pauses.
+fn speech_threshold(levels: &[f32]) -> f32 {
+    let mut sorted_levels = levels.to_vec();
+    sorted_levels.sort_by(f32::total_cmp);
+
+    let noise_floor = sorted_levels[sorted_levels.len() / 10];
+
+    let loud = &sorted_levels[sorted_levels.partition_point(|level| *level < noise_floor + LOUD_ABOVE_NOISE_DB)..];
+    let quietest_loud = loud.get(loud.len() / 10).copied().unwrap_or(f32::INFINITY);
+
+    (noise_floor + SPEECH_ABOVE_NOISE_DB).min(quietest_loud).max(MIN_SPEECH_THRESHOLD_DB)
+}
+
+/// Sample ranges of `pcm` that contain speech, detected by comparing each frame's energy to the noise floor
+pub fn detect_speech(pcm: &[f32]) -> Vec<Range<usize>> {
+    let levels: Vec<f32> = pcm.chunks(FRAME_LENGTH).map(frame_db).collect();
+
+    if levels.is_empty() {
+        return Vec::new();
+    }
+
+    let threshold = speech_threshold(&levels);
+
+    let mut regions: Vec<Range<usize>> = Vec::new();
+
+    for (index, level) in levels.iter().enumerate() {
+        if *level < threshold {
+            continue;
+        }
+
+        let start = (index * FRAME_LENGTH).saturating_sub(SPEECH_PADDING);
+        let end = ((index + 1) * FRAME_LENGTH + SPEECH_PADDING).min(pcm.len());
+
+        match regions.last_mut() {
+            Some(last) if start <= last.end + MIN_SILENCE => last.end = end,
+            _ => regions.push(start..end),
+        }
+    }
+
+    regions.retain(|region| region.len() >= MIN_SPEECH + 2 * SPEECH_PADDING);
+
+    regions
+}
+
+/// Where the pieces of the joined speech come from in the original audio
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    /// Start in the joined audio, start in the original audio and length, in samples
+    pieces: Vec<(usize, usize, usize)>,
+}
+
+impl Timeline {
+    /// Timeline of audio that wasn't cut
+    pub fn identity(length: usize) -> Self {
+        Self { pieces: vec![(0, 0, length)] }
+    }
+
+    /// Original position of a segment start, snapped forward when it falls between regions
+    pub fn map_start(&self, sample: usize) -> usize {
+        self.pieces.iter()
+            .find(|(joined_start, _, length)| sample < joined_start + length)
+            .map(|(joined_start, source_start, _)| source_start + sample.saturating_sub(*joined_start))
+            .or_else(|| self.pieces.last().map(|(_, source_start, length)| source_start + length))
+            .unwrap_or(sample)
+    }
+
+    /// Original position of a segment end, snapped back when it falls between regions
+    pub fn map_end(&self, sample: usize) -> usize {
+        self.pieces.iter()
+            .rev()
+            .find(|(joined_start, _, _)| sample > *joined_start)
+            .map(|(joined_start, source_start, length)| source_start + (sample - joined_start).min(*length))
+            .or_else(|| self.pieces.first().map(|(_, source_start, _)| *source_start))
+            .unwrap_or(sample)
+    }
+
+    /// [`Timeline::map_start`] for whisper timestamps, which count 10ms units
+    pub fn map_start_centiseconds(&self, timestamp: i64) -> i64 {
+        to_centiseconds(self.map_start(to_samples(timestamp)))
+    }
+
+    /// [`Timeline::map_end`] for whisper timestamps, which count 10ms units
+    pub fn map_end_centiseconds(&self, timestamp: i64) -> i64 {
+        to_centiseconds(self.map_end(to_samples(timestamp)))
+    }
+}
+
+fn to_samples(centiseconds: i64) -> usize {
+    centiseconds.max(0) as usize * SAMPLE_RATE / 100
+}
+
+fn to_centiseconds(samples: usize) -> i64 {
+    (samples * 100 / SAMPLE_RATE) as i64
+}
+
+/// Join the speech `regions` of `pcm` with a short silence between them
+pub fn join_speech(pcm: &[f32], regions: &[Range<usize>]) -> (Vec<f32>, Timeline) {
+    let mut joined = Vec::with_capacity(regions.iter().map(|region| region.len() + REGION_GAP).sum());
+    let mut pieces = Vec::with_capacity(regions.len());
+
+    for region in regions {
+        if !joined.is_empty() {
+            joined.resize(joined.len() + REGION_GAP, 0.0);
+        }
+
+        pieces.push((joined.len(), region.start, region.len()));
+        joined.extend_from_slice(&pcm[region.clone()]);
+    }
+
+    (joined, Timeline { pieces })
+}
+
+/// Whether the segment between the whisper timestamps `start` and `end` overlaps any of `regions`
+pub fn overlaps_speech(regions: &[Range<usize>], start: i64, end: i64) -> bool {
+    let (start, end) = (to_samples(start), to_samples(end));
+
+    regions.iter().any(|region| start < region.end && end > region.start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Noise with 440Hz bursts at the given ranges, in seconds, both at an RMS level in dBFS
+    fn clip_at(length: f32, bursts: &[(f32, f32)], noise_db: f32, burst_db: f32) -> Vec<f32> {
+        // Uniform noise and sines peak at √3 and √2 times their RMS
+        let noise_amplitude = 10f32.powf(noise_db / 20.0) * 3f32.sqrt();
+        let burst_amplitude = 10f32.powf(burst_db / 20.0) * 2f32.sqrt();
+
+        (0..(length * SAMPLE_RATE as f32) as usize)
+            .map(|i| {
+                let time = i as f32 / SAMPLE_RATE as f32;
+                let noise = ((i * 7919 % 1000) as f32 / 500.0 - 1.0) * noise_amplitude;
+                let speech = bursts.iter().any(|(start, end)| (*start..*end).contains(&time));
+
+                noise + if speech { burst_amplitude * (time * 440.0 * std::f32::consts::TAU).sin() } else { 0.0 }
+            })
+            .collect()
+    }
+
+    /// Quiet noise with loud bursts
+    fn clip(length: f32, bursts: &[(f32, f32)]) -> Vec<f32> {
+        clip_at(length, bursts, -65.0, -13.5)
+    }
+
+    fn seconds(sample: usize) -> f32 {
+        sample as f32 / SAMPLE_RATE as f32
+    }
+
+    #[test]
+    fn detects_bursts_with_padding() {
+        let regions = detect_speech(&clip(10.0, &[(1.0, 2.0), (6.0, 7.5)]));
+
+        assert_eq!(regions.len(), 2);
+        assert!((seconds(regions[0].start) - 0.8).abs() < 0.05);
+        assert!((seconds(regions[0].end) - 2.2).abs() < 0.05);
+        assert!((seconds(regions[1].start) - 5.8).abs() < 0.05);
+        assert!((seconds(regions[1].end) - 7.7).abs() < 0.05);
+    }
+
+    #[test]
+    fn merges_short_pauses_and_ignores_clicks() {
+        let regions = detect_speech(&clip(10.0, &[(1.0, 2.0), (2.3, 3.0), (8.0, 8.02)]));
+
+        assert_eq!(regions.len(), 1);
+        assert!((seconds(regions[0].end) - 3.2).abs() < 0.05);
+    }
+
+    #[test]
+    fn detects_quiet_speech() {
+        // Barely above a loud noise floor
+        let regions = detect_speech(&clip_at(10.0, &[(1.0, 2.0), (6.0, 7.5)], -52.0, -45.0));
+
+        assert_eq!(regions.len(), 2);
+        assert!((seconds(regions[0].start) - 0.8).abs() < 0.05);
+        assert!((seconds(regions[1].end) - 7.7).abs() < 0.05);
+    }
+
+    #[test]
+    fn silence_has_no_speech() {
+        assert!(detect_speech(&clip(5.0, &[])).is_empty());
+        assert!(detect_speech(&[]).is_empty());
+    }
+
+    #[test]
+    fn maps_joined_timestamps_back() {
+        let pcm = clip(10.0, &[(1.0, 2.0), (6.0, 7.5)]);
+        let regions = detect_speech(&pcm);
+        let (joined, timeline) = join_speech(&pcm, &regions);
+
+        assert_eq!(joined.len(), regions[0].len() + REGION_GAP + regions[1].len());
+
+        // 0.5s into each region
+        let first = regions[0].start + SAMPLE_RATE / 2;
+        let second = regions[1].start + SAMPLE_RATE / 2;
+        assert_eq!(timeline.map_start(SAMPLE_RATE / 2), first);
+        assert_eq!(timeline.map_end(regions[0].len() + REGION_GAP + SAMPLE_RATE / 2), second);
+
+        // A segment inside the gap maps to an empty range
+        let gap = regions[0].len() + REGION_GAP / 2;
+        assert!(timeline.map_start(gap) >= timeline.map_end(gap));
+
+        assert_eq!(timeline.map_start_centiseconds(50), to_centiseconds(first));
+    }
+
+    #[test]
+    fn finds_segments_without_speech() {
+        let regions = detect_speech(&clip(10.0, &[(1.0, 2.0)]));
+
+        assert!(overlaps_speech(&regions, 150, 300));
+        assert!(!overlaps_speech(&regions, 400, 600));
+    }
+}
@@ -0,0 +1,111 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::configuration::{ReplacementRule, VocabularyProfile};
+
+/// Whisper only conditions on the last 224 prompt tokens, longer glossaries lose their first terms
+const MAX_PROMPT_LENGTH: usize = 800;
+
+/// Initial prompt listing the glossary terms, `None` when the glossary is empty
+pub fn initial_prompt(profile: &VocabularyProfile) -> Option<String> {
+    let mut prompt = String::new();
+
+    for term in profile.glossary.iter().map(|term| term.trim().replace('\0', "")).filter(|term| !term.is_empty()) {
+        if prompt.len() + term.len() + 2 > MAX_PROMPT_LENGTH {
+            eprintln!("Glossary of {} is too long, ignoring terms from {term:?}", profile.name);
+            break;
+        }
+
+        if !prompt.is_empty() {
+            prompt.push_str(", ");
+        }
+
+        prompt.push_str(&term);
+    }
+
+    (!prompt.is_empty()).then(|| format!("{prompt}."))
+}
+
+enum Replacement {
+    Literal(String),
+    Expand(String),
+}
+
+/// Compiled [`ReplacementRule`]s, applied in order
+pub struct Replacer {
+    rules: Vec<(Regex, Replacement)>,
+}
+
+impl Replacer {
+    pub fn new(rules: &[ReplacementRule]) -> anyhow::Result<Self> {
+        let rules = rules.iter()
+            .filter(|rule| !rule.find.is_empty())
+            .map(|rule| {
+                let pattern = if rule.regex { rule.find.clone() } else { regex::escape(&rule.find) };
+
+                let find = RegexBuilder::new(&pattern)
+                    .case_insensitive(!rule.case_sensitive)
+                    .build()
+                    .map_err(|err| anyhow::anyhow!("Invalid pattern {:?}: {err}", rule.find))?;
+
+                let replace = if rule.regex { Replacement::Expand(rule.replace.clone()) } else { Replacement::Literal(rule.replace.clone()) };
+
+                anyhow::Ok((find, replace))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { rules })
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_owned();
+
+        for (find, replace) in &self.rules {
+            text = match replace {
+                Replacement::Literal(replace) => find.replace_all(&text, regex::NoExpand(replace)).into_owned(),
+                Replacement::Expand(replace) => find.replace_all(&text, replace.as_str()).into_owned(),
+            };
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(find: &str, replace: &str, regex: bool, case_sensitive: bool) -> ReplacementRule {
+        ReplacementRule { find: find.to_owned(), replace: replace.to_owned(), regex, case_sensitive }
+    }
+
+    #[test]
+    fn applies_rules_in_order() {
+        let replacer = Replacer::new(&[
+            rule("record script", "Recordscript", false, false),
+            rule(r"\bk8s\b", "Kubernetes", true, true),
+            rule(r"v(\d+)\.(\d+)", "version $1.$2", true, true),
+            rule("$1", "one", false, true),
+        ]).unwrap();
+
+        assert_eq!(
+            replacer.apply("Record Script on k8s v2.1 costs $1, K8S stays"),
+            "Recordscript on Kubernetes version 2.1 costs one, K8S stays",
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(Replacer::new(&[rule("(unclosed", "", true, false)]).is_err());
+        assert!(Replacer::new(&[rule("(unclosed", "", false, false)]).is_ok());
+    }
+
+    #[test]
+    fn builds_prompt_from_glossary() {
+        let mut profile = VocabularyProfile { name: "Team".to_owned(), glossary: vec![" Recordscript ".to_owned(), String::new(), "GStreamer".to_owned()], replacements: Vec::new() };
+
+        assert_eq!(initial_prompt(&profile).as_deref(), Some("Recordscript, GStreamer."));
+
+        profile.glossary.clear();
+        assert_eq!(initial_prompt(&profile), None);
+    }
+}
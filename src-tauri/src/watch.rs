@@ -0,0 +1,182 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, time::{Duration, Instant, UNIX_EPOCH}};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::configuration::{FileStamp, WatchFolderConfig, WatchFolderState, WatchedFolder};
+
+/// How often watched folders are checked for new files
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `*` matches any run of characters and `?` any one character, without case
+fn glob(pattern: &str) -> Regex {
+    let pattern = regex::escape(pattern.trim()).replace(r"\*", ".*").replace(r"\?", ".");
+
+    RegexBuilder::new(&format!("^{pattern}$")).case_insensitive(true).build().expect("Escaped glob is a valid regex")
+}
+
+/// Whether `path` is media the folder wants transcribed, what we write next to it never is
+fn is_wanted(patterns: &[Regex], path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    let is_ours = crate::subtitle::SubtitleFormat::from_path(path).is_some()
+        || name.contains(".redacted.")
+        || [crate::review::REVIEW_EXTENSION, crate::redaction::AUDIT_EXTENSION].iter().any(|extension| name.ends_with(&format!(".{extension}")));
+
+    !is_ours && (patterns.is_empty() || patterns.iter().any(|pattern| pattern.is_match(&name)))
+}
+
+fn stamp(metadata: &std::fs::Metadata) -> FileStamp {
+    FileStamp {
+        size: metadata.len(),
+        modified: metadata.modified().ok().and_then(|modified| modified.duration_since(UNIX_EPOCH).ok()).map_or(0, |modified| modified.as_secs()),
+    }
+}
+
+/// Wanted files of `folder`, hidden files and folders are left out
+fn scan(folder: &WatchedFolder) -> Vec<(PathBuf, FileStamp)> {
+    let patterns: Vec<Regex> = folder.patterns.iter().filter(|pattern| !pattern.trim().is_empty()).map(|pattern| glob(pattern)).collect();
+
+    let mut files = Vec::new();
+    let mut directories = vec![folder.path.clone()];
+
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = std::fs::read_dir(&directory) else { continue };
+
+        for entry in entries.flatten() {
+            let (path, Ok(metadata)) = (entry.path(), entry.metadata()) else { continue };
+
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            if metadata.is_dir() {
+                if folder.recursive {
+                    directories.push(path);
+                }
+            } else if is_wanted(&patterns, &path) {
+                files.push((path, stamp(&metadata)));
+            }
+        }
+    }
+
+    files
+}
+
+/// Files that stopped changing, a file still being written keeps growing
+#[derive(Debug, Default)]
+struct Settling {
+    seen: HashMap<PathBuf, (FileStamp, Instant)>,
+}
+
+impl Settling {
+    /// Files of `files` that kept the same stamp for `settle`, they are forgotten once returned
+    fn update(&mut self, files: Vec<(PathBuf, FileStamp)>, now: Instant, settle: Duration) -> Vec<(PathBuf, FileStamp)> {
+        let mut seen = HashMap::new();
+        let mut settled = Vec::new();
+
+        for (path, stamp) in files {
+            let since = match self.seen.get(&path) {
+                Some((last, since)) if *last == stamp => *since,
+                _ => now,
+            };
+
+            // Empty files are still being created
+            if stamp.size > 0 && now.duration_since(since) >= settle {
+                settled.push((path, stamp));
+            } else {
+                seen.insert(path, (stamp, since));
+            }
+        }
+
+        self.seen = seen;
+        settled
+    }
+}
+
+/// Check the watch folders every few seconds, `transcribe` gets every finished file that wasn't transcribed yet,
+/// one at a time, and returns once it's done, `false` when it failed and should be tried again later.
+/// Watching pauses while `can_transcribe` is `false`
+pub fn watch_folders(can_transcribe: impl Fn() -> bool + Send + 'static, transcribe: impl Fn(&Path) -> bool + Send + 'static) {
+    std::thread::spawn(move || {
+        let mut settling = Settling::default();
+
+        loop {
+            std::thread::sleep(WATCH_POLL_INTERVAL);
+
+            let config = WatchFolderConfig::default();
+
+            if !config.enabled {
+                settling = Settling::default();
+                continue;
+            }
+
+            if !can_transcribe() {
+                continue;
+            }
+
+            let state = WatchFolderState::default();
+
+            let files = config.folders.iter()
+                .flat_map(scan)
+                .filter(|(path, stamp)| state.transcribed.get(path) != Some(stamp))
+                .collect();
+
+            for (path, stamp) in settling.update(files, Instant::now(), Duration::from_secs(config.settle_seconds)) {
+                println!("Transcribing {} from a watched folder", path.display());
+
+                if !transcribe(&path) {
+                    continue;
+                }
+
+                // Transcribing takes a while, the state may have changed meanwhile
+                let mut state = WatchFolderState::default();
+                state.transcribed.insert(path, stamp);
+                crate::configuration::save(&state);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size: u64) -> (PathBuf, FileStamp) {
+        (PathBuf::from(path), FileStamp { size, modified: 0 })
+    }
+
+    #[test]
+    fn matches_globs() {
+        let patterns = [glob("*.mp4"), glob("meeting-??.MKV")];
+
+        assert!(is_wanted(&patterns, Path::new("/shared/Standup.MP4")));
+        assert!(is_wanted(&patterns, Path::new("/shared/meeting-01.mkv")));
+        assert!(!is_wanted(&patterns, Path::new("/shared/meeting-001.mkv")));
+        assert!(!is_wanted(&patterns, Path::new("/shared/standup.mp4.part")));
+
+        assert!(is_wanted(&[], Path::new("/shared/call.m4a")));
+        assert!(!is_wanted(&[], Path::new("/shared/call.srt")));
+        assert!(!is_wanted(&[], Path::new("/shared/call.redacted.m4a")));
+        assert!(!is_wanted(&[], Path::new("/shared/call.review.txt")));
+        assert!(!is_wanted(&[], Path::new("/shared/call.redactions.log")));
+        assert!(is_wanted(&[], Path::new("/shared/review.mp4")));
+    }
+
+    #[test]
+    fn waits_for_files_to_settle() {
+        let mut settling = Settling::default();
+        let start = Instant::now();
+        let settle = Duration::from_secs(10);
+
+        assert!(settling.update(vec![file("/a.mp4", 100), file("/b.mp4", 0)], start, settle).is_empty());
+
+        // Still growing
+        assert!(settling.update(vec![file("/a.mp4", 200), file("/b.mp4", 0)], start + Duration::from_secs(8), settle).is_empty());
+        assert!(settling.update(vec![file("/a.mp4", 200), file("/b.mp4", 0)], start + Duration::from_secs(16), settle).is_empty());
+
+        assert_eq!(settling.update(vec![file("/a.mp4", 200), file("/b.mp4", 0)], start + Duration::from_secs(18), settle), [file("/a.mp4", 200)]);
+
+        // Empty files never settle and settled files aren't returned twice
+        assert!(settling.update(vec![file("/b.mp4", 0)], start + Duration::from_secs(60), settle).is_empty());
+    }
+}
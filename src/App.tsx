@@ -28,6 +28,7 @@ import github_mark from "./assets/github-mark.svg";
 import { getName, getVersion } from "@tauri-apps/api/app";
 
 interface DeviceResult {
+    id: string;
     name: string;
     is_selected: boolean;
 }
@@ -35,13 +36,16 @@ interface DeviceResult {
 enum ModelCategory {
     Recommended = "Recommended",
     Other = "Other",
+    Imported = "Imported",
 }
 
 interface Model {
     type: string;
     name: string;
     disk_usage: number;
+    size_on_disk: number;
     mem_usage: number;
+    speaker_turns: boolean;
     is_downloaded: boolean;
     can_run: boolean;
     whitelisted_lang: string[] | null;
@@ -50,6 +54,167 @@ interface Model {
     description: string;
 }
 
+type DecodingStrategy = { Greedy: { best_of: number } } | { BeamSearch: { beam_size: number } };
+
+interface TranscriptionSettings {
+    strategy: DecodingStrategy;
+    temperature: number;
+    temperature_increment: number;
+    threads: number;
+    no_speech_threshold: number;
+    log_prob_threshold: number;
+    max_segment_length: number;
+    suppress_blank: boolean;
+    suppress_non_speech: boolean;
+    speaker_turns: boolean;
+}
+
+interface VadConfig {
+    voice_activity_detection: boolean;
+    drop_non_speech_segments: boolean;
+}
+
+interface ReplacementRule {
+    find: string;
+    replace: string;
+    regex: boolean;
+    case_sensitive: boolean;
+}
+
+interface VocabularyProfile {
+    name: string;
+    glossary: string[];
+    replacements: ReplacementRule[];
+}
+
+interface VocabularyConfig {
+    active_profile: string;
+    profiles: VocabularyProfile[];
+}
+
+interface SpeakerLabels {
+    me: string;
+    others: string;
+}
+
+interface DiarizationConfig {
+    enabled: boolean;
+    max_speakers: number;
+    similarity_threshold: number;
+    downloaded: boolean;
+}
+
+type TranslationBackend = "Local" | { LibreTranslate: { url: string; api_key: string | null } };
+
+interface TranslationConfig {
+    backend: TranslationBackend;
+    target_languages: string[];
+}
+
+type SubtitleFormat = "Srt" | "Vtt" | "Ass";
+
+interface BilingualConfig {
+    enabled: boolean;
+    format: SubtitleFormat;
+    max_line_length: number;
+    max_lines: number;
+}
+
+interface CueRules {
+    enabled: boolean;
+    max_line_length: number;
+    max_lines: number;
+    max_chars_per_second: number;
+    min_duration: number;
+    max_duration: number;
+    min_gap: number;
+}
+
+interface Segment {
+    start: number;
+    end: number;
+    text: string;
+    speaker: string | null;
+}
+
+interface ReviewConfig {
+    enabled: boolean;
+    threshold: number;
+}
+
+interface WatchedFolder {
+    path: string;
+    patterns: string[];
+    recursive: boolean;
+}
+
+type WatchOutput = "NextToFile" | "TranscriptSaveTo";
+
+interface WatchFolderConfig {
+    enabled: boolean;
+    folders: WatchedFolder[];
+    settle_seconds: number;
+    output: WatchOutput;
+    email: boolean;
+}
+
+interface AudioStream {
+    index: number;
+    codec: string;
+    language: string | null;
+    channels: number;
+    sample_rate: number;
+    bitrate: number | null;
+}
+
+interface VideoStream {
+    index: number;
+    codec: string;
+    language: string | null;
+    width: number;
+    height: number;
+    framerate: number | null;
+}
+
+interface MediaInfo {
+    duration: number | null;
+    container: string | null;
+    seekable: boolean;
+    audio_streams: AudioStream[];
+    video_streams: VideoStream[];
+    transcription_eta: number | null;
+}
+
+type AudioRedaction = "Keep" | "Bleep" | "Silence";
+
+interface RedactionConfig {
+    enabled: boolean;
+    emails: boolean;
+    phone_numbers: boolean;
+    card_numbers: boolean;
+    custom_patterns: string[];
+    audio: AudioRedaction;
+}
+
+interface Review {
+    path: string;
+    threshold: number;
+    email: boolean;
+    cues: {
+        index: number;
+        start: number;
+        end: number;
+        speaker: string | null;
+        words: { text: string; confidence: number; doubtful: boolean }[];
+    }[];
+}
+
+interface ModelStorage {
+    total_size: number;
+    models: [string, number][];
+    stray_files: { path: string; size: number; kind: "Partial" | "Orphan" }[];
+}
+
 enum ModelState {
     Stopped,
     Downloading,
@@ -76,6 +241,14 @@ interface SavePathConfig {
     save_path_histories: string[];
 }
 
+type AudioFileFormat = "Opus" | "Flac" | "Wav" | "M4a";
+
+type RecordMode = "Screen" | { Audio: AudioFileFormat };
+
+interface RecordConfig {
+    mode: RecordMode;
+}
+
 interface GeneralConfig {
     transcript: boolean;
     translate: boolean;
@@ -95,6 +268,7 @@ interface SMTPConfig {
 enum ActiveTab {
     Recorder,
     Transcribe,
+    Subtitles,
     About,
 }
 
@@ -328,6 +502,24 @@ function App() {
     const [model, set_model] = createSignal<string>("SmallWhisper");
     const [model_state, set_model_state] = createSignal(ModelState.Stopped);
     const [model_download_progress, set_model_download_progress] = createSignal(0);
+    const [benchmark_progress, set_benchmark_progress] = createSignal<string | null>(null);
+    const [vocabulary_config, { mutate: mutate_vocabulary_config }] = createInvokeResource<VocabularyConfig>("get_vocabulary_config");
+    const [speaker_labels, { mutate: mutate_speaker_labels }] = createInvokeResource<SpeakerLabels>("get_speaker_labels");
+    const [diarization_config, { mutate: mutate_diarization_config, refetch: update_diarization_config }] = createInvokeResource<DiarizationConfig>("get_diarization_config");
+    const [speaker_model_progress, set_speaker_model_progress] = createSignal<number | null>(null);
+    const [translation_config, { mutate: mutate_translation_config }] = createInvokeResource<TranslationConfig>("get_translation_config");
+    const [bilingual_config, { mutate: mutate_bilingual_config }] = createInvokeResource<BilingualConfig>("get_bilingual_config");
+    const [cue_rules, { mutate: mutate_cue_rules }] = createInvokeResource<CueRules>("get_cue_rules");
+    const [review_config, { mutate: mutate_review_config }] = createInvokeResource<ReviewConfig>("get_review_config");
+    const [redaction_config, { mutate: mutate_redaction_config }] = createInvokeResource<RedactionConfig>("get_redaction_config");
+    const [watch_folder_config, { mutate: mutate_watch_folder_config }] = createInvokeResource<WatchFolderConfig>("get_watch_folder_config");
+    const [editor_cues, set_editor_cues] = createSignal<Segment[] | null>(null);
+    const [translation_source, set_translation_source] = createSignal("en");
+    const [translation_model_progress, set_translation_model_progress] = createSignal<Record<string, number>>({});
+    const [transcription_settings, { mutate: mutate_transcription_settings }] = createResource(model, async (model) => {
+        return await invoke("get_transcription_settings", { model }) as TranscriptionSettings;
+    });
+    const [vad_config, { mutate: mutate_vad_config }] = createInvokeResource<VadConfig>("get_vad_config");
 
     const [language, set_language] = createSignal("auto");
 
@@ -335,12 +527,13 @@ function App() {
         RecorderState.Stopped,
     );
 
-    const [microphones] = createInvokeResource<DeviceResult[]>("list_microphone");
-    const [speakers] = createInvokeResource<DeviceResult[]>("list_speaker");
-    const [screens] = createInvokeResource<DeviceResult[]>("list_screen");
+    const [microphones, { refetch: update_microphones }] = createInvokeResource<DeviceResult[]>("list_microphone");
+    const [speakers, { refetch: update_speakers }] = createInvokeResource<DeviceResult[]>("list_speaker");
+    const [screens, { refetch: update_screens }] = createInvokeResource<DeviceResult[]>("list_screen");
 
     const [general_config, { refetch: update_general_config }] =
         createInvokeResource<GeneralConfig>("get_general_config");
+    const [record_config, { mutate: mutate_record_config }] = createInvokeResource<RecordConfig>("get_record_config");
     const [smtp_config, { refetch: update_smtp_config }] = createInvokeResource<
         SMTPConfig
     >("get_smtp_config");
@@ -349,10 +542,14 @@ function App() {
 
     const [models, { refetch: update_models }] = createInvokeResource<Model[]>("list_model");
     const [model_categories] = createInvokeResource<{ type: ModelCategory, name: string }[]>("list_model_categories");
+    const [model_storage, { refetch: update_model_storage }] = createInvokeResource<ModelStorage | null>("model_storage");
 
-    createEffect(() => invoke("select_microphone", { deviceName: microphone() }));
-    createEffect(() => invoke("select_speaker", { deviceName: speaker() }));
-    createEffect(() => invoke("select_screen", { deviceName: screen() }));
+    createEffect(() => microphone() && invoke("select_microphone", { deviceId: microphone() }));
+    createEffect(() => speaker() && invoke("select_speaker", { deviceId: speaker() }));
+    createEffect(() => screen() && invoke("select_screen", { deviceId: screen() }));
+
+    // Devices are only watched for hotplugging while they can be picked
+    createEffect(() => invoke("show_devices", { isShown: active_tab() === ActiveTab.Recorder }));
 
     createEffect(() => invoke("select_model", { model: model() }))
     createEffect(() => invoke("select_language", { language: language() }));
@@ -414,6 +611,18 @@ function App() {
         set_general_config(config);
     }
 
+    async function update_record_mode(value: string) {
+        const config: RecordConfig = { mode: value === "Screen" ? "Screen" : { Audio: value as AudioFileFormat } };
+
+        mutate_record_config(config);
+
+        await invoke("set_record_config", { config });
+    }
+
+    function is_screen_recording() {
+        return (record_config()?.mode ?? "Screen") === "Screen";
+    }
+
     async function update_is_translate(value: boolean) {
         let config = general_config()!;
 
@@ -513,6 +722,12 @@ function App() {
         },
     );
 
+    appWindow.listen("app://devices_changed", () => {
+        update_microphones();
+        update_speakers();
+        update_screens();
+    });
+
     appWindow.listen<EventResult>("app://notification", (event) => {
         let idx = -1;
 
@@ -567,6 +782,10 @@ function App() {
     });
 
 
+    appWindow.listen<Review>("app://transcript_review", (event) => {
+        set_popup(TranscriptReview(event.payload));
+    });
+
     appWindow.listen<string>("app://transcriber_start", (event) => {
         const transciption_uuid = event.payload;
 
@@ -633,18 +852,121 @@ function App() {
                     set_model_download_progress(0);
                     set_model_state(ModelState.Stopped);
                     update_models();
+                    update_model_storage();
+                    break;
+                case "error":
+                    set_model_download_progress(0);
+                    set_model_state(ModelState.Stopped);
+                    emit("app://notification", {
+                        type: "error",
+                        value: `Failed downloading the model because: ${event.payload.value}`,
+                    });
+                    break;
+            }
+        });
+    }
+
+    async function import_model() {
+        let result = await dialog.open({
+            multiple: false,
+            title: "Choose a whisper.cpp GGML model",
+            filters: [{ name: "GGML model", extensions: ["bin"] }],
+        });
+
+        let path = result as string ?? null;
+
+        if (path === null) return;
+
+        try {
+            const imported = await invoke("import_model", { path }) as string;
+
+            update_models();
+            update_model_storage();
+            set_model(imported);
+        } catch (_) {
+            // Reported through app://notification
+        }
+    }
+
+    async function delete_selected_model() {
+        const selected = models()?.find((m) => m.type === model());
+
+        if (selected === undefined) return;
+
+        const confirmed = await dialog.confirm(`Delete ${selected.name} (${util.megabytes_to_jedec_string(selected.size_on_disk / 1000000)})?`, { title: "Delete model", type: "warning" });
+
+        if (!confirmed) return;
+
+        try {
+            await invoke("delete_model", { model: selected.type });
+        } catch (_) {
+            // Reported through app://notification
+        }
+
+        update_models();
+        update_model_storage();
+    }
+
+    async function clean_model_storage() {
+        const freed = await invoke("clean_model_storage") as number;
+
+        emit("app://notification", {
+            type: "info",
+            value: `Removed leftover model files, freed ${util.megabytes_to_jedec_string(freed / 1000000)}`,
+        });
+
+        update_models();
+        update_model_storage();
+    }
+
+    async function benchmark_models() {
+        set_benchmark_progress("Preparing benchmark");
+
+        const channel_name = await invoke("benchmark_models") as string;
+
+        const unlisten = appWindow.listen<EventResult>(channel_name, (event) => {
+            switch (event.payload.type) {
+                case "progress":
+                    const { model, index, count } = event.payload.value;
+                    set_benchmark_progress(`Benchmarking ${model} (${index + 1}/${count})`);
+                    break;
+                case "done":
+                    set_benchmark_progress(null);
+
+                    const recommended = models()?.find((m) => m.type === event.payload.value.recommended);
+
+                    emit("app://notification", {
+                        type: "info",
+                        value: recommended
+                            ? `Benchmark finished, ${recommended.name} (${recommended.type_name}) is the best model that's fast enough on this machine`
+                            : "Benchmark finished, none of the downloaded models is fast enough on this machine",
+                    });
+
+                    if (recommended) set_model(recommended.type);
+
+                    unlisten.then((f) => f());
+                    break;
+                case "error":
+                    set_benchmark_progress(null);
+
+                    emit("app://notification", {
+                        type: "error",
+                        value: `Failed benchmarking models because: ${event.payload.value}`,
+                    });
+
+                    unlisten.then((f) => f());
                     break;
             }
         });
     }
 
     function ScreenSelector() {
-        function Screen<P extends { name: string; display_name: string; selected?: boolean }>(props: P) {
-            const [preview] = createInvokeResource<string>("preview_screen", { deviceName: props.name });
+        function Screen<P extends { id: string; display_name: string; selected?: boolean }>(props: P) {
+            const [preview] = createInvokeResource<string>("preview_screen", { deviceId: props.id });
 
             return <button class={`rounded p-2 outline outline-0 ${props.selected ? "bg-blue-50 !outline-2 outline-blue-300" : "hover:bg-blue-50 hover:outline-2 hover:outline-gray-300"}`}
             onClick={(_) => {
-                set_screen(props.name);
+                set_screen(props.id);
             }}>
                 <img class="max-w-64 rounded" src={`data:image/png;base64,${preview()}`}/>
                 <span class="text-sm">{props.display_name}</span>
@@ -658,7 +980,7 @@ function App() {
                     <Suspense>
                         <For each={screens()!}>
                             {(device, idx) => (
-                                <Screen name={device.name} display_name={`Screen ${idx() + 1}`} selected={screen() ? screen() === device.name : device.is_selected} />
+                                <Screen id={device.id} display_name={`Screen ${idx() + 1}`} selected={screen() ? screen() === device.id : device.is_selected} />
                             )}
                         </For>
                     </Suspense>
@@ -725,6 +1047,52 @@ function App() {
                             </Switch>
                         </Show>
                     </Suspense>
+                    <div class="flex gap-2 items-center text-xs text-gray-500">
+                        <button
+                            class="underline w-fit cursor-pointer"
+                            onclick={import_model}
+                        >
+                            Import model...
+                        </button>
+                        <Show
+                            when={benchmark_progress() === null}
+                            fallback={<span>{benchmark_progress()}</span>}
+                        >
+                            <button
+                                class="underline w-fit cursor-pointer"
+                                onclick={benchmark_models}
+                            >
+                                Benchmark
+                            </button>
+                        </Show>
+                        <Show when={models()?.find((m) => m.type === model())?.is_downloaded}>
+                            <button
+                                class="underline w-fit cursor-pointer"
+                                onclick={delete_selected_model}
+                            >
+                                Delete model
+                            </button>
+                        </Show>
+                        <Suspense>
+                            <Show when={model_storage()} keyed>
+                                {(storage) => (
+                                    <>
+                                        <span class="ml-auto">
+                                            Models use {util.megabytes_to_jedec_string(storage.total_size / 1000000)}
+                                        </span>
+                                        <Show when={storage.stray_files.length > 0 && model_state() !== ModelState.Downloading}>
+                                            <button
+                                                class="underline w-fit cursor-pointer"
+                                                onclick={clean_model_storage}
+                                            >
+                                                Clean up {storage.stray_files.length} leftover files
+                                            </button>
+                                        </Show>
+                                    </>
+                                )}
+                            </Show>
+                        </Suspense>
+                    </div>
                 </div>
             </section>
         )
@@ -753,6 +1121,778 @@ function App() {
         )
     }
 
+    async function update_transcription_settings(change: Partial<TranscriptionSettings>) {
+        const settings = { ...transcription_settings()!, ...change };
+
+        mutate_transcription_settings(settings);
+        await invoke("set_transcription_settings", { model: model(), settings });
+    }
+
+    async function update_vad_config(change: Partial<VadConfig>) {
+        const config = { ...vad_config()!, ...change };
+
+        mutate_vad_config(config);
+        await invoke("set_vad_config", { config });
+    }
+
+    async function reset_transcription_settings() {
+        mutate_transcription_settings(await invoke("reset_transcription_settings", { model: model() }) as TranscriptionSettings);
+    }
+
+    function TranscriptionSettingsSection() {
+        function NumberSetting<P extends { label: string; value: number; step?: number; min?: number; on_change: (value: number) => void }>(props: P) {
+            return (
+                <label class="flex items-center justify-between gap-2">
+                    {props.label}
+                    <input
+                        type="number"
+                        class="border p-1 w-24"
+                        step={props.step ?? 1}
+                        min={props.min}
+                        value={props.value}
+                        onchange={(e) => {
+                            const value = parseFloat(e.target.value);
+                            if (!isNaN(value)) props.on_change(value);
+                        }}
+                    />
+                </label>
+            )
+        }
+
+        return (
+            <Show when={transcription_settings()}>
+                {(settings) => (
+                    <details class="text-xs">
+                        <summary class="text-sm font-bold cursor-pointer">Advanced transcription settings</summary>
+                        <div class="flex flex-col gap-1 pl-4 pt-1">
+                            <label class="flex items-center justify-between gap-2">
+                                Decoding
+                                <select
+                                    class="border p-1 w-24"
+                                    onchange={(e) => update_transcription_settings({
+                                        strategy: e.target.value === "BeamSearch" ? { BeamSearch: { beam_size: 5 } } : { Greedy: { best_of: 5 } },
+                                    })}
+                                >
+                                    <option value="Greedy" selected={"Greedy" in settings().strategy}>Greedy</option>
+                                    <option value="BeamSearch" selected={"BeamSearch" in settings().strategy}>Beam search</option>
+                                </select>
+                            </label>
+                            <Show
+                                when={"BeamSearch" in settings().strategy}
+                                fallback={<NumberSetting label="Best of" min={1} value={(settings().strategy as { Greedy: { best_of: number } }).Greedy?.best_of ?? 5} on_change={(best_of) => update_transcription_settings({ strategy: { Greedy: { best_of } } })} />}
+                            >
+                                <NumberSetting label="Beam size" min={1} value={(settings().strategy as { BeamSearch: { beam_size: number } }).BeamSearch?.beam_size ?? 5} on_change={(beam_size) => update_transcription_settings({ strategy: { BeamSearch: { beam_size } } })} />
+                            </Show>
+                            <NumberSetting label="Temperature" step={0.1} min={0} value={settings().temperature} on_change={(temperature) => update_transcription_settings({ temperature })} />
+                            <NumberSetting label="Temperature fallback increment" step={0.1} min={0} value={settings().temperature_increment} on_change={(temperature_increment) => update_transcription_settings({ temperature_increment })} />
+                            <NumberSetting label="Threads (0 for auto)" min={0} value={settings().threads} on_change={(threads) => update_transcription_settings({ threads })} />
+                            <NumberSetting label="No speech threshold" step={0.05} value={settings().no_speech_threshold} on_change={(no_speech_threshold) => update_transcription_settings({ no_speech_threshold })} />
+                            <NumberSetting label="Log probability threshold" step={0.1} value={settings().log_prob_threshold} on_change={(log_prob_threshold) => update_transcription_settings({ log_prob_threshold })} />
+                            <NumberSetting label="Max segment length (0 for unlimited)" min={0} value={settings().max_segment_length} on_change={(max_segment_length) => update_transcription_settings({ max_segment_length })} />
+                            <label class="flex items-center justify-between gap-2">
+                                Suppress blank
+                                <input type="checkbox" checked={settings().suppress_blank} onchange={(e) => update_transcription_settings({ suppress_blank: e.target.checked })} />
+                            </label>
+                            <label class="flex items-center justify-between gap-2">
+                                Suppress non-speech tokens
+                                <input type="checkbox" checked={settings().suppress_non_speech} onchange={(e) => update_transcription_settings({ suppress_non_speech: e.target.checked })} />
+                            </label>
+                            <Show when={vad_config()}>
+                                {(vad) => (
+                                    <>
+                                        <label class="flex items-center justify-between gap-2" title="Only transcribe the parts of the recording with speech">
+                                            Skip silence
+                                            <input type="checkbox" checked={vad().voice_activity_detection} onchange={(e) => update_vad_config({ voice_activity_detection: e.target.checked })} />
+                                        </label>
+                                        <label class="flex items-center justify-between gap-2" title="Lines without detected speech are usually hallucinated">
+                                            Drop lines without speech
+                                            <input type="checkbox" checked={vad().drop_non_speech_segments} onchange={(e) => update_vad_config({ drop_non_speech_segments: e.target.checked })} />
+                                        </label>
+                                    </>
+                                )}
+                            </Show>
+                            <Show when={models()?.find((m) => m.type === model())?.speaker_turns}>
+                                <label class="flex items-center justify-between gap-2">
+                                    Mark speaker turns
+                                    <input type="checkbox" checked={settings().speaker_turns} onchange={(e) => update_transcription_settings({ speaker_turns: e.target.checked })} />
+                                </label>
+                            </Show>
+                            <button class="border rounded py-1 cursor-pointer w-fit px-2" onclick={reset_transcription_settings}>
+                                Reset to model defaults
+                            </button>
+                        </div>
+                    </details>
+                )}
+            </Show>
+        )
+    }
+
+    async function update_vocabulary_config(update: (config: VocabularyConfig) => void) {
+        const config: VocabularyConfig = structuredClone(vocabulary_config()!);
+
+        update(config);
+        mutate_vocabulary_config(config);
+
+        try {
+            await invoke("set_vocabulary_config", { config });
+        } catch (err) {
+            emit("app://notification", {
+                type: "error",
+                value: `Vocabulary isn't saved, fix the invalid pattern in ${err}`,
+            });
+        }
+    }
+
+    function update_active_profile(update: (profile: VocabularyProfile) => void) {
+        update_vocabulary_config((config) => {
+            const profile = config.profiles.find((p) => p.name === config.active_profile);
+            if (profile) update(profile);
+        });
+    }
+
+    function VocabularySection() {
+        const active_profile = () => vocabulary_config()?.profiles.find((p) => p.name === vocabulary_config()?.active_profile);
+
+        return (
+            <Show when={vocabulary_config()}>
+                {(config) => (
+                    <details class="text-xs">
+                        <summary class="text-sm font-bold cursor-pointer">Vocabulary</summary>
+                        <div class="flex flex-col gap-1 pl-4 pt-1">
+                            <label class="flex items-center justify-between gap-2">
+                                Profile
+                                <select
+                                    class="border p-1 w-40"
+                                    onchange={(e) => update_vocabulary_config((c) => c.active_profile = e.target.value)}
+                                >
+                                    <For each={config().profiles}>
+                                        {(profile) => <option value={profile.name} selected={profile.name === config().active_profile}>{profile.name}</option>}
+                                    </For>
+                                </select>
+                                <button
+                                    class="border rounded py-1 px-2 cursor-pointer"
+                                    onclick={() => update_vocabulary_config((c) => {
+                                        let index = c.profiles.length + 1;
+                                        while (c.profiles.some((p) => p.name === `Profile ${index}`)) index++;
+
+                                        c.profiles.push({ name: `Profile ${index}`, glossary: [], replacements: [] });
+                                        c.active_profile = `Profile ${index}`;
+                                    })}
+                                >
+                                    New
+                                </button>
+                                <Show when={config().profiles.length > 1}>
+                                    <button
+                                        class="border rounded py-1 px-2 cursor-pointer"
+                                        onclick={() => update_vocabulary_config((c) => {
+                                            c.profiles = c.profiles.filter((p) => p.name !== c.active_profile);
+                                            c.active_profile = c.profiles[0].name;
+                                        })}
+                                    >
+                                        Delete
+                                    </button>
+                                </Show>
+                            </label>
+                            <Show when={active_profile()}>
+                                {(profile) => (
+                                    <>
+                                        <label class="flex items-center justify-between gap-2">
+                                            Name
+                                            <input
+                                                class="border p-1 w-40"
+                                                value={profile().name}
+                                                onchange={(e) => {
+                                                    const name = e.target.value.trim();
+                                                    if (name === "" || config().profiles.some((p) => p.name === name)) return;
+
+                                                    update_vocabulary_config((c) => {
+                                                        c.profiles.find((p) => p.name === c.active_profile)!.name = name;
+                                                        c.active_profile = name;
+                                                    });
+                                                }}
+                                            />
+                                        </label>
+                                        <label class="flex flex-col gap-1">
+                                            Glossary, one term per line. Whisper is prompted with these spellings.
+                                            <textarea
+                                                class="border p-1 h-20"
+                                                value={profile().glossary.join("\n")}
+                                                onchange={(e) => update_active_profile((p) => p.glossary = e.target.value.split("\n").map((t) => t.trim()).filter((t) => t !== ""))}
+                                            />
+                                        </label>
+                                        <span>Find and replace, applied to every line in order</span>
+                                        <For each={profile().replacements}>
+                                            {(rule, index) => (
+                                                <div class="flex items-center gap-1">
+                                                    <input class="border p-1 w-28" placeholder="Find" value={rule.find} onchange={(e) => update_active_profile((p) => p.replacements[index()].find = e.target.value)} />
+                                                    <input class="border p-1 w-28" placeholder="Replace" value={rule.replace} onchange={(e) => update_active_profile((p) => p.replacements[index()].replace = e.target.value)} />
+                                                    <label title="Find is a regular expression, replace can use $1 for its groups">
+                                                        <input type="checkbox" checked={rule.regex} onchange={(e) => update_active_profile((p) => p.replacements[index()].regex = e.target.checked)} /> Regex
+                                                    </label>
+                                                    <label>
+                                                        <input type="checkbox" checked={rule.case_sensitive} onchange={(e) => update_active_profile((p) => p.replacements[index()].case_sensitive = e.target.checked)} /> Case
+                                                    </label>
+                                                    <button class="px-1 cursor-pointer" onclick={() => update_active_profile((p) => p.replacements.splice(index(), 1))}>✕</button>
+                                                </div>
+                                            )}
+                                        </For>
+                                        <button
+                                            class="border rounded py-1 px-2 cursor-pointer w-fit"
+                                            onclick={() => update_active_profile((p) => p.replacements.push({ find: "", replace: "", regex: false, case_sensitive: false }))}
+                                        >
+                                            Add rule
+                                        </button>
+                                    </>
+                                )}
+                            </Show>
+                        </div>
+                    </details>
+                )}
+            </Show>
+        )
+    }
+
+    async function update_speaker_label(side: keyof SpeakerLabels, label: string) {
+        const labels: SpeakerLabels = { ...speaker_labels()!, [side]: label.trim() };
+        if (labels[side] === "") return;
+
+        mutate_speaker_labels(labels);
+        await invoke("set_speaker_labels", { labels });
+    }
+
+    function SpeakerLabelsSection() {
+        return (
+            <Show when={speaker_labels()}>
+                {(labels) => (
+                    <details class="text-xs">
+                        <summary class="text-sm font-bold cursor-pointer">Speaker Labels</summary>
+                        <div class="flex flex-col gap-1 pl-4 pt-1">
+                            <span>Lines of recordings are labelled by whether the microphone or the system audio was louder.</span>
+                            <label class="flex items-center justify-between gap-2">
+                                Microphone
+                                <input class="border p-1 w-40" value={labels().me} onchange={(e) => update_speaker_label("me", e.target.value)} />
+                            </label>
+                            <label class="flex items-center justify-between gap-2">
+                                System audio
+                                <input class="border p-1 w-40" value={labels().others} onchange={(e) => update_speaker_label("others", e.target.value)} />
+                            </label>
+                        </div>
+                    </details>
+                )}
+            </Show>
+        )
+    }
+
+    async function update_cue_rules(update: (rules: CueRules) => void) {
+        const rules: CueRules = { ...cue_rules()! };
+
+        update(rules);
+        mutate_cue_rules(rules);
+
+        await invoke("set_cue_rules", { rules });
+    }
+
+    function CueRulesSection() {
+        const number = (value: string, fallback: number, min: number) => Math.max(min, parseFloat(value) || fallback);
+
+        return (
+            <Show when={cue_rules()}>
+                {(rules) => (
+                    <details class="text-xs">
+                        <summary class="text-sm font-bold cursor-pointer">Subtitle Cues</summary>
+                        <div class="flex flex-col gap-1 pl-4 pt-1">
+                            <label class="flex items-center gap-2">
+                                <input type="checkbox" checked={rules().enabled} onchange={(e) => update_cue_rules((r) => r.enabled = e.target.checked)} />
+                                Split long lines into readable cues
+                            </label>
+                            <Show when={rules().enabled}>
+                                <label class="flex items-center justify-between gap-2">
+                                    Characters per line
+                                    <input type="number" min="10" class="border p-1 w-20" value={rules().max_line_length} onchange={(e) => update_cue_rules((r) => r.max_line_length = Math.round(number(e.target.value, 42, 10)))} />
+                                </label>
+                                <label class="flex items-center justify-between gap-2">
+                                    Lines per cue
+                                    <input type="number" min="1" class="border p-1 w-20" value={rules().max_lines} onchange={(e) => update_cue_rules((r) => r.max_lines = Math.round(number(e.target.value, 2, 1)))} />
+                                </label>
+                                <label class="flex items-center justify-between gap-2">
+                                    Characters per second
+                                    <input type="number" min="1" step="0.5" class="border p-1 w-20" value={rules().max_chars_per_second} onchange={(e) => update_cue_rules((r) => r.max_chars_per_second = number(e.target.value, 17, 1))} />
+                                </label>
+                                <label class="flex items-center justify-between gap-2">
+                                    Shortest cue (ms)
+                                    <input type="number" min="0" step="100" class="border p-1 w-20" value={rules().min_duration} onchange={(e) => update_cue_rules((r) => r.min_duration = Math.round(number(e.target.value, 1000, 0)))} />
+                                </label>
+                                <label class="flex items-center justify-between gap-2">
+                                    Longest cue (ms)
+                                    <input type="number" min="1000" step="100" class="border p-1 w-20" value={rules().max_duration} onchange={(e) => update_cue_rules((r) => r.max_duration = Math.round(number(e.target.value, 7000, 1000)))} />
+                                </label>
+                                <label class="flex items-center justify-between gap-2">
+                                    Gap between cues (ms)
+                                    <input type="number" min="0" step="10" class="border p-1 w-20" value={rules().min_gap} onchange={(e) => update_cue_rules((r) => r.min_gap = Math.round(number(e.target.value, 80, 0)))} />
+                                </label>
+                            </Show>
+                        </div>
+                    </details>
+                )}
+            </Show>
+        )
+    }
+
+    async function update_review_config(update: (config: ReviewConfig) => void) {
+        const config: ReviewConfig = { ...review_config()! };
+
+        update(config);
+        mutate_review_config(config);
+
+        await invoke("set_review_config", { config });
+    }
+
+    function ReviewSection() {
+        return (
+            <Show when={review_config()}>
+                {(review) => (
+                    <details class="text-xs">
+                        <summary class="text-sm font-bold cursor-pointer">Review</summary>
+                        <div class="flex flex-col gap-1 pl-4 pt-1">
+                            <label class="flex items-center gap-2">
+                                <input type="checkbox" checked={review().enabled} onchange={(e) => update_review_config((c) => c.enabled = e.target.checked)} />
+                                Proofread doubtful words before emailing
+                            </label>
+                            <Show when={review().enabled}>
+                                <label class="flex items-center justify-between gap-2">
+                                    Doubtful below confidence (%)
+                                    <input type="number" min="1" max="99" class="border p-1 w-20" value={Math.round(review().threshold * 100)} onchange={(e) => update_review_config((c) => c.threshold = Math.min(99, Math.max(1, parseInt(e.target.value) || 50)) / 100)} />
+                                </label>
+                            </Show>
+                        </div>
+                    </details>
+                )}
+            </Show>
+        )
+    }
+
+    async function update_redaction_config(update: (config: RedactionConfig) => void) {
+        const config: RedactionConfig = structuredClone(redaction_config()!);
+
+        update(config);
+        mutate_redaction_config(config);
+
+        try {
+            await invoke("set_redaction_config", { config });
+        } catch (err) {
+            emit("app://notification", {
+                type: "error",
+                value: `Redaction isn't saved, fix the invalid pattern: ${err}`,
+            });
+        }
+    }
+
+    function RedactionSection() {
+        return (
+            <Show when={redaction_config()}>
+                {(redaction) => (
+                    <details class="text-xs">
+                        <summary class="text-sm font-bold cursor-pointer">Redaction</summary>
+                        <div class="flex flex-col gap-1 pl-4 pt-1">
+                            <label class="flex items-center gap-2">
+                                <input type="checkbox" checked={redaction().enabled} onchange={(e) => update_redaction_config((c) => c.enabled = e.target.checked)} />
+                                Replace personal data with [REDACTED] before saving and emailing
+                            </label>
+                            <Show when={redaction().enabled}>
+                                <label class="flex items-center gap-2">
+                                    <input type="checkbox" checked={redaction().emails} onchange={(e) => update_redaction_config((c) => c.emails = e.target.checked)} />
+                                    Emails
+                                </label>
+                                <label class="flex items-center gap-2">
+                                    <input type="checkbox" checked={redaction().phone_numbers} onchange={(e) => update_redaction_config((c) => c.phone_numbers = e.target.checked)} />
+                                    Phone numbers
+                                </label>
+                                <label class="flex items-center gap-2">
+                                    <input type="checkbox" checked={redaction().card_numbers} onchange={(e) => update_redaction_config((c) => c.card_numbers = e.target.checked)} />
+                                    Card numbers
+                                </label>
+                                <label class="flex flex-col gap-1">
+                                    Custom patterns, one regular expression per line
+                                    <textarea
+                                        class="border p-1 h-20 font-mono"
+                                        value={redaction().custom_patterns.join("\n")}
+                                        onchange={(e) => update_redaction_config((c) => c.custom_patterns = e.target.value.split("\n").filter((p) => p.trim() !== ""))}
+                                    />
+                                </label>
+                                <label class="flex items-center justify-between gap-2">
+                                    Recording
+                                    <select class="border p-1 w-40" onchange={(e) => update_redaction_config((c) => c.audio = e.target.value as AudioRedaction)}>
+                                        <option value="Keep" selected={redaction().audio === "Keep"}>Keep as is</option>
+                                        <option value="Bleep" selected={redaction().audio === "Bleep"}>Save a bleeped copy</option>
+                                        <option value="Silence" selected={redaction().audio === "Silence"}>Save a silenced copy</option>
+                                    </select>
+                                </label>
+                                <span class="text-gray-500">What was redacted, without the data itself, is listed in name.redactions.log</span>
+                            </Show>
+                        </div>
+                    </details>
+                )}
+            </Show>
+        )
+    }
+
+    async function update_watch_folder_config(update: (config: WatchFolderConfig) => void) {
+        const config: WatchFolderConfig = structuredClone(watch_folder_config()!);
+
+        update(config);
+        mutate_watch_folder_config(config);
+
+        await invoke("set_watch_folder_config", { config });
+    }
+
+    async function add_watched_folder() {
+        const path = await dialog.open({
+            multiple: false,
+            directory: true,
+            title: "Choose a folder to watch",
+        }) as string | null;
+
+        if (path === null) return;
+
+        update_watch_folder_config((c) => {
+            if (c.folders.some((folder) => folder.path === path)) return;
+
+            c.folders.push({ path, patterns: ["*.mp4", "*.mkv", "*.webm", "*.m4a", "*.mp3", "*.wav", "*.ogg", "*.flac"], recursive: false });
+        });
+    }
+
+    function WatchFoldersSection() {
+        return (
+            <Show when={watch_folder_config()}>
+                {(watch) => (
+                    <details class="text-xs">
+                        <summary class="text-sm font-bold cursor-pointer">Watch Folders</summary>
+                        <div class="flex flex-col gap-1 pl-4 pt-1">
+                            <label class="flex items-center gap-2">
+                                <input type="checkbox" checked={watch().enabled} onchange={(e) => update_watch_folder_config((c) => c.enabled = e.target.checked)} />
+                                Transcribe media that appears in these folders
+                            </label>
+                            <For each={watch().folders}>
+                                {(folder, index) => (
+                                    <div class="flex flex-col gap-1 border-l pl-2">
+                                        <div class="flex items-center gap-1">
+                                            <span class="w-full truncate" title={folder.path}>{folder.path}</span>
+                                            <button
+                                                class="border rounded py-1 px-2 cursor-pointer"
+                                                onclick={() => update_watch_folder_config((c) => c.folders.splice(index(), 1))}
+                                            >
+                                                Remove
+                                            </button>
+                                        </div>
+                                        <label class="flex items-center justify-between gap-2">
+                                            Files
+                                            <input
+                                                class="border p-1 w-full"
+                                                placeholder="Every file"
+                                                value={folder.patterns.join(", ")}
+                                                onchange={(e) => update_watch_folder_config((c) => c.folders[index()].patterns = e.target.value.split(",").map((p) => p.trim()).filter((p) => p !== ""))}
+                                            />
+                                        </label>
+                                        <label class="flex items-center gap-2">
+                                            <input type="checkbox" checked={folder.recursive} onchange={(e) => update_watch_folder_config((c) => c.folders[index()].recursive = e.target.checked)} />
+                                            Include folders inside
+                                        </label>
+                                    </div>
+                                )}
+                            </For>
+                            <button onclick={add_watched_folder} class="border rounded py-1 px-2 cursor-pointer w-fit">Add Folder</button>
+                            <label class="flex items-center justify-between gap-2">
+                                Finished after unchanged for (s)
+                                <input type="number" min="1" class="border p-1 w-20" value={watch().settle_seconds} onchange={(e) => update_watch_folder_config((c) => c.settle_seconds = Math.max(1, parseInt(e.target.value) || 10))} />
+                            </label>
+                            <label class="flex items-center justify-between gap-2">
+                                Save transcripts
+                                <select class="border p-1 w-40" onchange={(e) => update_watch_folder_config((c) => c.output = e.target.value as WatchOutput)}>
+                                    <option value="NextToFile" selected={watch().output === "NextToFile"}>Next to the file</option>
+                                    <option value="TranscriptSaveTo" selected={watch().output === "TranscriptSaveTo"}>In the Save To folder</option>
+                                </select>
+                            </label>
+                            <label class="flex items-center gap-2">
+                                <input type="checkbox" checked={watch().email} onchange={(e) => update_watch_folder_config((c) => c.email = e.target.checked)} />
+                                Email transcripts
+                            </label>
+                            <span class="text-gray-500">Files already in the folders are transcribed too, each file only once</span>
+                        </div>
+                    </details>
+                )}
+            </Show>
+        )
+    }
+
+    function TranscriptReview(review: Review) {
+        async function proofread() {
+            try {
+                set_editor_cues(await invoke("open_subtitles", { path: review.path }) as Segment[]);
+                set_active_tab(ActiveTab.Subtitles);
+            } catch {}
+        }
+
+        async function send_email() {
+            await invoke("send_transcript_email", { path: review.path });
+            set_popup(null);
+        }
+
+        return (
+            <div class="flex flex-col gap-2 p-4 text-sm">
+                <h2 class="font-bold">{review.cues.length} cues need proofreading</h2>
+                <span class="text-xs text-gray-500">Highlighted words are below {Math.round(review.threshold * 100)}% confidence. {review.path}</span>
+                <div class="flex flex-col gap-1 max-h-96 overflow-y-auto text-xs">
+                    <For each={review.cues}>
+                        {(cue) => (
+                            <div class="flex gap-2 border-b pb-1">
+                                <span class="w-24 shrink-0 text-gray-500">#{cue.index + 1} {util.format_timestamp(cue.start)}</span>
+                                <p>
+                                    <Show when={cue.speaker}>
+                                        <span class="font-bold">{cue.speaker}: </span>
+                                    </Show>
+                                    <For each={cue.words}>
+                                        {(word) => (
+                                            <>
+                                                <span class={word.doubtful ? "bg-yellow-200 rounded px-0.5" : ""} title={`${Math.round(word.confidence * 100)}%`}>{word.text}</span>{" "}
+                                            </>
+                                        )}
+                                    </For>
+                                </p>
+                            </div>
+                        )}
+                    </For>
+                </div>
+                <div class="flex gap-2 justify-end">
+                    <button class="border rounded py-1 px-3 cursor-pointer" onclick={proofread}>Proofread</button>
+                    <Show when={review.email}>
+                        <button class="border rounded py-1 px-3 cursor-pointer" onclick={send_email}>Send Email</button>
+                    </Show>
+                </div>
+            </div>
+        )
+    }
+
+    async function update_diarization(update: (config: DiarizationConfig) => void) {
+        const config: DiarizationConfig = { ...diarization_config()! };
+
+        update(config);
+        mutate_diarization_config(config);
+
+        const { downloaded, ...saved } = config;
+        await invoke("set_diarization_config", { config: saved });
+    }
+
+    async function download_speaker_model() {
+        set_speaker_model_progress(0);
+
+        const channel_name = await invoke("download_diarization_model") as string;
+
+        const unlisten = appWindow.listen<EventResult>(channel_name, async (event) => {
+            switch (event.payload.type) {
+                case "progress":
+                    set_speaker_model_progress(parseInt(event.payload.value));
+                    break;
+                case "done":
+                    set_speaker_model_progress(null);
+                    update_diarization_config();
+                    (await unlisten)();
+                    break;
+                case "error":
+                    set_speaker_model_progress(null);
+                    emit("app://notification", {
+                        type: "error",
+                        value: `Failed downloading the speaker model because: ${event.payload.value}`,
+                    });
+                    (await unlisten)();
+                    break;
+            }
+        });
+    }
+
+    function DiarizationSection() {
+        return (
+            <Show when={diarization_config()}>
+                {(config) => (
+                    <details class="text-xs">
+                        <summary class="text-sm font-bold cursor-pointer">Speaker Diarization</summary>
+                        <div class="flex flex-col gap-1 pl-4 pt-1">
+                            <span>Tells speakers apart by their voice and labels lines SPEAKER_1, SPEAKER_2… Works with every model and language.</span>
+                            <label class="flex items-center justify-between gap-2">
+                                Enabled
+                                <input type="checkbox" checked={config().enabled} onchange={(e) => update_diarization((c) => c.enabled = e.target.checked)} />
+                            </label>
+                            <Show when={!config().downloaded}>
+                                <button
+                                    class="border rounded py-1 px-2 cursor-pointer w-fit"
+                                    disabled={speaker_model_progress() !== null}
+                                    onclick={download_speaker_model}
+                                >
+                                    {speaker_model_progress() === null ? "Download speaker model (27 MB)" : `Downloading ${speaker_model_progress()}%`}
+                                </button>
+                            </Show>
+                            <label class="flex items-center justify-between gap-2" title="0 lets the similarity threshold decide">
+                                Maximum speakers
+                                <input type="number" min="0" class="border p-1 w-20" value={config().max_speakers} onchange={(e) => update_diarization((c) => c.max_speakers = Math.max(0, parseInt(e.target.value) || 0))} />
+                            </label>
+                            <label class="flex items-center justify-between gap-2" title="Voices more similar than this are the same speaker, raise it when speakers are merged">
+                                Similarity threshold
+                                <input type="number" min="0" max="1" step="0.05" class="border p-1 w-20" value={config().similarity_threshold} onchange={(e) => update_diarization((c) => c.similarity_threshold = parseFloat(e.target.value))} />
+                            </label>
+                        </div>
+                    </details>
+                )}
+            </Show>
+        )
+    }
+
+    async function update_translation(update: (config: TranslationConfig) => void) {
+        const config: TranslationConfig = structuredClone(translation_config()!);
+
+        update(config);
+        mutate_translation_config(config);
+
+        await invoke("set_translation_config", { config });
+    }
+
+    async function update_bilingual(update: (config: BilingualConfig) => void) {
+        const config: BilingualConfig = { ...bilingual_config()! };
+
+        update(config);
+        mutate_bilingual_config(config);
+
+        await invoke("set_bilingual_config", { config });
+    }
+
+    async function download_translation_model(target: string) {
+        const source = translation_source();
+
+        set_translation_model_progress((progress) => ({ ...progress, [target]: 0 }));
+
+        const channel_name = await invoke("download_translation_model", { source, target }) as string;
+
+        const done = () => set_translation_model_progress(({ [target]: _, ...progress }) => progress);
+
+        const unlisten = appWindow.listen<EventResult>(channel_name, async (event) => {
+            switch (event.payload.type) {
+                case "progress":
+                    set_translation_model_progress((progress) => ({ ...progress, [target]: parseInt(event.payload.value) }));
+                    break;
+                case "done":
+                    done();
+                    emit("app://notification", { type: "info", value: `Translation model from ${source} to ${target} is downloaded` });
+                    (await unlisten)();
+                    break;
+                case "error":
+                    done();
+                    emit("app://notification", {
+                        type: "error",
+                        value: `No translation model from ${source} to ${target} could be downloaded: ${event.payload.value}`,
+                    });
+                    (await unlisten)();
+                    break;
+            }
+        });
+    }
+
+    function TranslationSection() {
+        return (
+            <Show when={translation_config()}>
+                {(config) => (
+                    <details class="text-xs">
+                        <summary class="text-sm font-bold cursor-pointer">Translation</summary>
+                        <div class="flex flex-col gap-1 pl-4 pt-1">
+                            <label class="flex items-center justify-between gap-2" title="Language codes like de, ja, id. Each one is saved as name.<language>.srt">
+                                Languages
+                                <input
+                                    class="border p-1 w-40"
+                                    placeholder="de, ja, id"
+                                    value={config().target_languages.join(", ")}
+                                    onchange={(e) => update_translation((c) => c.target_languages = e.target.value.split(",").map((l) => l.trim().toLowerCase()).filter((l) => l !== ""))}
+                                />
+                            </label>
+                            <label class="flex items-center justify-between gap-2">
+                                Translator
+                                <select
+                                    class="border p-1 w-40"
+                                    onchange={(e) => update_translation((c) => c.backend = e.target.value === "Local" ? "Local" : { LibreTranslate: { url: "http://localhost:5000", api_key: null } })}
+                                >
+                                    <option value="Local" selected={config().backend === "Local"}>Offline model</option>
+                                    <option value="LibreTranslate" selected={config().backend !== "Local"}>LibreTranslate server</option>
+                                </select>
+                            </label>
+                            <Show when={config().backend !== "Local"}>
+                                <label class="flex items-center justify-between gap-2">
+                                    Server
+                                    <input
+                                        class="border p-1 w-40"
+                                        value={(config().backend as any).LibreTranslate?.url ?? ""}
+                                        onchange={(e) => update_translation((c) => (c.backend as any).LibreTranslate.url = e.target.value.trim())}
+                                    />
+                                </label>
+                                <label class="flex items-center justify-between gap-2">
+                                    API key
+                                    <input
+                                        class="border p-1 w-40"
+                                        type="password"
+                                        value={(config().backend as any).LibreTranslate?.api_key ?? ""}
+                                        onchange={(e) => update_translation((c) => (c.backend as any).LibreTranslate.api_key = e.target.value.trim() || null)}
+                                    />
+                                </label>
+                            </Show>
+                            <Show when={config().backend === "Local"}>
+                                <label class="flex items-center justify-between gap-2" title="Offline models translate one language into another, download one for each language pair">
+                                    Transcripts in
+                                    <input class="border p-1 w-40" value={translation_source()} onchange={(e) => set_translation_source(e.target.value.trim().toLowerCase())} />
+                                </label>
+                                <For each={config().target_languages}>
+                                    {(target) => (
+                                        <button
+                                            class="border rounded py-1 px-2 cursor-pointer w-fit"
+                                            disabled={translation_model_progress()[target] !== undefined}
+                                            onclick={() => download_translation_model(target)}
+                                        >
+                                            {translation_model_progress()[target] === undefined
+                                                ? `Download model from ${translation_source()} to ${target}`
+                                                : `Downloading ${translation_model_progress()[target]}%`}
+                                        </button>
+                                    )}
+                                </For>
+                            </Show>
+                            <Show when={bilingual_config()}>
+                                {(bilingual) => (
+                                    <>
+                                        <label class="flex items-center justify-between gap-2" title="Saves name.<source>-<language> with the original line above its translation">
+                                            Bilingual subtitles
+                                            <input type="checkbox" checked={bilingual().enabled} onchange={(e) => update_bilingual((c) => c.enabled = e.target.checked)} />
+                                        </label>
+                                        <Show when={bilingual().enabled}>
+                                            <label class="flex items-center justify-between gap-2" title="ASS shows each language in its own style">
+                                                Format
+                                                <select class="border p-1 w-40" onchange={(e) => update_bilingual((c) => c.format = e.target.value as SubtitleFormat)}>
+                                                    <option value="Srt" selected={bilingual().format === "Srt"}>SRT</option>
+                                                    <option value="Vtt" selected={bilingual().format === "Vtt"}>WebVTT</option>
+                                                    <option value="Ass" selected={bilingual().format === "Ass"}>ASS</option>
+                                                </select>
+                                            </label>
+                                            <label class="flex items-center justify-between gap-2">
+                                                Characters per line
+                                                <input type="number" min="10" class="border p-1 w-20" value={bilingual().max_line_length} onchange={(e) => update_bilingual((c) => c.max_line_length = Math.max(10, parseInt(e.target.value) || 42))} />
+                                            </label>
+                                            <label class="flex items-center justify-between gap-2" title="Both languages together, longer cues are split">
+                                                Lines per cue
+                                                <input type="number" min="2" class="border p-1 w-20" value={bilingual().max_lines} onchange={(e) => update_bilingual((c) => c.max_lines = Math.max(2, parseInt(e.target.value) || 4))} />
+                                            </label>
+                                        </Show>
+                                    </>
+                                )}
+                            </Show>
+                        </div>
+                    </details>
+                )}
+            </Show>
+        )
+    }
+
     function RecorderSaveToSection<P extends { title_class?: string; }>(props: P) {
         return (
             <section class="flex items-center gap-2">
@@ -855,7 +1995,7 @@ function App() {
                                 <Suspense>
                                     <For each={microphones()!}>
                                         {(device) => (
-                                            <option value={device.name} selected={device.is_selected}>
+                                            <option value={device.id} selected={device.is_selected}>
                                                 {device.name}
                                             </option>
                                         )}
@@ -873,7 +2013,7 @@ function App() {
                                 <Suspense>
                                     <For each={speakers()!}>
                                         {(device) => (
-                                            <option value={device.name} selected={device.is_selected}>
+                                            <option value={device.id} selected={device.is_selected}>
                                                 {device.name}
                                             </option>
                                         )}
@@ -881,6 +2021,28 @@ function App() {
                                 </Suspense>
                             </select>
                         </section>
+                        <section class="flex items-center gap-2">
+                            <h3 class="text-sm font-bold my-0 h-fit w-32">Record</h3>
+                            <select
+                                class="border p-1 text-xs w-full"
+                                onchange={(e) => update_record_mode(e.target.value)}
+                            >
+                                <For each={[
+                                    ["Screen", "Screen and audio (MP4)"],
+                                    ["Opus", "Audio only (Opus)"],
+                                    ["Flac", "Audio only (FLAC)"],
+                                    ["Wav", "Audio only (WAV)"],
+                                    ["M4a", "Audio only (M4A)"],
+                                ]}>
+                                    {([value, label]) => {
+                                        const mode = record_config()?.mode;
+                                        const selected = mode === value || (typeof mode === "object" && mode.Audio === value);
+
+                                        return <option value={value} selected={selected}>{label}</option>;
+                                    }}
+                                </For>
+                            </select>
+                        </section>
                         <section class="flex items-center gap-2">
                             <h3 class="text-sm font-bold my-0 h-fit w-32">Subtitle</h3>
                             <input type="checkbox" onchange={(e) => update_is_transcript(e.target.checked)} checked={general_config()?.transcript} />
@@ -894,7 +2056,16 @@ function App() {
                         <div class="flex flex-col gap-1">
                             <ListModelSection />
                             <ListLanguageSection />
+                            <TranscriptionSettingsSection />
+                            <VocabularySection />
+                            <SpeakerLabelsSection />
+                            <DiarizationSection />
                             <TranscriberTranslateSection />
+                            <CueRulesSection />
+                            <ReviewSection />
+                            <RedactionSection />
+                            <WatchFoldersSection />
+                            <TranslationSection />
                         </div>
                     </div>
                 </Show>
@@ -954,7 +2125,7 @@ function App() {
                         </Match>
                         <Match when={recording_state() === RecorderState.Stopped}>
                             <button
-                                onclick={() => set_popup(ScreenSelector())}
+                                onclick={() => is_screen_recording() ? set_popup(ScreenSelector()) : recording.start()}
                                 class="w-full h-full max-h-[3rem] border border-x-transparent font-bold p-2 cursor-pointer"
                             >
                                 Start Recording
@@ -985,6 +2156,51 @@ function App() {
     function Transcribe() {
         const [file, set_file] = createSignal<string | null>(null);
         const [file_histories, set_file_histories] = createSignal<string[]>([]);
+        const [script, set_script] = createSignal<string | null>(null);
+        // Probed again when the model changes, for its estimated time
+        const [media_info] = createResource(() => file() === null ? null : { path: file()!, model: model() }, async ({ path }) => {
+            try {
+                return await invoke<MediaInfo>("probe_media", { path });
+            } catch {
+                return null;
+            }
+        });
+        const [audio_stream, set_audio_stream] = createSignal<number | null>(null);
+        const [range_start, set_range_start] = createSignal("");
+        const [range_end, set_range_end] = createSignal("");
+
+        createEffect(() => {
+            file();
+
+            set_audio_stream(null);
+            set_range_start("");
+            set_range_end("");
+        });
+
+        async function start_transcription() {
+            const start = range_start().trim() === "" ? null : util.parse_timestamp(range_start());
+            const end = range_end().trim() === "" ? null : util.parse_timestamp(range_end());
+
+            if ((range_start().trim() !== "" && start === null) || (range_end().trim() !== "" && end === null)) {
+                emit("app://notification", { type: "error", value: "Write the range like 01:02:03.500" });
+                return;
+            }
+
+            await invoke("start_transcription", { mediaPath: file(), selection: { start, end, audio_stream: audio_stream() } });
+        }
+
+        async function update_script_path() {
+            const path = await dialog.open({
+                multiple: false,
+                directory: false,
+                filters: [{ name: "Script", extensions: ["txt", "md"] }],
+                title: "Choose the narration script",
+            }) as string | null;
+
+            if (path === null) return;
+
+            set_script(path);
+        }
 
         async function update_file_path() {
             let result = await dialog.open({
@@ -1058,21 +2274,109 @@ function App() {
                             </button>
                         </div>
                     </section>
+                    <Show when={media_info()}>
+                        {(info) => (
+                            <section class="flex items-start gap-2">
+                                <h3 class="text-sm font-bold my-0 h-fit w-32">
+                                    Media
+                                </h3>
+                                <div class="flex flex-col w-full text-xs text-gray-500">
+                                    <span>
+                                        {[info().container, info().duration === null ? null : util.format_timestamp(info().duration!)].filter((part) => part !== null).join(", ")}
+                                        {info().seekable ? "" : ", not seekable"}
+                                    </span>
+                                    <For each={info().video_streams}>
+                                        {(stream) => (
+                                            <span>
+                                                Video {stream.index + 1}: {stream.codec}, {stream.width}x{stream.height}
+                                                {stream.framerate === null ? "" : `, ${stream.framerate.toFixed(2)} fps`}
+                                                {stream.language ? ` (${stream.language})` : ""}
+                                            </span>
+                                        )}
+                                    </For>
+                                    <For each={info().audio_streams}>
+                                        {(stream) => (
+                                            <span>
+                                                Audio {stream.index + 1}: {stream.codec}, {stream.sample_rate} Hz, {stream.channels}ch
+                                                {stream.bitrate === null ? "" : `, ${Math.round(stream.bitrate / 1000)} kbps`}
+                                                {stream.language ? ` (${stream.language})` : ""}
+                                            </span>
+                                        )}
+                                    </For>
+                                    <span>
+                                        {info().transcription_eta === null
+                                            ? "Benchmark the models to estimate how long transcribing takes"
+                                            : `Transcribing takes about ${util.format_timestamp(info().transcription_eta! * 1000).split(".")[0]} with this model`}
+                                    </span>
+                                </div>
+                            </section>
+                        )}
+                    </Show>
+                    <Show when={media_info()}>
+                        {(info) => (
+                            <section class="flex items-center gap-2">
+                                <h3 class="text-sm font-bold my-0 h-fit w-32">
+                                    Range
+                                </h3>
+                                <div class="flex items-center w-full gap-1 text-xs">
+                                    <input class="border p-1 w-24" placeholder="00:00:00.000" value={range_start()} onchange={(e) => set_range_start(e.target.value)} />
+                                    to
+                                    <input class="border p-1 w-24" placeholder={info().duration === null ? "end" : util.format_timestamp(info().duration!)} value={range_end()} onchange={(e) => set_range_end(e.target.value)} />
+                                    <Show when={info().audio_streams.length > 1}>
+                                        <select class="border p-1 w-full" onchange={(e) => set_audio_stream(parseInt(e.target.value))}>
+                                            <For each={info().audio_streams}>
+                                                {(stream) => (
+                                                    <option value={stream.index} selected={stream.index === (audio_stream() ?? 0)}>
+                                                        Track {stream.index + 1}{stream.language ? ` (${stream.language})` : ""}, {stream.codec}, {stream.channels}ch
+                                                    </option>
+                                                )}
+                                            </For>
+                                        </select>
+                                    </Show>
+                                </div>
+                            </section>
+                        )}
+                    </Show>
+                    <section class="flex items-center gap-2">
+                        <h3 class="text-sm font-bold my-0 h-fit w-32">
+                            Script
+                        </h3>
+                        <div class="flex items-center w-full gap-1 text-xs">
+                            <span class="w-full truncate" title="Subtitles use the exact wording of the script, timed to the recording">{script() ?? "None, transcribe freely"}</span>
+                            <Show when={script() !== null}>
+                                <button onclick={() => set_script(null)} class="border rounded py-1 px-2 cursor-pointer">Clear</button>
+                            </Show>
+                            <button onclick={update_script_path} class="border rounded py-1 px-2 cursor-pointer">Browse</button>
+                        </div>
+                    </section>
                 <ListModelSection />
                 <ListLanguageSection />
                 <TranscriptSaveToSection title_class="!w-32" />
                 <TranscriberTranslateSection />
+                <TranscriptionSettingsSection />
+                <VocabularySection />
+                <SpeakerLabelsSection />
+                <DiarizationSection />
+                <CueRulesSection />
+                <ReviewSection />
+                <RedactionSection />
+                <WatchFoldersSection />
+                <TranslationSection />
                 </div>
                 <div class="h-full flex items-end">
                     <Switch fallback={
                         <button
                             onClick={async () => {
-                                await invoke("start_transcription", { mediaPath: file() });
+                                if (script() === null) {
+                                    await start_transcription();
+                                } else {
+                                    await invoke("align_transcript", { media: file(), script: script() });
+                                }
                             }}
                             class="w-full h-full max-h-[3rem] border border-x-transparent font-bold p-2 cursor-pointer disabled:opacity-50 disabled:cursor-default"
                             disabled={file() === null}
                         >
-                            Generate Subtitle
+                            {script() === null ? "Generate Subtitle" : "Align Script"}
                         </button>
                     }>
                         <Match when={model() !== null && !models()?.find((m) => m.type === model())?.can_run}>
@@ -1097,6 +2401,134 @@ function App() {
         )
     }
 
+    function SubtitleEditor() {
+        const [cues, set_cues] = [editor_cues, set_editor_cues];
+        const [shift, set_shift] = createSignal(0);
+        const [from_fps, set_from_fps] = createSignal(23.976);
+        const [to_fps, set_to_fps] = createSignal(25);
+
+        // Commands notify about their own failures
+        async function edit(command: string, args: InvokeArgs) {
+            try {
+                set_cues(await invoke(command, args) as Segment[]);
+            } catch {}
+        }
+
+        async function open_file() {
+            const path = await dialog.open({
+                multiple: false,
+                directory: false,
+                filters: [{ name: "Subtitles", extensions: ["srt", "vtt", "ass", "ssa"] }],
+                title: "Choose subtitles to edit",
+            }) as string | null;
+
+            if (path === null) return;
+
+            await edit("open_subtitles", { path });
+        }
+
+        async function save_file(format: SubtitleFormat) {
+            const extension = format.toLowerCase();
+
+            const path = await dialog.save({
+                filters: [{ name: format.toUpperCase(), extensions: [extension] }],
+                title: "Save subtitles",
+            });
+
+            if (path === null) return;
+
+            await edit("save_subtitles", { path, format });
+
+            emit("app://notification", { type: "info", value: `Saved subtitles to ${path}` });
+        }
+
+        function edit_timing(index: number, field: "start" | "end", text: string) {
+            const milliseconds = util.parse_timestamp(text);
+
+            if (milliseconds === null) {
+                emit("app://notification", { type: "error", value: `${text} is not a timestamp like 00:01:02.345` });
+                set_cues(cues()!.map((cue) => ({ ...cue })));
+                return;
+            }
+
+            edit("edit_cue", { index, [field]: milliseconds });
+        }
+
+        return (
+            <div class="flex flex-col h-full gap-2 m-2 text-xs">
+                <div class="flex items-center gap-2">
+                    <button class="border rounded py-1 px-2 cursor-pointer" onclick={open_file}>Open</button>
+                    <Show when={cues()}>
+                        <button class="border rounded py-1 px-2 cursor-pointer" onclick={() => edit("save_subtitles", {})}>Save</button>
+                        <button class="border rounded py-1 px-2 cursor-pointer" onclick={() => save_file("Srt")}>Save as SRT</button>
+                        <button class="border rounded py-1 px-2 cursor-pointer" onclick={() => save_file("Vtt")}>Save as WebVTT</button>
+                        <button class="border rounded py-1 px-2 cursor-pointer" onclick={() => save_file("Ass")}>Save as ASS</button>
+                    </Show>
+                </div>
+                <Show when={cues()}>
+                    {(cues) => (
+                        <>
+                            <div class="flex items-center gap-2">
+                                <label class="flex items-center gap-1">
+                                    Shift by (ms)
+                                    <input type="number" step="100" class="border p-1 w-20" value={shift()} onchange={(e) => set_shift(parseInt(e.target.value) || 0)} />
+                                </label>
+                                <button class="border rounded py-1 px-2 cursor-pointer" onclick={() => edit("shift_subtitles", { milliseconds: shift() })}>Shift</button>
+                                <label class="flex items-center gap-1">
+                                    Framerate from
+                                    <input type="number" step="0.001" min="1" class="border p-1 w-20" value={from_fps()} onchange={(e) => set_from_fps(parseFloat(e.target.value) || 23.976)} />
+                                </label>
+                                <label class="flex items-center gap-1">
+                                    to
+                                    <input type="number" step="0.001" min="1" class="border p-1 w-20" value={to_fps()} onchange={(e) => set_to_fps(parseFloat(e.target.value) || 25)} />
+                                </label>
+                                <button class="border rounded py-1 px-2 cursor-pointer" onclick={() => edit("scale_subtitles", { factor: from_fps() / to_fps() })}>Convert</button>
+                            </div>
+                            <div class="flex flex-col gap-1 overflow-y-auto">
+                                <For each={cues()}>
+                                    {(cue, index) => (
+                                        <div class="flex items-start gap-2 border-b pb-1">
+                                            <span class="w-8 text-right pt-1">{index() + 1}</span>
+                                            <div class="flex flex-col gap-1">
+                                                <input class="border p-1 w-24" value={util.format_timestamp(cue.start)} onchange={(e) => edit_timing(index(), "start", e.target.value)} />
+                                                <input class="border p-1 w-24" value={util.format_timestamp(cue.end)} onchange={(e) => edit_timing(index(), "end", e.target.value)} />
+                                            </div>
+                                            <Show when={cue.speaker}>
+                                                <span class="pt-1 font-bold">{cue.speaker}:</span>
+                                            </Show>
+                                            <textarea
+                                                class="border p-1 w-full resize-none"
+                                                rows={2}
+                                                value={cue.text}
+                                                onchange={(e) => edit("edit_cue", { index: index(), text: e.target.value })}
+                                                onkeydown={(e) => {
+                                                    // Split the cue where the cursor is
+                                                    if (e.key === "Enter" && e.ctrlKey) {
+                                                        e.preventDefault();
+                                                        edit("split_cue", { index: index(), position: [...e.currentTarget.value.slice(0, e.currentTarget.selectionStart)].length });
+                                                    }
+                                                }}
+                                            />
+                                            <button
+                                                class="border rounded py-1 px-2 cursor-pointer disabled:opacity-50"
+                                                disabled={index() === cues().length - 1}
+                                                title="Merge with the next cue"
+                                                onclick={() => edit("merge_cues", { index: index() })}
+                                            >
+                                                Merge
+                                            </button>
+                                        </div>
+                                    )}
+                                </For>
+                            </div>
+                            <span class="text-gray-500">Press Ctrl+Enter in a cue to split it at the cursor.</span>
+                        </>
+                    )}
+                </Show>
+            </div>
+        )
+    }
+
     function About() {
         const [name] = createResource(getName);
         const [version] = createResource(getVersion);
@@ -1157,6 +2589,7 @@ function App() {
                 <div class="flex text-sm border border-b-transparent overflow-hidden w-full justify-evenly">
                     <button onClick={() => set_active_tab(ActiveTab.Recorder)} class={`border-b py-1 px-2 w-full ${active_tab() === ActiveTab.Recorder ? "border-blue-400 text-blue-400" : "hover:bg-gray-50"}`}>Screen Record</button>
                     <button onClick={() => set_active_tab(ActiveTab.Transcribe)} class={`border-b py-1 px-2 w-full ${active_tab() === ActiveTab.Transcribe ? "border-blue-400 text-blue-400" : "hover:bg-gray-50"}`}>File Transcription/Subtitling</button>
+                    <button onClick={() => set_active_tab(ActiveTab.Subtitles)} class={`border-b py-1 px-2 w-full ${active_tab() === ActiveTab.Subtitles ? "border-blue-400 text-blue-400" : "hover:bg-gray-50"}`}>Edit Subtitles</button>
                     <button onClick={() => set_active_tab(ActiveTab.About)} class={`border-b py-1 px-2 w-full ${active_tab() === ActiveTab.About ? "border-blue-400 text-blue-400" : "hover:bg-gray-50"}`}>About</button>
                 </div>
             </div>
@@ -1168,6 +2601,9 @@ function App() {
                     <Match when={active_tab() === ActiveTab.Transcribe}>
                         <Transcribe />
                     </Match>
+                    <Match when={active_tab() === ActiveTab.Subtitles}>
+                        <SubtitleEditor />
+                    </Match>
                     <Match when={active_tab() === ActiveTab.About}>
                         <About />
                     </Match>
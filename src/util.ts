@@ -91,3 +91,20 @@ export function megabytes_to_jedec_string(megabytes: number): string {
     return `${size.toFixed(2)} ${units[unit_index]}`;
 }
 
+
+// `01:02:03.045` from milliseconds
+export function format_timestamp(milliseconds: number): string {
+    const pad = (value: number, length: number) => Math.floor(value).toString().padStart(length, "0");
+
+    return `${pad(milliseconds / 3_600_000, 2)}:${pad(milliseconds / 60_000 % 60, 2)}:${pad(milliseconds / 1000 % 60, 2)}.${pad(milliseconds % 1000, 3)}`;
+}
+
+// Milliseconds of `01:02:03.045`, `02:03,045` or `3.5`, null when it's not a timestamp
+export function parse_timestamp(text: string): number | null {
+    const match = /^(?:(?:(\d+):)?(\d{1,2}):)?(\d{1,2})(?:[.,](\d{1,3}))?$/.exec(text.trim());
+    if (match === null) return null;
+
+    const [, hours, minutes, seconds, fraction] = match;
+
+    return ((parseInt(hours ?? "0") * 60 + parseInt(minutes ?? "0")) * 60 + parseInt(seconds)) * 1000 + parseInt((fraction ?? "0").padEnd(3, "0"));
+}